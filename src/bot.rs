@@ -1,22 +1,59 @@
 use crate::game_info::GameInfo;
-use api::sr_libs::utils::card_templates::CardTemplate;
-use api::sr_libs::utils::card_templates::CardTemplate::*;
-use api::Upgrade::U3;
 use api::*;
 use log::{debug, info, warn};
-use std::borrow::Cow;
+use std::sync::OnceLock;
 
 use crate::command_scheduler::CommandScheduler;
-use crate::controller::macro_controller::MacroController;
+use crate::controller::macro_controller::{MacroController, MacroState};
+use crate::deck;
+use crate::replay::{ReplayRecord, ReplayRecorder, REPLAY_FILE_EXTENSION};
+use crate::strategy_config::StrategyConfig;
 
 // /AI: add SkylordsRebot ShadowFrost 4
 const NAME: &'static str = "SkylordsRebot";
 
+// set once at startup when matches should be recorded
+static RECORD_REPLAYS: OnceLock<bool> = OnceLock::new();
+
+pub fn enable_replay_recording() {
+    RECORD_REPLAYS.get_or_init(|| true);
+}
+
+// set once at startup when a strategy file is given, otherwise the defaults are used
+static STRATEGY_CONFIG: OnceLock<StrategyConfig> = OnceLock::new();
+
+pub fn set_strategy_config(config: StrategyConfig) {
+    STRATEGY_CONFIG.get_or_init(|| config);
+}
+
+pub fn strategy_config() -> StrategyConfig {
+    STRATEGY_CONFIG.get().cloned().unwrap_or_default()
+}
+
 pub struct SkylordsRebot {
     deck: &'static Deck,
     game_info: GameInfo,
     macro_controller: MacroController,
     command_scheduler: CommandScheduler,
+    replay_recorder: Option<ReplayRecorder>,
+}
+
+impl SkylordsRebot {
+    pub fn with_strategy(map_info: &MapInfo, deck: &'static Deck, config: &StrategyConfig) -> Self {
+        let mut game_info = GameInfo::new();
+        game_info.set_map(map_info);
+        SkylordsRebot {
+            deck,
+            game_info,
+            macro_controller: MacroController::new(config),
+            command_scheduler: CommandScheduler::new(config.command_scheduler.clone()),
+            replay_recorder: None,
+        }
+    }
+
+    pub fn macro_state(&self) -> &MacroState {
+        self.macro_controller.state()
+    }
 }
 
 impl warp_wrapper::BotImpl for SkylordsRebot {
@@ -25,17 +62,31 @@ impl warp_wrapper::BotImpl for SkylordsRebot {
     }
 
     fn decks_for_map(_map_info: &MapInfo) -> &'static [Deck] {
-        &[BOT_DECK]
+        std::slice::from_ref(&deck::bot_deck().deck)
     }
 
     fn prepare_for_battle(map_info: &MapInfo, deck: &'static Deck) -> Self {
         info!("Preparing for: {:?}?", map_info.map);
 
+        let mut replay_recorder = None;
+        if RECORD_REPLAYS.get().is_some_and(|&record| record) {
+            // use the same naming scheme as the log file
+            let file_name = chrono::Local::now().format("%Y-%m-%d_%H_%M_%S").to_string()
+                + REPLAY_FILE_EXTENSION;
+            replay_recorder = ReplayRecorder::create(file_name);
+        }
+        let config = strategy_config();
+        if let Some(recorder) = &mut replay_recorder {
+            recorder.record(&ReplayRecord::MapInfo(map_info.clone()));
+            recorder.record(&ReplayRecord::Strategy {
+                deck: deck.name.to_string(),
+                config: config.clone(),
+            });
+        }
+
         SkylordsRebot {
-            deck,
-            game_info: GameInfo::new(),
-            macro_controller: MacroController::new(),
-            command_scheduler: CommandScheduler::new(),
+            replay_recorder,
+            ..SkylordsRebot::with_strategy(map_info, deck, &config)
         }
     }
 
@@ -49,6 +100,9 @@ impl warp_wrapper::BotImpl for SkylordsRebot {
 }
 
 fn match_start(bot_state: &mut SkylordsRebot, state: GameStartState) {
+    if let Some(recorder) = &mut bot_state.replay_recorder {
+        recorder.record(&ReplayRecord::MatchStart(state.clone()));
+    }
     bot_state.game_info.init(state);
 }
 
@@ -56,6 +110,7 @@ fn on_tick(bot_state: &mut SkylordsRebot, state: GameState) -> Vec<Command> {
     if state.rejected_commands.len() > 0 {
         warn!("Rejected commands: {:?}", state.rejected_commands);
     }
+    let recorded_state = bot_state.replay_recorder.as_ref().map(|_| state.clone());
     bot_state.game_info.parse_state(state);
     bot_state
         .command_scheduler
@@ -71,66 +126,12 @@ fn on_tick(bot_state: &mut SkylordsRebot, state: GameState) -> Vec<Command> {
         debug!("Sending commands: {:?}", scheduled_commands);
     }
 
+    if let (Some(recorder), Some(state)) = (&mut bot_state.replay_recorder, recorded_state) {
+        recorder.record(&ReplayRecord::Tick {
+            state,
+            commands: scheduled_commands.clone(),
+        });
+    }
+
     scheduled_commands
 }
-
-pub const BOT_DECK: Deck = Deck {
-    name: Cow::Borrowed("ShadowFrost"),
-    cover_card_index: 0,
-    cards: [
-        CardId::new(Dreadcharger, U3),
-        CardId::new(Forsaken, U3),
-        CardId::new(NoxTrooper, U3),
-        CardId::new(Executor, U3),
-        CardId::new(Motivate, U3),
-        CardId::new(NastySurprise, U3),
-        CardId::new(LifeWeaving, U3),
-        CardId::new(FrostBiteAShadow, U3),
-        CardId::new(GlacierShell, U3),
-        CardId::new(KoboldTrick, U3),
-        CardId::new(DarkelfAssassins, U3),
-        CardId::new(Nightcrawler, U3),
-        CardId::new(StormsingerANature, U3),
-        CardId::new(LostReaverAShadow, U3),
-        CardId::new(Coldsnap, U3),
-        CardId::new(AuraofCorruption, U3),
-        CardId::new(TimelessOne, U3),
-        CardId::new(SilverwindLancers, U3),
-        CardId::new(Tremor, U3),
-        CardId::new(LostGrigoriAFire, U3),
-    ],
-};
-
-pub const BOT_CARDS: [CardTemplate; 20] = [
-    Dreadcharger,
-    Forsaken,
-    NoxTrooper,
-    Executor,
-    Motivate,
-    NastySurprise,
-    LifeWeaving,
-    FrostBiteAShadow,
-    GlacierShell,
-    KoboldTrick,
-    DarkelfAssassins,
-    Nightcrawler,
-    StormsingerANature,
-    LostReaverAShadow,
-    Coldsnap,
-    AuraofCorruption,
-    TimelessOne,
-    SilverwindLancers,
-    Tremor,
-    LostGrigoriAFire,
-];
-
-pub const BOT_ORBS: [CreateOrbColor; 3] = [
-    CreateOrbColor::Shadow,
-    CreateOrbColor::Frost,
-    CreateOrbColor::Frost,
-];
-
-// TODO: figure this out dynamically
-pub const BOT_T1_UNITS: [usize; 4] = [0, 1, 2, 3];
-pub const BOT_T2_UNITS: [usize; 4] = [10, 11, 12, 13];
-pub const BOT_T3_UNITS: [usize; 3] = [17, 18, 19];
@@ -1,29 +1,158 @@
 use api::sr_libs::utils::card_templates::CardTemplate;
 use api::*;
 use log::*;
+use serde::{de, Deserialize, Deserializer};
 use std::collections::BTreeMap;
-use std::env;
-use std::fs;
-use std::path;
 use std::str::FromStr;
 
 use crate::game_info::PlayerInfo;
 
-const CARD_INFO_FILE_PATH: &'static str = "data/cards.json";
+pub mod matchup;
+
+// Typed view of data/cards.json. The file is deserialised once and indexed by the official card
+// ids, integer coded fields are turned into the enums of the "enums" block of the file.
 
 pub struct CardData {
-    data: serde_json::Value,
-    card_info_cache: BTreeMap<u32, CardInfo>,
+    cards: Vec<CardEntry>,
+    // derived info of each card for every upgrade, in the same order as the cards
+    card_infos: Vec<[CardInfo; 4]>,
+    // position in cards for each official card id
+    card_index: BTreeMap<u32, usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardsFile {
+    enums: CardEnums,
+    data: Vec<CardEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CardEnums {
+    // number of charges per upgrade by the max charges of a card
+    max_charges: BTreeMap<String, [u32; 4]>,
+}
+
+// one card of cards.json, fields which are not needed by the bot are skipped
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardEntry {
+    pub card_slug: String,
+    pub card_name: String,
+    pub official_card_ids: Vec<u32>,
+    pub edition: Edition,
+    pub color: CardColor,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub affinity: Option<Affinity>, // None for cards without affinity
+    #[serde(rename = "type")]
+    pub card_type: CardType,
+    #[serde(deserialize_with = "deserialize_spell_class")]
+    pub spell_class: Option<SpellClass>,
+    pub orbs_total: i32,
+    pub orbs_neutral: i32,
+    pub orbs_fire: i32,
+    pub orbs_shadow: i32,
+    pub orbs_nature: i32,
+    pub orbs_frost: i32,
+    // hybrid orbs, which can be either of two colors, only used by a few T3 cards
+    pub orbs_fire_shadow: i32,
+    pub orbs_nature_frost: i32,
+    pub orbs_fire_nature: i32,
+    pub orbs_shadow_frost: i32,
+    pub orbs_shadow_nature: i32,
+    pub orbs_fire_frost: i32,
+    // the following are None for spells and buildings
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub movement_type: Option<MovementType>,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub attack_type: Option<AttackType>,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub offense_type: Option<CardOffenseType>,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub defense_type: Option<CardDefenseType>,
+    pub max_charges: u32,
+    pub squad_size: i32, // -1 for spells and 0 for buildings
+    // values per upgrade
+    pub power_cost: [f32; 4],
+    pub damage: [f32; 4],
+    pub health: [f32; 4],
+    pub abilities: Vec<AbilityEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbilityEntry {
+    pub ability_identifier: String,
+    pub ability_name: String,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub ability_type: Option<AbilityType>,
+    #[serde(deserialize_with = "deserialize_optional_index")]
+    pub ability_affinity: Option<Affinity>,
+    pub ability_hidden: bool,
+    pub ability_description: String, // values are marked with %s
+    // values per upgrade
+    pub ability_availability: [bool; 4],
+    pub ability_stars: [u32; 4],
+    pub ability_description_values: Vec<[String; 4]>,
+    pub ability_cost: [f32; 4],
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct CardOrbRequirements {
-    total: i32,
-    neutral: i32,
-    fire: i32,
-    shadow: i32,
-    nature: i32,
-    frost: i32,
+    pub total: i32,
+    pub neutral: i32,
+    pub fire: i32,
+    pub shadow: i32,
+    pub nature: i32,
+    pub frost: i32,
+    // one more orb of either color, included in the total
+    pub hybrid: Option<[OrbColor; 2]>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "i64")]
+pub enum Edition {
+    Twilight,
+    Renegade,
+    LostSouls,
+    Amii,
+    Rebirth,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "i64")]
+pub enum CardColor {
+    Neutral,
+    Fire,
+    Shadow,
+    Nature,
+    Frost,
+    Bandits,
+    Stonekin,
+    Twilight,
+    LostSouls,
+    Amii,
+    FireFrost,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Affinity {
+    Fire,
+    Shadow,
+    Nature,
+    Frost,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum MovementType {
+    Ground,
+    Flying,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum AttackType {
+    Melee,
+    Ranged,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
@@ -43,21 +172,72 @@ pub enum CardDefenseType {
     XL,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "i64")]
+pub enum CardType {
+    Unit,
+    Building,
+    Spell,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum SpellClass {
+    Spell,
+    Arcane,
+    Enchantment,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum AbilityType {
+    Passive,
+    Active,
+    Toggle,
+    Autocast,
+    Godspell,
+}
+
+#[derive(Debug, Clone)]
+pub struct AbilityInfo {
+    pub identifier: String,
+    pub name: String,
+    pub ability_type: Option<AbilityType>,
+    pub affinity: Option<Affinity>,
+    pub hidden: bool,
+    pub stars: u32,
+    pub description: String, // with the values of the upgrade filled in
+    pub values: Vec<String>,
+    pub power_cost: f32,
+}
+
+#[derive(Debug, Clone)]
 pub struct CardInfo {
     pub id: u32,
+    pub upgrade: Upgrade,
+    pub card_type: CardType,
+    pub spell_class: Option<SpellClass>, // None for units and buildings
     pub power_cost: f32,
     pub orb_requirements: CardOrbRequirements,
     pub offense_type: CardOffenseType,
     pub defense_type: CardDefenseType,
     pub melee: bool,
     pub siege: bool,
+    pub flying: bool,
+    pub movement_type: Option<MovementType>, // None for spells and buildings
+    pub damage: f32,                         // damage per 20 seconds
+    pub health: f32,                         // health of the whole squad
+    pub squad_size: u32,                     // 0 for spells and buildings
+    pub max_charges: u32,
+    // abilities available at the upgrade
+    pub abilities: Vec<AbilityInfo>,
 }
 
 impl CardInfo {
     pub fn new() -> CardInfo {
         CardInfo {
             id: 0,
+            upgrade: Upgrade::U3,
+            card_type: CardType::Unit,
+            spell_class: None,
             power_cost: 0.,
             orb_requirements: CardOrbRequirements {
                 total: 0,
@@ -66,117 +246,311 @@ impl CardInfo {
                 shadow: 0,
                 nature: 0,
                 frost: 0,
+                hybrid: None,
             },
             offense_type: CardOffenseType::S,
             defense_type: CardDefenseType::S,
             melee: false,
             siege: false,
+            flying: false,
+            movement_type: None,
+            damage: 0.,
+            health: 0.,
+            squad_size: 0,
+            max_charges: 0,
+            abilities: vec![],
         }
     }
 
-    pub fn from_card_json(card: &serde_json::Value) -> CardInfo {
+    pub fn from_card_entry(card: &CardEntry, upgrade: Upgrade, max_charges: u32) -> CardInfo {
+        let level = CardData::upgrade_level(upgrade);
         CardInfo {
-            id: CardInfo::get_card_id(card),
-            power_cost: CardInfo::get_card_power_cost(card),
-            orb_requirements: CardInfo::get_card_orbs(card),
-            offense_type: CardInfo::get_card_offense_type(card),
-            defense_type: CardInfo::get_card_defense_type(card),
-            melee: CardInfo::get_card_melee(card),
+            id: card.official_card_ids.first().copied().unwrap_or(0),
+            upgrade,
+            card_type: card.card_type,
+            spell_class: card.spell_class,
+            power_cost: card.power_cost[level],
+            orb_requirements: CardOrbRequirements {
+                total: card.orbs_total,
+                neutral: card.orbs_neutral,
+                fire: card.orbs_fire,
+                shadow: card.orbs_shadow,
+                nature: card.orbs_nature,
+                frost: card.orbs_frost,
+                hybrid: CardInfo::get_hybrid_orb(card),
+            },
+            // spells and buildings count as S
+            offense_type: card.offense_type.unwrap_or(CardOffenseType::S),
+            defense_type: card.defense_type.unwrap_or(CardDefenseType::S),
+            // spells and buildings count as melee
+            melee: card.attack_type != Some(AttackType::Ranged),
             siege: CardInfo::get_card_siege(card),
+            flying: card.movement_type == Some(MovementType::Flying),
+            movement_type: card.movement_type,
+            damage: card.damage[level],
+            health: card.health[level],
+            squad_size: card.squad_size.max(0) as u32,
+            max_charges,
+            abilities: card
+                .abilities
+                .iter()
+                .filter(|ability| ability.ability_availability[level])
+                .map(|ability| AbilityInfo::from_ability_entry(ability, level))
+                .collect(),
         }
     }
 
-    fn get_card_id(card: &serde_json::Value) -> u32 {
-        let ids = card["officialCardIds"].as_array().unwrap();
+    pub fn dps(&self) -> f32 {
+        // damage is given per 20 seconds
+        self.damage / 20.
+    }
 
-        if ids.len() == 0 {
-            error!("Unable to find CardId for {card:?}");
-            0
-        } else if ids.len() > 1 {
-            warn!("Got more than one CardId for {card:?}");
-            0
+    pub fn dps_per_power(&self) -> f32 {
+        if self.power_cost > 0. {
+            self.dps() / self.power_cost
         } else {
-            ids.get(0).unwrap().as_i64().unwrap() as u32
+            0.
         }
     }
 
-    fn get_card_power_cost(card: &serde_json::Value) -> f32 {
-        card["powerCost"].as_array().unwrap()[3].as_f64().unwrap() as f32
+    pub fn hp_per_power(&self) -> f32 {
+        if self.power_cost > 0. {
+            self.health / self.power_cost
+        } else {
+            0.
+        }
     }
 
-    fn get_card_orbs(card: &serde_json::Value) -> CardOrbRequirements {
-        CardOrbRequirements {
-            total: card["orbsTotal"].as_i64().unwrap() as i32,
-            neutral: card["orbsNeutral"].as_i64().unwrap() as i32,
-            fire: card["orbsFire"].as_i64().unwrap() as i32,
-            shadow: card["orbsShadow"].as_i64().unwrap() as i32,
-            nature: card["orbsNature"].as_i64().unwrap() as i32,
-            frost: card["orbsFrost"].as_i64().unwrap() as i32,
+    pub fn has_ability(&self, identifier: &str) -> bool {
+        self.abilities
+            .iter()
+            .any(|ability| ability.identifier == identifier)
+    }
+
+    fn get_hybrid_orb(card: &CardEntry) -> Option<[OrbColor; 2]> {
+        // no card requires more than one hybrid orb
+        let hybrid_orbs = [
+            (card.orbs_fire_shadow, [OrbColor::Fire, OrbColor::Shadow]),
+            (card.orbs_nature_frost, [OrbColor::Nature, OrbColor::Frost]),
+            (card.orbs_fire_nature, [OrbColor::Fire, OrbColor::Nature]),
+            (card.orbs_shadow_frost, [OrbColor::Shadow, OrbColor::Frost]),
+            (
+                card.orbs_shadow_nature,
+                [OrbColor::Shadow, OrbColor::Nature],
+            ),
+            (card.orbs_fire_frost, [OrbColor::Fire, OrbColor::Frost]),
+        ];
+        hybrid_orbs
+            .into_iter()
+            .find(|(num, _)| *num > 0)
+            .map(|(_, colors)| colors)
+    }
+
+    fn get_card_siege(card: &CardEntry) -> bool {
+        if card.card_slug.to_lowercase() == "sunstriders" {
+            // Sunstriders also have an ability called "Siege", but it only adds a fixed
+            // 1 damage to it's attack. We only care about Siege multipliers (like Firedancer),
+            // so ignore this one.
+            return true;
+        }
+
+        card.abilities
+            .iter()
+            .any(|ability| ability.ability_name == "Siege")
+    }
+}
+
+impl AbilityInfo {
+    pub fn from_ability_entry(ability: &AbilityEntry, level: usize) -> AbilityInfo {
+        let values: Vec<String> = ability
+            .ability_description_values
+            .iter()
+            .map(|values| values[level].clone())
+            .collect();
+
+        // fill in the values in their order
+        let mut description = String::new();
+        let mut parts = ability.ability_description.split("%s");
+        description.push_str(parts.next().unwrap_or_default());
+        for (index, part) in parts.enumerate() {
+            description.push_str(values.get(index).map_or("?", |value| value.as_str()));
+            description.push_str(part);
+        }
+
+        AbilityInfo {
+            identifier: ability.ability_identifier.clone(),
+            name: ability.ability_name.clone(),
+            ability_type: ability.ability_type,
+            affinity: ability.ability_affinity,
+            hidden: ability.ability_hidden,
+            stars: ability.ability_stars[level],
+            description,
+            values,
+            power_cost: ability.ability_cost[level],
         }
     }
+}
 
-    fn get_card_offense_type(card: &serde_json::Value) -> CardOffenseType {
-        let index = card["offenseType"].as_i64().unwrap();
+impl TryFrom<i64> for Edition {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<Edition, Self::Error> {
         match index {
-            0 => CardOffenseType::S,
-            1 => CardOffenseType::M,
-            2 => CardOffenseType::L,
-            3 => CardOffenseType::XL,
-            4 => CardOffenseType::Special,
-            _ => {
-                error!("Unable to find CardOffenseType for index {index:?}");
-                CardOffenseType::S
-            }
+            0 => Ok(Edition::Twilight),
+            1 => Ok(Edition::Renegade),
+            2 => Ok(Edition::LostSouls),
+            3 => Ok(Edition::Amii),
+            4 => Ok(Edition::Rebirth),
+            _ => Err(format!("Unknown edition {index}")),
         }
     }
+}
+
+impl TryFrom<i64> for CardColor {
+    type Error = String;
 
-    fn get_card_defense_type(card: &serde_json::Value) -> CardDefenseType {
-        let index = card["defenseType"].as_i64().unwrap();
+    fn try_from(index: i64) -> Result<CardColor, Self::Error> {
         match index {
-            0 => CardDefenseType::S,
-            1 => CardDefenseType::M,
-            2 => CardDefenseType::L,
-            3 => CardDefenseType::XL,
-            _ => {
-                error!("Unable to find CardDefenseType for index {index:?}");
-                CardDefenseType::S
-            }
+            -1 => Ok(CardColor::Neutral),
+            0 => Ok(CardColor::Fire),
+            1 => Ok(CardColor::Shadow),
+            2 => Ok(CardColor::Nature),
+            3 => Ok(CardColor::Frost),
+            4 => Ok(CardColor::Bandits),
+            5 => Ok(CardColor::Stonekin),
+            6 => Ok(CardColor::Twilight),
+            7 => Ok(CardColor::LostSouls),
+            8 => Ok(CardColor::Amii),
+            9 => Ok(CardColor::FireFrost),
+            _ => Err(format!("Unknown color {index}")),
         }
     }
+}
 
-    fn get_card_siege(card: &serde_json::Value) -> bool {
-        let card_name = card.get("cardSlug").unwrap().as_str().unwrap();
-        if card_name.to_lowercase() == "sunstriders" {
-            // Sunstriders also have an ability called "Siege", but it only adds a fixed
-            // 1 damage to it's attack. We only care about Siege multipliers (like Firedancer),
-            // so ignore this one.
-            return true;
+impl TryFrom<i64> for Affinity {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<Affinity, Self::Error> {
+        match index {
+            0 => Ok(Affinity::Fire),
+            1 => Ok(Affinity::Shadow),
+            2 => Ok(Affinity::Nature),
+            3 => Ok(Affinity::Frost),
+            _ => Err(format!("Unknown affinity {index}")),
+        }
+    }
+}
+
+impl TryFrom<i64> for CardType {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<CardType, Self::Error> {
+        match index {
+            0 => Ok(CardType::Unit),
+            1 => Ok(CardType::Building),
+            2 => Ok(CardType::Spell),
+            _ => Err(format!("Unknown card type {index}")),
         }
+    }
+}
 
-        for ability in card.get("abilities").unwrap().as_array().unwrap() {
-            let ability_name = ability.get("abilityName").unwrap().as_str().unwrap();
-            if ability_name == "Siege" {
-                return true;
-            }
+impl TryFrom<i64> for MovementType {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<MovementType, Self::Error> {
+        match index {
+            0 => Ok(MovementType::Ground),
+            1 => Ok(MovementType::Flying),
+            _ => Err(format!("Unknown movement type {index}")),
         }
+    }
+}
+
+impl TryFrom<i64> for AttackType {
+    type Error = String;
 
-        false
+    fn try_from(index: i64) -> Result<AttackType, Self::Error> {
+        match index {
+            0 => Ok(AttackType::Melee),
+            1 => Ok(AttackType::Ranged),
+            _ => Err(format!("Unknown attack type {index}")),
+        }
     }
+}
 
-    fn get_card_melee(card: &serde_json::Value) -> bool {
-        let attack_type = card.get("attackType").unwrap().as_i64().unwrap();
-        match attack_type {
-            0 => return true,
-            1 => return false,
-            _ => {
-                let card_name = card.get("cardSlug").unwrap().as_str().unwrap();
-                error!("Unable to evaluate attack type {attack_type:?} for card {card_name:?}");
-                return true;
-            }
+impl TryFrom<i64> for AbilityType {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<AbilityType, Self::Error> {
+        match index {
+            0 => Ok(AbilityType::Passive),
+            1 => Ok(AbilityType::Active),
+            2 => Ok(AbilityType::Toggle),
+            3 => Ok(AbilityType::Autocast),
+            4 => Ok(AbilityType::Godspell),
+            _ => Err(format!("Unknown ability type {index}")),
         }
     }
 }
 
+impl TryFrom<i64> for CardOffenseType {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<CardOffenseType, Self::Error> {
+        match index {
+            0 => Ok(CardOffenseType::S),
+            1 => Ok(CardOffenseType::M),
+            2 => Ok(CardOffenseType::L),
+            3 => Ok(CardOffenseType::XL),
+            4 => Ok(CardOffenseType::Special),
+            _ => Err(format!("Unknown offense type {index}")),
+        }
+    }
+}
+
+impl TryFrom<i64> for CardDefenseType {
+    type Error = String;
+
+    fn try_from(index: i64) -> Result<CardDefenseType, Self::Error> {
+        match index {
+            0 => Ok(CardDefenseType::S),
+            1 => Ok(CardDefenseType::M),
+            2 => Ok(CardDefenseType::L),
+            3 => Ok(CardDefenseType::XL),
+            _ => Err(format!("Unknown defense type {index}")),
+        }
+    }
+}
+
+fn deserialize_optional_index<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<i64, Error = String>,
+{
+    // -1 marks values which don't apply to the card, e.g. the attack type of a spell
+    let index = i64::deserialize(deserializer)?;
+    if index == -1 {
+        return Ok(None);
+    }
+    T::try_from(index).map(Some).map_err(de::Error::custom)
+}
+
+fn deserialize_spell_class<'de, D>(deserializer: D) -> Result<Option<SpellClass>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // "~" for units and buildings
+    match String::deserialize(deserializer)?.as_str() {
+        "Spell" => Ok(Some(SpellClass::Spell)),
+        "Arcane" => Ok(Some(SpellClass::Arcane)),
+        "Enchantment" => Ok(Some(SpellClass::Enchantment)),
+        "~" => Ok(None),
+        spell_class => Err(de::Error::custom(format!(
+            "Unknown spell class {spell_class}"
+        ))),
+    }
+}
+
 impl FromStr for CardOffenseType {
     type Err = ();
 
@@ -232,70 +606,125 @@ impl ToString for CardDefenseType {
 impl CardData {
     pub fn new() -> Self {
         CardData {
-            data: serde_json::Value::Null,
-            card_info_cache: BTreeMap::new(),
+            cards: vec![],
+            card_infos: vec![],
+            card_index: BTreeMap::new(),
         }
     }
 
-    pub fn get_card_info_from_id(&mut self, card_id: u32) -> CardInfo {
-        if let Some(card_info) = self.card_info_cache.get(&card_id) {
-            return *card_info;
-        }
+    pub fn get_card_info_from_id(&self, card_id: u32) -> Result<CardInfo, String> {
+        // ids without an upgrade, e.g. the ones of decks, are taken as fully upgraded
+        let upgrade = match card_id / (Upgrade::U1 as u32) {
+            1 => Upgrade::U1,
+            2 => Upgrade::U2,
+            _ => Upgrade::U3,
+        };
+        self.get_card_info_with_upgrade(CardData::card_id_without_upgrade(card_id), upgrade)
+    }
 
-        let card_option = self.get_card_from_id(card_id);
-        if let Some(card) = card_option {
-            let card_info = CardInfo::from_card_json(card);
-            self.card_info_cache.insert(card_id, card_info);
-            card_info
-        } else {
-            CardInfo::new()
-        }
+    pub fn get_card_info(&self, card: &CardTemplate) -> Result<CardInfo, String> {
+        self.get_card_info_with_upgrade(card.id(), Upgrade::U3)
+    }
+
+    pub fn get_card_info_with_upgrade(
+        &self,
+        card_id: u32,
+        upgrade: Upgrade,
+    ) -> Result<CardInfo, String> {
+        let index = self.get_card_index(card_id)?;
+        let mut card_info = self.card_infos[index][CardData::upgrade_level(upgrade)].clone();
+        // a few cards have more than one official id
+        card_info.id = card_id;
+        Ok(card_info)
+    }
+
+    pub fn get_cards(&self, upgrade: Upgrade) -> impl Iterator<Item = (&CardEntry, &CardInfo)> {
+        // every card of cards.json with its info at the upgrade
+        let level = CardData::upgrade_level(upgrade);
+        self.cards
+            .iter()
+            .zip(self.card_infos.iter().map(move |infos| &infos[level]))
     }
 
     pub fn card_id_without_upgrade(id: u32) -> u32 {
-        if id >= (Upgrade::U3 as u32) {
-            return id - (Upgrade::U3 as u32);
-        }
-        id
+        id % (Upgrade::U1 as u32)
     }
 
-    fn get_card_from_id(&self, card_id: u32) -> Option<&serde_json::Value> {
-        for card in self.data["data"].as_array().unwrap() {
-            let ids = card["officialCardIds"].as_array().unwrap();
-            for id in ids {
-                if id.as_i64().unwrap() as u32 == CardData::card_id_without_upgrade(card_id) {
-                    return Some(card);
-                }
-            }
-        }
+    pub fn upgrade_level(upgrade: Upgrade) -> usize {
+        // index of the upgrade in the values of a card
+        (upgrade as u32 / Upgrade::U1 as u32) as usize
+    }
 
-        error!("Unable to find card with ID {card_id:?}");
-        None
+    fn get_card_index(&self, card_id: u32) -> Result<usize, String> {
+        self.card_index
+            .get(&CardData::card_id_without_upgrade(card_id))
+            .copied()
+            .ok_or_else(|| format!("Unable to find card with ID {card_id:?}"))
     }
 
     pub fn load(&mut self) {
         debug!("Loading card data");
 
-        self.data = serde_json::from_str(include_str!("../data/cards.json"))
+        let cards_file: CardsFile = serde_json::from_str(include_str!("../data/cards.json"))
             .expect("Unable to parse cards.json file");
+        self.cards = cards_file.data;
+        let upgrades = [Upgrade::U0, Upgrade::U1, Upgrade::U2, Upgrade::U3];
+        let max_charges = &cards_file.enums.max_charges;
+        self.card_infos = self
+            .cards
+            .iter()
+            .map(|card| {
+                // the number of charges of a card depends on its upgrade
+                let charges = max_charges
+                    .get(&card.max_charges.to_string())
+                    .copied()
+                    .unwrap_or([card.max_charges; 4]);
+                upgrades.map(|upgrade| {
+                    let level = CardData::upgrade_level(upgrade);
+                    CardInfo::from_card_entry(card, upgrade, charges[level])
+                })
+            })
+            .collect();
+        self.card_index.clear();
+        for (index, card) in self.cards.iter().enumerate() {
+            if card.official_card_ids.is_empty() {
+                warn!("Card {} has no official id", card.card_slug);
+            }
+            for card_id in card.official_card_ids.iter() {
+                self.card_index.insert(*card_id, index);
+            }
+        }
 
-        debug!("Finished loading card data");
+        debug!("Finished loading {} cards", self.cards.len());
     }
 
     pub fn player_fullfills_orb_requirements(
-        &mut self,
+        &self,
         card_template: &CardTemplate,
         player_info: &PlayerInfo,
     ) -> bool {
-        let orb_requirements = self
-            .get_card_info_from_id(card_template.id())
-            .orb_requirements;
+        let orb_colors: Vec<OrbColor> = player_info
+            .token_slots
+            .values()
+            .map(|token_slot| token_slot.color)
+            .collect();
+        self.orb_colors_fullfill_requirements(card_template.id(), &orb_colors)
+    }
+
+    pub fn orb_colors_fullfill_requirements(&self, card_id: u32, orb_colors: &[OrbColor]) -> bool {
+        let orb_requirements = match self.get_card_info_from_id(card_id) {
+            Ok(card_info) => card_info.orb_requirements,
+            Err(err) => {
+                error!("{err}");
+                return false;
+            }
+        };
 
         // fire, shadow, nature, frost
         let mut num_colors: Vec<i32> = vec![0; 4];
         let mut has_starting_orb = false;
-        for token_slot in player_info.token_slots.values() {
-            match token_slot.color {
+        for orb_color in orb_colors {
+            match orb_color {
                 OrbColor::Fire => num_colors[0] += 1,
                 OrbColor::Shadow => num_colors[1] += 1,
                 OrbColor::Nature => num_colors[2] += 1,
@@ -321,7 +750,114 @@ impl CardData {
             return false;
         }
 
+        // the hybrid orb takes one of the remaining orbs of either color
+        if let Some(hybrid) = orb_requirements.hybrid {
+            let index = |color| match color {
+                OrbColor::Fire => 0,
+                OrbColor::Shadow => 1,
+                OrbColor::Nature => 2,
+                _ => 3,
+            };
+            match hybrid.into_iter().map(index).find(|&i| num_colors[i] > 0) {
+                Some(i) => num_colors[i] -= 1,
+                None => return false,
+            }
+        }
+
         // check if any possible neutral requirements can be fullfilled
         num_colors.iter().sum::<i32>() >= orb_requirements.neutral
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck_analysis;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn lookup_by_id_and_template() {
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        let card_info = card_data.get_card_info(&Dreadcharger).unwrap();
+        assert_eq!(card_info.id, Dreadcharger.id());
+        assert_eq!(card_info.card_type, CardType::Unit);
+        assert!(card_info.melee);
+
+        // upgrades share the card
+        let upgraded_id = CardId::new(Dreadcharger, Upgrade::U3).0;
+        let upgraded = card_data.get_card_info_from_id(upgraded_id).unwrap();
+        assert_eq!(upgraded.id, Dreadcharger.id());
+
+        // both ids of a card with two official ids are indexed
+        assert_eq!(card_data.get_card_info_from_id(729).unwrap().id, 729);
+        assert_eq!(card_data.get_card_info_from_id(1664).unwrap().id, 1664);
+
+        assert!(card_data.get_card_info_from_id(1).is_err());
+    }
+
+    #[test]
+    fn values_per_upgrade() {
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        let u0 = card_data
+            .get_card_info_with_upgrade(Dreadcharger.id(), Upgrade::U0)
+            .unwrap();
+        let u3 = card_data.get_card_info(&Dreadcharger).unwrap();
+        assert_eq!(u0.health, 520.);
+        assert_eq!(u3.health, 660.);
+        assert_eq!(u0.max_charges, 8);
+        assert_eq!(u3.max_charges, 16);
+        assert_eq!(u3.dps(), 30.);
+        assert_eq!(u3.dps_per_power(), 0.5);
+        assert_eq!(u3.hp_per_power(), 11.);
+
+        // The Reaping is only available from U1 on
+        assert!(!u0.has_ability("The Reaping"));
+        let reaping = u3
+            .abilities
+            .iter()
+            .find(|ability| ability.identifier == "The Reaping")
+            .unwrap();
+        assert_eq!(reaping.ability_type, Some(AbilityType::Active));
+        assert!(reaping.description.contains("A total of 3000 life points"));
+
+        let u1_id = CardId::new(Dreadcharger, Upgrade::U1).0;
+        let u1 = card_data.get_card_info_from_id(u1_id).unwrap();
+        assert_eq!(u1.upgrade, Upgrade::U1);
+        assert_eq!(u1.health, 550.);
+    }
+
+    #[test]
+    fn hybrid_orb_requirements() {
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        // Twilight Hag needs Fire, Nature and one more orb of either
+        let (_, twilight_hag) = card_data
+            .get_cards(Upgrade::U3)
+            .find(|(entry, _)| entry.card_name == "Twilight Hag [G]")
+            .unwrap();
+        let requirements = twilight_hag.orb_requirements;
+        assert_eq!(requirements.total, 3);
+        assert_eq!(requirements.neutral, 0);
+        assert_eq!(
+            requirements.hybrid,
+            Some([OrbColor::Fire, OrbColor::Nature])
+        );
+
+        let id = twilight_hag.id;
+        let fire_nature_frost = [OrbColor::Fire, OrbColor::Nature, OrbColor::Frost];
+        let fire_nature_nature = [OrbColor::Fire, OrbColor::Nature, OrbColor::Nature];
+        assert!(!card_data.orb_colors_fullfill_requirements(id, &fire_nature_frost));
+        assert!(card_data.orb_colors_fullfill_requirements(id, &fire_nature_nature));
+
+        let missing = deck_analysis::get_missing_orbs(&requirements, &fire_nature_frost);
+        assert_eq!(missing.total, 1);
+        assert_eq!(missing.hybrid, requirements.hybrid);
+        let missing = deck_analysis::get_missing_orbs(&requirements, &fire_nature_nature);
+        assert_eq!(missing.total, 0);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::card_data::{CardDefenseType, CardInfo, CardOffenseType};
+
+// Scores how well a card fares against another one. Both sides spend the same amount of power and
+// the score compares their fighting strength, which takes the size counters, melee against ranged
+// and flying units as well as the damage and health per power into account.
+
+// bonus damage when the offense type matches the defense type of the target
+pub const COUNTER_DAMAGE_MULTIPLIER: f32 = 1.5;
+// bonus damage of siege units against structures
+pub const SIEGE_DAMAGE_MULTIPLIER: f32 = 2.;
+// ranged units deal damage while melee units are still closing in
+const RANGED_ADVANTAGE: f32 = 1.25;
+// limit of the score, e.g. for units which can't attack the other one at all
+const MAX_SCORE: f32 = 5.;
+
+pub fn counters(offense_type: CardOffenseType, defense_type: CardDefenseType) -> bool {
+    matches!(
+        (offense_type, defense_type),
+        (CardOffenseType::S, CardDefenseType::S)
+            | (CardOffenseType::M, CardDefenseType::M)
+            | (CardOffenseType::L, CardDefenseType::L)
+            | (CardOffenseType::XL, CardDefenseType::XL)
+    )
+}
+
+pub fn get_damage_multiplier(attacker: &CardInfo, target: &CardInfo) -> f32 {
+    if attacker.melee && target.flying {
+        // melee units can't reach flying units
+        return 0.;
+    }
+    if counters(attacker.offense_type, target.defense_type) {
+        COUNTER_DAMAGE_MULTIPLIER
+    } else {
+        1.
+    }
+}
+
+pub fn get_structure_damage_multiplier(attacker: &CardInfo) -> f32 {
+    if attacker.siege {
+        SIEGE_DAMAGE_MULTIPLIER
+    } else {
+        1.
+    }
+}
+
+pub fn get_effective_dps(attacker: &CardInfo, target: &CardInfo) -> f32 {
+    let mut dps = attacker.dps() * get_damage_multiplier(attacker, target);
+    if !attacker.melee && target.melee && !target.flying {
+        dps *= RANGED_ADVANTAGE;
+    }
+    dps
+}
+
+pub fn get_matchup_score(card: &CardInfo, opponent: &CardInfo) -> f32 {
+    // positive if the card wins, 0 for an even fight and the negated score for the opponent
+    let strength = get_strength(card, opponent);
+    let opponent_strength = get_strength(opponent, card);
+    match (strength > 0., opponent_strength > 0.) {
+        (false, false) => 0.,
+        (true, false) => MAX_SCORE,
+        (false, true) => -MAX_SCORE,
+        (true, true) => (strength / opponent_strength)
+            .ln()
+            .clamp(-MAX_SCORE, MAX_SCORE),
+    }
+}
+
+pub fn get_army_matchup_score(card: &CardInfo, army: &[CardInfo]) -> f32 {
+    // average score against the squads of an army, 0 for an empty army
+    if army.is_empty() {
+        return 0.;
+    }
+    let total: f32 = army
+        .iter()
+        .map(|opponent| get_matchup_score(card, opponent))
+        .sum();
+    total / army.len() as f32
+}
+
+fn get_strength(card: &CardInfo, opponent: &CardInfo) -> f32 {
+    // the strength of a group grows with the square of its size (Lanchester), so for the same
+    // power it is the product of the damage and health per power
+    if card.power_cost <= 0. {
+        return 0.;
+    }
+    let dps_per_power = get_effective_dps(card, opponent) / card.power_cost;
+    dps_per_power * card.hp_per_power()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_data::CardData;
+    use api::sr_libs::utils::card_templates::CardTemplate;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    fn card_info(card: CardTemplate) -> CardInfo {
+        let mut card_data = CardData::new();
+        card_data.load();
+        card_data.get_card_info(&card).unwrap()
+    }
+
+    #[test]
+    fn counters_and_ranged_units_win() {
+        let dreadcharger = card_info(Dreadcharger);
+        let forsaken = card_info(Forsaken);
+        let nightcrawler = card_info(Nightcrawler);
+
+        // both counter each other, but Forsaken is ranged
+        assert!(get_matchup_score(&forsaken, &dreadcharger) > 0.);
+        let sum = get_matchup_score(&forsaken, &dreadcharger)
+            + get_matchup_score(&dreadcharger, &forsaken);
+        assert!(sum.abs() < 1e-5);
+        // Nightcrawler counters the M defense of Dreadcharger
+        assert!(get_matchup_score(&nightcrawler, &dreadcharger) > 0.);
+        assert_eq!(get_matchup_score(&dreadcharger, &dreadcharger), 0.);
+    }
+
+    #[test]
+    fn melee_units_can_not_attack_flying_units() {
+        let dreadcharger = card_info(Dreadcharger);
+        let mut flying = card_info(Forsaken);
+        flying.flying = true;
+
+        assert_eq!(get_damage_multiplier(&dreadcharger, &flying), 0.);
+        assert_eq!(get_matchup_score(&flying, &dreadcharger), MAX_SCORE);
+        assert_eq!(
+            get_army_matchup_score(&dreadcharger, &[flying, card_info(Dreadcharger)]),
+            -MAX_SCORE / 2.
+        );
+    }
+
+    #[test]
+    fn siege_units_deal_more_damage_to_structures() {
+        assert_eq!(
+            get_structure_damage_multiplier(&card_info(Firedancer)),
+            SIEGE_DAMAGE_MULTIPLIER
+        );
+        assert_eq!(
+            get_structure_damage_multiplier(&card_info(Dreadcharger)),
+            1.
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use api::*;
+use serde_json::json;
+
+use crate::card_data::{matchup, CardData, CardEntry, CardInfo};
+use crate::deck;
+
+// Searches the card database for the cards subcommand, e.g. the T2 Frost units with M offense
+// or the cards of the bot's deck which counter Sunstriders. Text filters are compared
+// case-insensitively to the names of the card data enums.
+
+#[derive(Debug)]
+pub struct CardQuery {
+    pub id: Option<u32>,
+    // part of the card name
+    pub name: Option<String>,
+    pub tier: Option<i32>,
+    pub color: Option<String>,
+    pub card_type: Option<String>,
+    pub offense: Option<String>,
+    pub defense: Option<String>,
+    // name of a card the results have to win the matchup against
+    pub counters: Option<String>,
+    // only cards of the bot's deck
+    pub deck: bool,
+    pub upgrade: Upgrade,
+}
+
+pub struct QueryResult<'a> {
+    pub entry: &'a CardEntry,
+    pub info: CardInfo,
+    // matchup score against the card of the counters filter
+    pub score: Option<f32>,
+}
+
+pub fn run_query(query: &CardQuery, as_json: bool) -> Result<(), String> {
+    let mut card_data = CardData::new();
+    card_data.load();
+
+    let results = find_cards(&card_data, query)?;
+    if as_json {
+        let cards: Vec<serde_json::Value> = results.iter().map(to_json).collect();
+        let output = serde_json::to_string_pretty(&cards).map_err(|err| err.to_string())?;
+        println!("{output}");
+    } else {
+        print_table(&results);
+    }
+    Ok(())
+}
+
+pub fn find_cards<'a>(
+    card_data: &'a CardData,
+    query: &CardQuery,
+) -> Result<Vec<QueryResult<'a>>, String> {
+    let target = match &query.counters {
+        Some(name) => Some(find_card_by_name(card_data, name, query.upgrade)?),
+        None => None,
+    };
+    let deck_ids: Vec<u32> = deck::bot_deck()
+        .cards
+        .iter()
+        .map(|card| card.id())
+        .collect();
+
+    let mut results = vec![];
+    for (entry, info) in card_data.get_cards(query.upgrade) {
+        // cards without an official id can't be played
+        if entry.official_card_ids.is_empty() {
+            continue;
+        }
+        if let Some(id) = query.id {
+            if !entry
+                .official_card_ids
+                .contains(&CardData::card_id_without_upgrade(id))
+            {
+                continue;
+            }
+        }
+        if let Some(name) = &query.name {
+            if !entry
+                .card_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+            {
+                continue;
+            }
+        }
+        if query.tier.is_some_and(|tier| tier != entry.orbs_total) {
+            continue;
+        }
+        if !matches_filter(&query.color, Some(entry.color))
+            || !matches_filter(&query.card_type, Some(entry.card_type))
+            || !matches_filter(&query.offense, entry.offense_type)
+            || !matches_filter(&query.defense, entry.defense_type)
+        {
+            continue;
+        }
+        if query.deck
+            && !entry
+                .official_card_ids
+                .iter()
+                .any(|id| deck_ids.contains(id))
+        {
+            continue;
+        }
+
+        let info = info.clone();
+        let score = target
+            .as_ref()
+            .map(|target| matchup::get_matchup_score(&info, target));
+        if score.is_some_and(|score| score <= 0.) {
+            continue;
+        }
+        results.push(QueryResult { entry, info, score });
+    }
+
+    if target.is_some() {
+        // best counters first, sort_by is stable so equal scores keep the order of the file
+        results.sort_by(|a, b| b.score.unwrap().total_cmp(&a.score.unwrap()));
+    }
+    Ok(results)
+}
+
+fn find_card_by_name(
+    card_data: &CardData,
+    name: &str,
+    upgrade: Upgrade,
+) -> Result<CardInfo, String> {
+    // exact names first, as many names are part of other ones
+    let cards: Vec<(&CardEntry, &CardInfo)> = card_data.get_cards(upgrade).collect();
+    let exact = cards
+        .iter()
+        .find(|(entry, _)| entry.card_name.eq_ignore_ascii_case(name));
+    if let Some((_, info)) = exact {
+        return Ok((*info).clone());
+    }
+
+    let matching: Vec<&(&CardEntry, &CardInfo)> = cards
+        .iter()
+        .filter(|(entry, _)| {
+            entry
+                .card_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        })
+        .collect();
+    match matching.as_slice() {
+        [(_, info)] => Ok((*info).clone()),
+        [] => Err(format!("No card named {name:?}")),
+        _ => Err(format!(
+            "{name:?} matches several cards: {:?}",
+            matching
+                .iter()
+                .map(|(entry, _)| entry.card_name.as_str())
+                .collect::<Vec<_>>()
+        )),
+    }
+}
+
+fn matches_filter<T: std::fmt::Debug>(filter: &Option<String>, value: Option<T>) -> bool {
+    match (filter, value) {
+        (None, _) => true,
+        (Some(filter), Some(value)) => format!("{value:?}").eq_ignore_ascii_case(filter),
+        // e.g. the offense type of a spell
+        (Some(_), None) => false,
+    }
+}
+
+fn print_table(results: &[QueryResult]) {
+    println!(
+        "{:<8} {:<32} {:<10} {:<9} {:>4} {:>6} {:>7} {:>7} {:>6} {:>5} {:<10} {:>6}",
+        "Id",
+        "Name",
+        "Color",
+        "Type",
+        "Orbs",
+        "Power",
+        "Offense",
+        "Defense",
+        "Health",
+        "DPS",
+        "Attack",
+        "Score"
+    );
+    for result in results.iter() {
+        let (entry, info) = (result.entry, &result.info);
+        let attack = match (entry.attack_type, info.flying) {
+            (None, _) => String::new(),
+            (Some(attack_type), true) => format!("{attack_type:?}, fly"),
+            (Some(attack_type), false) => format!("{attack_type:?}"),
+        };
+        println!(
+            "{:<8} {:<32} {:<10} {:<9} {:>4} {:>6.0} {:>7} {:>7} {:>6.0} {:>5.1} {:<10} {:>6}",
+            info.id,
+            entry.card_name,
+            format!("{:?}", entry.color),
+            format!("{:?}", entry.card_type),
+            entry.orbs_total,
+            info.power_cost,
+            entry.offense_type.map_or(String::new(), |o| o.to_string()),
+            entry.defense_type.map_or(String::new(), |d| d.to_string()),
+            info.health,
+            info.dps(),
+            attack,
+            result
+                .score
+                .map_or(String::new(), |score| format!("{score:.2}"))
+        );
+    }
+
+    // the abilities only fit for a single card
+    if let [result] = results {
+        for ability in result.info.abilities.iter().filter(|a| !a.hidden) {
+            println!();
+            println!(
+                "{} ({:?}): {}",
+                ability.name, ability.ability_type, ability.description
+            );
+        }
+    }
+}
+
+fn to_json(result: &QueryResult) -> serde_json::Value {
+    let (entry, info) = (result.entry, &result.info);
+    let abilities: Vec<serde_json::Value> = info
+        .abilities
+        .iter()
+        .map(|ability| {
+            json!({
+                "identifier": ability.identifier,
+                "name": ability.name,
+                "type": ability.ability_type.map(|t| format!("{t:?}")),
+                "affinity": ability.affinity.map(|a| format!("{a:?}")),
+                "hidden": ability.hidden,
+                "stars": ability.stars,
+                "description": ability.description,
+                "values": ability.values,
+                "powerCost": ability.power_cost,
+            })
+        })
+        .collect();
+    let orbs = &info.orb_requirements;
+
+    json!({
+        "id": info.id,
+        "officialCardIds": entry.official_card_ids,
+        "slug": entry.card_slug,
+        "name": entry.card_name,
+        "upgrade": CardData::upgrade_level(info.upgrade),
+        "edition": format!("{:?}", entry.edition),
+        "color": format!("{:?}", entry.color),
+        "affinity": entry.affinity.map(|a| format!("{a:?}")),
+        "type": format!("{:?}", entry.card_type),
+        "spellClass": info.spell_class.map(|s| format!("{s:?}")),
+        "orbs": {
+            "total": orbs.total,
+            "neutral": orbs.neutral,
+            "fire": orbs.fire,
+            "shadow": orbs.shadow,
+            "nature": orbs.nature,
+            "frost": orbs.frost,
+        },
+        "powerCost": info.power_cost,
+        "movementType": info.movement_type.map(|m| format!("{m:?}")),
+        "attackType": entry.attack_type.map(|a| format!("{a:?}")),
+        "offenseType": entry.offense_type.map(|o| o.to_string()),
+        "defenseType": entry.defense_type.map(|d| d.to_string()),
+        "siege": info.siege,
+        "squadSize": info.squad_size,
+        "damage": info.damage,
+        "dps": info.dps(),
+        "health": info.health,
+        "dpsPerPower": info.dps_per_power(),
+        "hpPerPower": info.hp_per_power(),
+        "maxCharges": info.max_charges,
+        "abilities": abilities,
+        "matchupScore": result.score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_data::{CardOffenseType, CardType};
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    fn empty_query() -> CardQuery {
+        CardQuery {
+            id: None,
+            name: None,
+            tier: None,
+            color: None,
+            card_type: None,
+            offense: None,
+            defense: None,
+            counters: None,
+            deck: false,
+            upgrade: Upgrade::U3,
+        }
+    }
+
+    fn card_data() -> CardData {
+        let mut card_data = CardData::new();
+        card_data.load();
+        card_data
+    }
+
+    #[test]
+    fn filter_by_tier_color_and_size() {
+        let card_data = card_data();
+        let query = CardQuery {
+            tier: Some(2),
+            color: Some("frost".to_string()),
+            card_type: Some("Unit".to_string()),
+            offense: Some("M".to_string()),
+            ..empty_query()
+        };
+        let results = find_cards(&card_data, &query).unwrap();
+        assert!(!results.is_empty());
+        assert!(results
+            .iter()
+            .all(|result| result.info.card_type == CardType::Unit
+                && result.info.orb_requirements.total == 2
+                && result.info.offense_type == CardOffenseType::M));
+    }
+
+    #[test]
+    fn counters_among_the_deck() {
+        let card_data = card_data();
+        let query = CardQuery {
+            counters: Some("Sunstriders".to_string()),
+            deck: true,
+            ..empty_query()
+        };
+        let results = find_cards(&card_data, &query).unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| deck::bot_deck()
+            .cards
+            .iter()
+            .any(|c| c.id() == result.info.id)));
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score.unwrap() >= pair[1].score.unwrap()));
+
+        let query = CardQuery {
+            counters: Some("no such card".to_string()),
+            ..empty_query()
+        };
+        assert!(find_cards(&card_data, &query).is_err());
+    }
+
+    #[test]
+    fn lookup_by_id_with_upgrade() {
+        let card_data = card_data();
+        let query = CardQuery {
+            id: Some(CardId::new(Dreadcharger, Upgrade::U3).0),
+            upgrade: Upgrade::U0,
+            ..empty_query()
+        };
+        let results = find_cards(&card_data, &query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].entry.card_name, "Dreadcharger");
+        assert_eq!(results[0].info.health, 520.);
+        assert_eq!(to_json(&results[0])["upgrade"], 0);
+    }
+}
@@ -0,0 +1,209 @@
+use api::*;
+use log::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::card_data::CardType;
+use crate::deck_analysis::DeckAnalysis;
+
+// Records the cards a player actually played during the match, taken from new squads, buildings
+// and the effects of spells. Unlike the squads alive at the moment, the history is kept after
+// the squads died and predicts which units the player is going to play next.
+
+// number of ticks after which an observation only counts half
+const OBSERVATION_HALF_LIFE: f32 = 600.;
+// weight of playable units of the deck which were not seen yet
+const UNSEEN_UNIT_WEIGHT: f32 = 0.1;
+// weight factor per tier the player advanced since the card was played
+const OLDER_TIER_FACTOR: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct PlayedCard {
+    // None if the card is ambiguous, e.g. the deck contains several playable spells
+    pub card_id: Option<u32>,
+    pub card_type: CardType,
+    pub tick: u32,
+    // number of orbs of the player when the card was played
+    pub tier: usize,
+}
+
+#[derive(Debug)]
+pub struct CardTracker {
+    pub played: Vec<PlayedCard>,
+    known_buildings: BTreeSet<EntityId>,
+    // ability id and start tick of the spell effects seen so far
+    known_spell_effects: BTreeSet<(u32, u32)>,
+}
+
+impl CardTracker {
+    pub fn new() -> CardTracker {
+        CardTracker {
+            played: vec![],
+            known_buildings: BTreeSet::new(),
+            known_spell_effects: BTreeSet::new(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        player_id: &EntityId,
+        state: &GameState,
+        new_squad_card_ids: &[u32],
+        deck: &DeckAnalysis,
+        orbs: &[OrbColor],
+    ) {
+        let tick = state.current_tick.0.get();
+
+        for card_id in new_squad_card_ids.iter() {
+            self.add(player_id, Some(*card_id), CardType::Unit, tick, orbs);
+        }
+
+        for building in state.entities.buildings.iter() {
+            if building.entity.player_entity_id != Some(*player_id)
+                || !self.known_buildings.insert(building.entity.id)
+            {
+                continue;
+            }
+            let card_id = CardTracker::identify_card(deck, CardType::Building, orbs);
+            self.add(player_id, card_id, CardType::Building, tick, orbs);
+        }
+
+        // spells are cast by the player itself, abilities of units have their squad as source
+        let entities = state
+            .entities
+            .squads
+            .iter()
+            .map(|squad| &squad.entity)
+            .chain(state.entities.figures.iter().map(|figure| &figure.entity))
+            .chain(state.entities.buildings.iter().map(|b| &b.entity))
+            .chain(state.entities.power_slots.iter().map(|slot| &slot.entity))
+            .chain(state.entities.token_slots.iter().map(|slot| &slot.entity));
+        let mut new_spells = 0;
+        for effect in entities.flat_map(|entity| entity.effects.iter()) {
+            if effect.source != player_id.0.get() {
+                continue;
+            }
+            // a spell can affect several entities, they all share the start tick
+            let Some(start_tick) = effect.start_tick else {
+                continue;
+            };
+            if self.known_spell_effects.insert((effect.id, start_tick)) {
+                new_spells += 1;
+            }
+        }
+        for _ in 0..new_spells {
+            let card_id = CardTracker::identify_card(deck, CardType::Spell, orbs);
+            self.add(player_id, card_id, CardType::Spell, tick, orbs);
+        }
+    }
+
+    pub fn num_played(&self, card_type: CardType) -> usize {
+        self.played
+            .iter()
+            .filter(|card| card.card_type == card_type)
+            .count()
+    }
+
+    pub fn predict_next_units(
+        &self,
+        deck: &DeckAnalysis,
+        orbs: &[OrbColor],
+        current_tick: u32,
+    ) -> Vec<(u32, f32)> {
+        // recently played units are the most likely ones, sorted by descending weight
+        let mut weights: BTreeMap<u32, f32> = deck
+            .get_playable_cards(CardType::Unit, orbs)
+            .iter()
+            .map(|card| (card.id, UNSEEN_UNIT_WEIGHT))
+            .collect();
+        for played in self.played.iter() {
+            let (CardType::Unit, Some(card_id)) = (played.card_type, played.card_id) else {
+                continue;
+            };
+            let age = current_tick.saturating_sub(played.tick) as f32;
+            let tiers_advanced = orbs.len().saturating_sub(played.tier) as i32;
+            *weights.entry(card_id).or_insert(0.) +=
+                0.5f32.powf(age / OBSERVATION_HALF_LIFE) * OLDER_TIER_FACTOR.powi(tiers_advanced);
+        }
+
+        let mut predicted: Vec<(u32, f32)> = weights.into_iter().collect();
+        // sort_by is stable, so equal weights keep the order of the ids
+        predicted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        predicted
+    }
+
+    fn identify_card(deck: &DeckAnalysis, card_type: CardType, orbs: &[OrbColor]) -> Option<u32> {
+        // only possible if a single card of the type is playable
+        match deck.get_playable_cards(card_type, orbs).as_slice() {
+            [card] => Some(card.id),
+            _ => None,
+        }
+    }
+
+    fn add(
+        &mut self,
+        player_id: &EntityId,
+        card_id: Option<u32>,
+        card_type: CardType,
+        tick: u32,
+        orbs: &[OrbColor],
+    ) {
+        debug!(
+            "Player {:?} played {:?} {:?} at tier {}",
+            player_id,
+            card_type,
+            card_id,
+            orbs.len()
+        );
+        self.played.push(PlayedCard {
+            card_id,
+            card_type,
+            tick,
+            tier: orbs.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+    use api::Upgrade::U3;
+
+    #[test]
+    fn dead_squads_are_still_expected() {
+        let mut builder = ScenarioBuilder::new();
+        builder.opponent_orb("North", OrbColor::Frost);
+        let mut game_info = builder.game_info();
+        assert_eq!(
+            game_info.opponent.played_cards.num_played(CardType::Unit),
+            0
+        );
+        // only the T1 Frost units of the deck
+        let predicted = game_info.opponent.predict_next_units(1);
+        assert_eq!(predicted.len(), 2);
+        assert!(predicted
+            .iter()
+            .all(|(_, weight)| *weight == UNSEEN_UNIT_WEIGHT));
+
+        builder
+            .opponent_squads(Dreadcharger, 2, "North", 1.)
+            .tick(10);
+        game_info.parse_state(builder.game_state());
+        assert_eq!(
+            game_info.opponent.played_cards.num_played(CardType::Unit),
+            2
+        );
+
+        builder.kill_opponent_squads().tick(600);
+        game_info.parse_state(builder.game_state());
+        assert!(game_info.opponent.squads.is_empty());
+        let predicted = game_info.opponent.predict_next_units(600);
+        assert_eq!(predicted.len(), 3);
+        assert_eq!(predicted[0].0, CardId::new(Dreadcharger, U3).0);
+        assert!(predicted[0].1 > 1.);
+
+        // Fire is missing for the T2 and T3 cards
+        assert_eq!(game_info.opponent.predict_next_orb(), Some(OrbColor::Fire));
+    }
+}
@@ -4,10 +4,10 @@ use log::*;
 
 use crate::card_data::CardData;
 use crate::game_info::GameInfo;
-
-const CARD_PLAY_TICK_TIMEOUT: u32 = 10;
+use crate::strategy_config::CommandSchedulerConfig;
 
 pub struct CommandScheduler {
+    config: CommandSchedulerConfig,
     tick_last_played_card: Option<Tick>,
     waiting_for_card_spawn: bool,
     waiting_for_power_slot: bool,
@@ -18,8 +18,9 @@ pub struct CommandScheduler {
 }
 
 impl CommandScheduler {
-    pub fn new() -> CommandScheduler {
+    pub fn new(config: CommandSchedulerConfig) -> CommandScheduler {
         CommandScheduler {
+            config,
             tick_last_played_card: None,
             waiting_for_card_spawn: false,
             waiting_for_power_slot: false,
@@ -120,7 +121,7 @@ impl CommandScheduler {
         self.scheduled_commands.push(command);
     }
 
-    pub fn card_can_be_played(&self, card: CardTemplate, game_info: &mut GameInfo) -> bool {
+    pub fn card_can_be_played(&self, card: CardTemplate, game_info: &GameInfo) -> bool {
         if self.current_tick.is_none() {
             return false;
         }
@@ -131,7 +132,7 @@ impl CommandScheduler {
 
         if self.tick_last_played_card.is_some()
             && self.current_tick.unwrap().0.get()
-                < self.tick_last_played_card.unwrap().0.get() + CARD_PLAY_TICK_TIMEOUT
+                < self.tick_last_played_card.unwrap().0.get() + self.config.card_play_tick_timeout
         {
             return false;
         }
@@ -143,12 +144,13 @@ impl CommandScheduler {
             return false;
         }
 
-        let card_cost = game_info
-            .card_data
-            .get_card_info_from_id(card.id())
-            .power_cost;
-
-        self.current_power >= card_cost
+        match game_info.card_data.get_card_info(&card) {
+            Ok(card_info) => self.current_power >= card_info.power_cost,
+            Err(err) => {
+                error!("{err}");
+                false
+            }
+        }
     }
 
     pub fn power_slot_can_be_built(&self) -> bool {
@@ -7,9 +7,6 @@ use crate::game_info::GameInfo;
 use crate::location::*;
 use crate::utils;
 
-const DEFENSE_AGGRO_RADIUS: f32 = 30.;
-const ATTACK_AGGR_RADIUS: f32 = 30.;
-
 #[derive(Debug)]
 pub struct CombatController {
     state: CombatControllerState,
@@ -50,25 +47,46 @@ impl CombatController {
         &self,
         game_info: &GameInfo,
         owned_location: &Location,
-    ) -> Position2D {
+    ) -> Option<Position2D> {
         let ready_squads: Vec<&SquadController> =
             self.squads.iter().filter(|s| s.initialized()).collect();
         if ready_squads.len() == 0 {
-            // no squads assigned yet, return the start token location
-            game_info.locations.get(owned_location).unwrap().position()
+            // no squads assigned yet, return the owned location or the start location before
+            // the first location is owned
+            let location = game_info
+                .locations
+                .get(owned_location)
+                .or(game_info.locations.get(&game_info.bot.start_location));
+            if let Some(location) = location {
+                return Some(location.position());
+            }
+            // start location unknown on unknown maps -> use the own start orb or power well
+            let token_slots = game_info.bot.token_slots.values().map(|slot| &slot.entity);
+            let power_slots = game_info.bot.power_slots.values().map(|slot| &slot.entity);
+            token_slots
+                .chain(power_slots)
+                .next()
+                .map(|entity| entity.position.to_2d())
         } else {
-            // return the position of the first squad
-            get_squad_position(ready_squads.first().unwrap().entity_id, game_info)
+            // return the position of the squad with the most support against nearby enemies
+            ready_squads
+                .iter()
+                .map(|s| get_squad_position(s.entity_id, game_info))
+                .max_by(|a, b| {
+                    let balance_a = game_info.influence.balance_at(a);
+                    let balance_b = game_info.influence.balance_at(b);
+                    balance_a.total_cmp(&balance_b)
+                })
         }
     }
 
-    pub fn move_squads(&mut self, pos: Position2D, force: bool) {
+    pub fn move_squads(&mut self, pos: Position2D, force: bool, game_info: &GameInfo) {
         if self.state != CombatControllerState::Moving {
             self.enter_state(CombatControllerState::Moving);
         }
 
         for squad in &mut self.squads {
-            squad.move_squad(pos, force);
+            squad.move_squad(pos, force, game_info);
         }
     }
 
@@ -77,15 +95,14 @@ impl CombatController {
             self.enter_state(CombatControllerState::SlotDefense);
         }
 
-        // ToDo: how to deal with attacks outside of this range, e.g. Firedancers?
+        // ranged squads like Firedancers reach the location from further away
         let location_pos = game_info.locations.get(location).unwrap().position();
-        let mut enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&location_pos, DEFENSE_AGGRO_RADIUS);
+        let mut enemy_squads_in_range = game_info.get_threatening_enemy_squads(&location_pos);
 
         if enemy_squads_in_range.len() == 0 {
             // no enemy in range -> stay close to the defending location
             for squad in &mut self.squads {
-                squad.move_squad(location_pos, false);
+                squad.move_squad(location_pos, false, game_info);
             }
         } else if enemy_squads_in_range.len() == 1 {
             // one enemy in range -> attack that one
@@ -120,17 +137,17 @@ impl CombatController {
         if utils::dist(own_pos, center) > radius {
             // outside of the area to control -> move there first
             for squad in &mut self.squads {
-                squad.move_squad(*center, false);
+                squad.move_squad(*center, false, game_info);
             }
             return;
         }
 
-        let mut enemy_squads = game_info.get_enemy_squads_in_range(center, radius);
+        let mut enemy_squads = game_info.get_threatening_enemy_squads(center);
 
         if enemy_squads.len() == 0 {
             // no enemies in range -> move there
             for squad in &mut self.squads {
-                squad.move_squad(*center, false);
+                squad.move_squad(*center, false, game_info);
             }
             return;
         }
@@ -146,7 +163,10 @@ impl CombatController {
 
     pub fn attack_squad(&mut self, entity_id: &EntityId, game_info: &GameInfo) {
         // attack an enemy squad
-        if !game_info.opponent.squads.contains_key(&entity_id) {
+        if !game_info
+            .opponents()
+            .any(|p| p.squads.contains_key(&entity_id))
+        {
             warn!("Can not attack entity {:?} as it is not a squad", entity_id);
             return;
         }
@@ -187,28 +207,16 @@ impl CombatController {
             self.enter_state(CombatControllerState::AttackSlotControl);
         }
 
-        let slot_position: Position2D;
-        if game_info.opponent.power_slots.contains_key(&entity_id) {
-            slot_position = game_info
-                .opponent
-                .power_slots
-                .get(&entity_id)
+        let slot_position = match game_info.get_opponent_power_slot(entity_id) {
+            Some(power_slot) => power_slot.entity.position.to_2d(),
+            None => game_info
+                .get_opponent_token_slot(entity_id)
                 .unwrap()
                 .entity
                 .position
-                .to_2d();
-        } else {
-            slot_position = game_info
-                .opponent
-                .token_slots
-                .get(&entity_id)
-                .unwrap()
-                .entity
-                .position
-                .to_2d();
-        }
-        let mut enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&slot_position, ATTACK_AGGR_RADIUS);
+                .to_2d(),
+        };
+        let mut enemy_squads_in_range = game_info.get_threatening_enemy_squads(&slot_position);
 
         if enemy_squads_in_range.len() == 0 {
             // no enemy squads in range -> attack slot directly
@@ -255,32 +263,24 @@ impl CombatController {
     }
 
     fn slot_is_valid_target(&mut self, entity_id: &EntityId, game_info: &GameInfo) -> bool {
-        // return true if the entity is slot owned by the opponent
-        if game_info.opponent.power_slots.contains_key(&entity_id)
-            && game_info.opponent.token_slots.contains_key(&entity_id)
-        {
-            warn!("{:?} is neither a power nor a token slot", entity_id);
+        // return true if the entity is slot owned by one of the opponents
+        let power_slot = game_info.get_opponent_power_slot(entity_id);
+        let token_slot = game_info.get_opponent_token_slot(entity_id);
+        if power_slot.is_none() && token_slot.is_none() {
+            warn!(
+                "{:?} is not a power or token slot of an opponent",
+                entity_id
+            );
             return false;
         }
 
-        if let Some(power_slot) = game_info.opponent.power_slots.get(&entity_id) {
-            let power_slot_id = power_slot.entity.player_entity_id;
-            if power_slot_id.is_none()
-                || (power_slot_id.is_some() && power_slot_id.unwrap() != game_info.opponent.id)
-            {
-                warn!("{:?} is not owned by the opponent", entity_id);
-                return false;
-            }
-        }
-
-        if let Some(token_slot) = game_info.opponent.token_slots.get(&entity_id) {
-            let token_slot_id = token_slot.entity.player_entity_id;
-            if token_slot_id.is_none()
-                || (token_slot_id.is_some() && token_slot_id.unwrap() != game_info.opponent.id)
-            {
-                warn!("{:?} is not owned by the opponent", entity_id);
-                return false;
-            }
+        let owner = power_slot
+            .map(|slot| slot.entity.player_entity_id)
+            .or(token_slot.map(|slot| slot.entity.player_entity_id))
+            .flatten();
+        if !owner.is_some_and(|id| game_info.is_opponent(&id)) {
+            warn!("{:?} is not owned by an opponent", entity_id);
+            return false;
         }
 
         true
@@ -305,3 +305,35 @@ impl Controller for CombatController {
         commands
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+
+    #[test]
+    fn spawn_location_without_known_start_location() {
+        let mut game_info = ScenarioBuilder::new().game_info();
+        let combat_controller = CombatController::new(vec![]);
+        let start_pos = game_info.locations[&game_info.bot.start_location].position();
+        assert_eq!(
+            combat_controller.get_spawn_location(&game_info, &Location::UNKNOWN),
+            Some(start_pos)
+        );
+
+        // unknown maps may have no start location, the own start orb is used instead
+        game_info.bot.start_location = Location::UNKNOWN;
+        let orb = game_info.bot.token_slots.values().next().unwrap();
+        assert_eq!(
+            combat_controller.get_spawn_location(&game_info, &Location::UNKNOWN),
+            Some(orb.entity.position.to_2d())
+        );
+
+        game_info.bot.token_slots.clear();
+        game_info.bot.power_slots.clear();
+        assert_eq!(
+            combat_controller.get_spawn_location(&game_info, &Location::UNKNOWN),
+            None
+        );
+    }
+}
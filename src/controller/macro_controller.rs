@@ -1,86 +1,21 @@
 use api::*;
 use log::*;
 
-use crate::bot::BOT_ORBS;
 use crate::command_scheduler::CommandScheduler;
 use crate::controller::combat_controller::CombatController;
 use crate::controller::spawn_controller::SpawnController;
 use crate::controller::Controller;
+use crate::deck;
 use crate::game_info;
 use crate::game_info::GameInfo;
 use crate::location;
 use crate::location::Location;
+use crate::location_priority;
+use crate::strategy_config::{LocationPriorityConfig, MacroConfig, StrategyConfig};
 use crate::utils;
 
-// radius around location to aggro on enemy squads
-const CONTROL_AREA_AGGRO_RADIUS: f32 = 60.;
-// required power before a well is built
-const MIN_POWER_BUILD_WELL: f32 = 200.;
-// minimum difference in tempo to consider it an advantage
-const MIN_TEMPO_DIFF_ADVANTAGE: f32 = 0.;
-// radius in which a location is considered under attack by enemy units
-const DEFEND_LOCATION_AGGRO_RADIUS: f32 = 60.;
-// difference in number of squads to focus a well or orb instead of enemy squads
-const NUM_SQUADS_CRITICAL_MASS: i32 = 6;
-
-// locations to prioritize when ahead or even
-const LOCATION_PRIOS_AHEAD_SOUTH_START: [Location; 9] = [
-    Location::South,
-    Location::Center,
-    Location::Centersouth,
-    Location::Centernorth,
-    Location::Southeast,
-    // Location::Southwest,
-    Location::East,
-    Location::West,
-    Location::Northwest,
-    // Location::Northeast,
-    Location::North,
-];
-const LOCATION_PRIOS_AHEAD_NORTH_START: [Location; 9] = [
-    Location::North,
-    Location::Center,
-    Location::Centernorth,
-    Location::Centersouth,
-    Location::Northwest,
-    // Location::Northeast,
-    Location::West,
-    Location::East,
-    Location::Southeast,
-    // Location::Southwest,
-    Location::South,
-];
-
-// locations to prioritize when behind
-const LOCATION_PRIOS_BEHIND_SOUTH_START: [Location; 9] = [
-    Location::South,
-    Location::Southeast,
-    Location::East,
-    // Location::Southwest,
-    Location::West,
-    Location::Centersouth,
-    Location::Center,
-    Location::Centernorth,
-    Location::Northwest,
-    // Location::Northeast,
-    Location::North,
-];
-const LOCATION_PRIOS_BEHIND_NORTH_START: [Location; 9] = [
-    Location::North,
-    Location::Northwest,
-    Location::West,
-    // Location::Northeast,
-    Location::East,
-    Location::Centernorth,
-    Location::Center,
-    Location::Centersouth,
-    Location::Southeast,
-    // Location::Southwest,
-    Location::South,
-];
-
-#[derive(Default, Debug)]
-enum MacroState {
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum MacroState {
     #[default]
     MatchStart,
     GroundPresenceNextLoc, // get ground presence at the next location not owned by me
@@ -93,6 +28,8 @@ enum MacroState {
 }
 
 pub struct MacroController {
+    config: MacroConfig,
+    location_priorities: LocationPriorityConfig,
     state: MacroState,
     attack_focus_loc: Location,
     latest_owning_loc: Location,
@@ -102,17 +39,23 @@ pub struct MacroController {
 }
 
 impl MacroController {
-    pub fn new() -> Self {
+    pub fn new(config: &StrategyConfig) -> Self {
         MacroController {
+            config: config.macro_controller.clone(),
+            location_priorities: config.location_priorities.clone(),
             state: MacroState::MatchStart,
-            attack_focus_loc: Location::Center,
-            latest_owning_loc: Location::Center,
+            attack_focus_loc: Location::UNKNOWN,
+            latest_owning_loc: Location::UNKNOWN,
             owning_loc_history: vec![],
             combat_controller: CombatController::new(vec![]),
-            spawn_controller: SpawnController::new(),
+            spawn_controller: SpawnController::new(config.spawn_controller.clone()),
         }
     }
 
+    pub fn state(&self) -> &MacroState {
+        &self.state
+    }
+
     pub fn tick(&mut self, game_info: &mut GameInfo, command_scheduler: &mut CommandScheduler) {
         if self.combat_controller.has_errored_squads() {
             // hacky -> remove spawn lock when a spawn command failed
@@ -122,10 +65,12 @@ impl MacroController {
         self.combat_controller
             .remove_dead_and_errored_squads(game_info);
         self.handle_destroyed_slots(game_info);
-        let current_pos = self
+        let spawn_pos = self
             .combat_controller
             .get_spawn_location(game_info, &self.latest_owning_loc);
-        self.spawn_controller.set_spawn_pos(current_pos);
+        if let Some(spawn_pos) = spawn_pos {
+            self.spawn_controller.set_spawn_pos(spawn_pos);
+        }
 
         MacroController::repair_structures(game_info, command_scheduler);
 
@@ -140,8 +85,12 @@ impl MacroController {
             MacroState::Defend => self.run_defend(game_info),
         }
 
-        for squad in self.spawn_controller.tick(command_scheduler, game_info) {
-            self.combat_controller.add_squad(squad);
+        if spawn_pos.is_some() {
+            for squad in self.spawn_controller.tick(command_scheduler, game_info) {
+                self.combat_controller.add_squad(squad);
+            }
+        } else {
+            warn!("Unable to find a spawn location, skipping spawns");
         }
         let squad_commands = self.combat_controller.tick(game_info);
         command_scheduler.schedule_commands(squad_commands);
@@ -159,11 +108,14 @@ impl MacroController {
         }
 
         self.spawn_controller.set_in_offense(true);
-        self.set_attack_focus_loc(self.get_next_attack_focus_loc(game_info));
+        self.set_attack_focus_loc(self.get_next_attack_focus_loc(game_info), game_info);
 
-        let current_pos = self
+        let Some(current_pos) = self
             .combat_controller
-            .get_spawn_location(game_info, &self.latest_owning_loc);
+            .get_spawn_location(game_info, &self.latest_owning_loc)
+        else {
+            return;
+        };
         let loc_pos = game_info
             .locations
             .get(&self.attack_focus_loc)
@@ -172,41 +124,46 @@ impl MacroController {
         let dist_to_loc = utils::dist(&current_pos, &loc_pos);
 
         let loc_owner = location::get_location_owner(&self.attack_focus_loc, game_info);
-        let is_enemy_loc = loc_owner.is_some_and(|entity_id| entity_id == game_info.opponent.id);
+        let is_enemy_loc = loc_owner.is_some_and(|entity_id| game_info.is_opponent(&entity_id));
 
-        let enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&loc_pos, CONTROL_AREA_AGGRO_RADIUS);
+        let loc_is_threatened = game_info.influence.is_threatened(&loc_pos);
 
-        if is_enemy_loc && dist_to_loc < CONTROL_AREA_AGGRO_RADIUS {
+        if is_enemy_loc && dist_to_loc < self.config.control_area_aggro_radius {
             // location is controlled by enemy -> attack
             self.enter_state(MacroState::AttackLoc);
             return;
         }
 
-        if enemy_squads_in_range.len() > 0 && dist_to_loc < CONTROL_AREA_AGGRO_RADIUS {
+        if loc_is_threatened && dist_to_loc < self.config.control_area_aggro_radius {
             // approaching location and enemies nearby -> control area
             self.enter_state(MacroState::ControlArea);
             return;
         }
 
-        if enemy_squads_in_range.len() == 0 && dist_to_loc < game_info::GROUND_PRESENCE_MIN_DIST {
+        if !loc_is_threatened && dist_to_loc < game_info::GROUND_PRESENCE_MIN_DIST {
             // no enemies nearby and reached location
             if game_info.token_slot_diff() < 0 {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
-            if game_info.seconds_have_passed(180) && game_info.bot.token_slots.len() == 1 {
+            if game_info.seconds_have_passed(self.config.advance_t2_seconds)
+                && game_info.bot.token_slots.len() == 1
+            {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
-            if game_info.seconds_have_passed(420) && game_info.bot.token_slots.len() == 2 {
+            if game_info.seconds_have_passed(self.config.advance_t3_seconds)
+                && game_info.bot.token_slots.len() == 2
+            {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
-            if game_info.power_slot_diff() < 0 || game_info.bot.power > MIN_POWER_BUILD_WELL {
+            if game_info.power_slot_diff() < 0
+                || game_info.bot.power > self.config.min_power_build_well
+            {
                 // opponent has one or more wells or bot has enough power to defend an attack
                 self.enter_state(MacroState::TakeWell);
                 return;
@@ -214,7 +171,7 @@ impl MacroController {
         }
 
         self.spawn_controller.spawn_single_unit();
-        self.combat_controller.move_squads(loc_pos, true);
+        self.combat_controller.move_squads(loc_pos, true, game_info);
     }
 
     fn run_take_well(&mut self, command_scheduler: &mut CommandScheduler, game_info: &GameInfo) {
@@ -287,13 +244,22 @@ impl MacroController {
         }
 
         if command_scheduler.token_slot_can_be_built(game_info) {
+            // re-planned from the current orbs, e.g. after one was destroyed
+            let Some(color) = deck::bot_deck()
+                .orb_planner
+                .get_next_orb(&game_info.bot.get_orbs(), Some(&game_info.opponent.deck))
+            else {
+                self.enter_state(MacroState::GroundPresenceNextLoc);
+                return;
+            };
+
             let offense_slot_id =
                 location::get_next_free_token_slot(&self.attack_focus_loc, game_info);
 
             if offense_slot_id.is_some() && game_info.has_ground_presence(&self.attack_focus_loc) {
                 let command = Command::TokenSlotBuild {
                     slot_id: offense_slot_id.unwrap(),
-                    color: BOT_ORBS[game_info.bot.token_slots.len()],
+                    color,
                 };
                 command_scheduler.schedule_command(command);
                 self.set_latest_owning_loc(self.attack_focus_loc);
@@ -306,7 +272,7 @@ impl MacroController {
             if defense_slot_id.is_some() {
                 let command = Command::TokenSlotBuild {
                     slot_id: defense_slot_id.unwrap(),
-                    color: BOT_ORBS[game_info.bot.token_slots.len()],
+                    color,
                 };
                 command_scheduler.schedule_command(command);
                 return;
@@ -329,7 +295,7 @@ impl MacroController {
             .get(&self.latest_owning_loc)
             .unwrap()
             .position();
-        self.combat_controller.move_squads(pos, true);
+        self.combat_controller.move_squads(pos, true, game_info);
 
         for squad_id in game_info.bot.squads.keys() {
             let (current_health, max_health) = game_info.get_squad_health(squad_id);
@@ -338,7 +304,7 @@ impl MacroController {
             }
         }
 
-        if MacroController::tempo_advantage(game_info) {
+        if self.tempo_advantage(game_info) {
             self.enter_state(MacroState::GroundPresenceNextLoc);
         } else {
             self.spawn_controller.stop_spawn();
@@ -349,9 +315,12 @@ impl MacroController {
     fn run_control_area(&mut self, game_info: &mut GameInfo) {
         self.spawn_controller.set_in_offense(false);
 
-        let current_pos = self
+        let Some(current_pos) = self
             .combat_controller
-            .get_spawn_location(game_info, &self.latest_owning_loc);
+            .get_spawn_location(game_info, &self.latest_owning_loc)
+        else {
+            return;
+        };
         let loc_pos = game_info
             .locations
             .get(&self.attack_focus_loc)
@@ -360,7 +329,7 @@ impl MacroController {
 
         if self.combat_controller.get_squads().len() == 0 {
             // all own squads are dead -> attack again or defend
-            if MacroController::tempo_advantage(game_info) {
+            if self.tempo_advantage(game_info) {
                 self.enter_state(MacroState::GroundPresenceNextLoc);
             } else {
                 self.spawn_controller.stop_spawn();
@@ -369,19 +338,15 @@ impl MacroController {
             return;
         }
 
-        let enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&current_pos, CONTROL_AREA_AGGRO_RADIUS);
-        if (game_info.bot.squads.len() as i64) - (enemy_squads_in_range.len() as i64) < -1 {
-            // opponent has 2 or more squads more than me -> fight is lost, retreat
+        if self.fight_is_lost(&current_pos, game_info) {
+            // opponent is clearly stronger around my squads -> fight is lost, retreat
             self.spawn_controller.stop_spawn();
             self.enter_state(MacroState::HealUnits);
             return;
         }
 
-        let enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&loc_pos, CONTROL_AREA_AGGRO_RADIUS);
-        if enemy_squads_in_range.len() == 0 {
-            // no more enemy squads in range -> take location
+        if !game_info.influence.is_threatened(&loc_pos) {
+            // no more enemy squads reach the location -> take location
             self.spawn_controller.stop_spawn();
 
             if game_info.token_slot_diff() < 0 {
@@ -389,18 +354,22 @@ impl MacroController {
                 return;
             }
 
-            if game_info.seconds_have_passed(180) && game_info.bot.token_slots.len() == 1 {
+            if game_info.seconds_have_passed(self.config.advance_t2_seconds)
+                && game_info.bot.token_slots.len() == 1
+            {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
-            if game_info.seconds_have_passed(420) && game_info.bot.token_slots.len() == 2 {
+            if game_info.seconds_have_passed(self.config.advance_t3_seconds)
+                && game_info.bot.token_slots.len() == 2
+            {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
             if game_info.power_slot_diff() < 0
-                || game_info.bot.power > MIN_POWER_BUILD_WELL
+                || game_info.bot.power > self.config.min_power_build_well
                 || game_info.bot.squads.len() >= 3
             {
                 // opponent has one or more wells, bot has enough power to defend an attack or has
@@ -415,7 +384,7 @@ impl MacroController {
         self.combat_controller.control_area(
             &current_pos,
             &loc_pos,
-            CONTROL_AREA_AGGRO_RADIUS,
+            self.config.control_area_aggro_radius,
             game_info,
         );
     }
@@ -431,7 +400,7 @@ impl MacroController {
 
         if self.combat_controller.get_squads().len() == 0 {
             // all own squads are dead -> attack again or defend
-            if MacroController::tempo_advantage(game_info) {
+            if self.tempo_advantage(game_info) {
                 self.enter_state(MacroState::GroundPresenceNextLoc);
             } else {
                 self.spawn_controller.stop_spawn();
@@ -440,13 +409,14 @@ impl MacroController {
             return;
         }
 
-        let current_pos = self
+        let Some(current_pos) = self
             .combat_controller
-            .get_spawn_location(game_info, &self.latest_owning_loc);
-        let enemy_squads_in_range =
-            game_info.get_enemy_squads_in_range(&current_pos, CONTROL_AREA_AGGRO_RADIUS);
-        if (game_info.bot.squads.len() as i64) - (enemy_squads_in_range.len() as i64) < -1 {
-            // opponent has 2 or more squads more than me -> fight is lost, retreat
+            .get_spawn_location(game_info, &self.latest_owning_loc)
+        else {
+            return;
+        };
+        if self.fight_is_lost(&current_pos, game_info) {
+            // opponent is clearly stronger around my squads -> fight is lost, retreat
             self.spawn_controller.stop_spawn();
             self.enter_state(MacroState::HealUnits);
             return;
@@ -460,22 +430,9 @@ impl MacroController {
 
         // attack power slots first
         for power_slot in &loc.powers {
-            if game_info
-                .opponent
-                .power_slots
-                .contains_key(&power_slot.entity_id.unwrap())
-            {
+            if let Some(slot) = game_info.get_opponent_power_slot(&power_slot.entity_id.unwrap()) {
                 target = power_slot.entity_id;
-                pos = Some(
-                    game_info
-                        .opponent
-                        .power_slots
-                        .get(&target.unwrap())
-                        .unwrap()
-                        .entity
-                        .position
-                        .to_2d(),
-                );
+                pos = Some(slot.entity.position.to_2d());
             }
         }
 
@@ -485,9 +442,7 @@ impl MacroController {
                 target = token.entity_id;
                 pos = Some(
                     game_info
-                        .opponent
-                        .token_slots
-                        .get(&target.unwrap())
+                        .get_opponent_token_slot(&target.unwrap())
                         .unwrap()
                         .entity
                         .position
@@ -499,12 +454,11 @@ impl MacroController {
         }
 
         if target.is_some() {
-            let num_enemy_squads_in_range = game_info
-                .get_enemy_squads_in_range(&pos.unwrap(), CONTROL_AREA_AGGRO_RADIUS)
-                .len() as i32;
+            let num_enemy_squads_in_range =
+                game_info.get_threatening_enemy_squads(&pos.unwrap()).len() as i32;
 
             if (game_info.bot.squads.len() as i32) - num_enemy_squads_in_range
-                >= NUM_SQUADS_CRITICAL_MASS
+                >= self.config.num_squads_critical_mass
             {
                 // reached a critical mass of own squads -> focus the well or orb
                 self.combat_controller
@@ -538,17 +492,17 @@ impl MacroController {
                 return;
             }
 
-            if game_info.seconds_have_passed(180)
+            if game_info.seconds_have_passed(self.config.advance_t2_seconds)
                 && game_info.bot.token_slots.len() == 1
-                && game_info.bot.power >= 200.
+                && game_info.bot.power >= self.config.defend_min_power_advance_t2
             {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
             }
 
-            if game_info.seconds_have_passed(420)
+            if game_info.seconds_have_passed(self.config.advance_t3_seconds)
                 && game_info.bot.token_slots.len() == 2
-                && game_info.bot.power >= 300.
+                && game_info.bot.power >= self.config.defend_min_power_advance_t3
             {
                 self.enter_state(MacroState::AdvanceTier);
                 return;
@@ -560,13 +514,13 @@ impl MacroController {
                 return;
             }
 
-            if MacroController::tempo_advantage(game_info) {
+            if self.tempo_advantage(game_info) {
                 // tempo advantage -> move towards next location
                 self.enter_state(MacroState::GroundPresenceNextLoc);
                 return;
             }
 
-            if game_info.bot.power >= 300. {
+            if game_info.bot.power >= self.config.defend_max_unspent_power {
                 // lots of unspent power -> build a well or attack
                 if game_info.bot.power_slots.len() < 7 {
                     self.enter_state(MacroState::TakeWell);
@@ -598,65 +552,119 @@ impl MacroController {
     }
 
     fn get_next_attack_focus_loc(&self, game_info: &GameInfo) -> Location {
-        if MacroController::tempo_advantage(game_info) {
-            let location_prios_ahead;
-            if game_info.bot.start_location == Location::South {
-                location_prios_ahead = LOCATION_PRIOS_AHEAD_SOUTH_START;
-            } else if game_info.bot.start_location == Location::North {
-                location_prios_ahead = LOCATION_PRIOS_AHEAD_NORTH_START;
-            } else {
-                error!("Unable to find prio locations based on start location, using south");
-                location_prios_ahead = LOCATION_PRIOS_AHEAD_SOUTH_START;
-            }
+        if let Some(loc) = self.get_ally_attack_loc(game_info) {
+            // join the attack of an ally
+            return loc;
+        }
+
+        if self.tempo_advantage(game_info) {
+            let location_prios_ahead =
+                location_priority::get_location_prios(game_info, &self.location_priorities, true);
+            // no locations were found or discovered
+            let Some((last_loc, other_locs)) = location_prios_ahead.split_last() else {
+                return game_info.opponent.start_location;
+            };
 
             // find the next location owned by the opponent if tempo is good
-            for loc in location_prios_ahead[..(location_prios_ahead.len() - 1)].iter() {
-                if let Some(owner_id) = location::get_location_owner(&loc, game_info) {
-                    if owner_id == game_info.opponent.id {
-                        // location is owned by the opponent
-                        return *loc;
-                    }
-                }
+            let opponent_locations: Vec<Location> = other_locs
+                .iter()
+                .filter(|loc| {
+                    location::get_location_owner(loc, game_info)
+                        .is_some_and(|owner| game_info.is_opponent(&owner))
+                })
+                .cloned()
+                .collect();
+            if let Some(loc) =
+                MacroController::prefer_adjacent_to_owned(game_info, &opponent_locations)
+            {
+                return loc;
             }
 
             // TODO: implement this properly
-            if game_info.seconds_have_passed(300) {
+            if game_info.seconds_have_passed(self.config.attack_start_location_seconds) {
                 // hacky: allow targetting enemy base after 5 mins
-                return location_prios_ahead[location_prios_ahead.len() - 1];
+                return *last_loc;
             }
 
             // find the location not owned by anyone
-            for loc in location_prios_ahead.iter() {
-                if let None = location::get_location_owner(&loc, game_info) {
-                    return *loc;
-                }
+            let free_locations =
+                MacroController::get_free_locations(game_info, &location_prios_ahead);
+            if let Some(loc) = MacroController::prefer_adjacent_to_owned(game_info, &free_locations)
+            {
+                return loc;
             }
 
             error!("Unable to find next free location from ahead");
-            Location::North
+            game_info.opponent.start_location
         } else {
-            let location_prios_behind;
-            if game_info.bot.start_location == Location::South {
-                location_prios_behind = LOCATION_PRIOS_BEHIND_SOUTH_START;
-            } else if game_info.bot.start_location == Location::North {
-                location_prios_behind = LOCATION_PRIOS_BEHIND_NORTH_START;
-            } else {
-                error!("Unable to find prio locations based on start location, using south");
-                location_prios_behind = LOCATION_PRIOS_BEHIND_SOUTH_START;
-            }
+            let location_prios_behind =
+                location_priority::get_location_prios(game_info, &self.location_priorities, false);
 
             // find the location not owned by anyone
-            for loc in location_prios_behind.iter() {
-                if let None = location::get_location_owner(&loc, game_info) {
-                    return *loc;
-                }
+            let free_locations =
+                MacroController::get_free_locations(game_info, &location_prios_behind);
+            if let Some(loc) = MacroController::prefer_adjacent_to_owned(game_info, &free_locations)
+            {
+                return loc;
             }
 
             error!("Unable to find next free location from behind");
-            Location::North
+            game_info.opponent.start_location
         }
     }
 
+    fn fight_is_lost(&self, own_pos: &Position2D, game_info: &GameInfo) -> bool {
+        // the opponent's squads around are clearly stronger than the own and allied ones
+        let influence = &game_info.influence;
+        !influence.is_safe(own_pos)
+            && influence.strength_at(own_pos)
+                < self.config.min_strength_ratio_retreat * influence.threat_at(own_pos)
+    }
+
+    fn get_ally_attack_loc(&self, game_info: &GameInfo) -> Option<Location> {
+        // opponent location with the most allied squads close to it
+        let mut ally_attack_loc: Option<(Location, usize)> = None;
+        for (loc, loc_pos) in game_info.locations.iter() {
+            let owner = location::get_location_owner(loc, game_info);
+            if !owner.is_some_and(|id| game_info.is_opponent(&id)) {
+                continue;
+            }
+
+            let position = loc_pos.position();
+            let num_ally_squads = game_info
+                .allies
+                .iter()
+                .flat_map(|ally| ally.squads.values())
+                .filter(|squad| {
+                    utils::dist(&position, &squad.entity.position.to_2d())
+                        < self.config.control_area_aggro_radius
+                })
+                .count();
+            if num_ally_squads > 0 && ally_attack_loc.map_or(true, |(_, n)| num_ally_squads > n) {
+                ally_attack_loc = Some((*loc, num_ally_squads));
+            }
+        }
+        ally_attack_loc.map(|(loc, _)| loc)
+    }
+
+    fn get_free_locations(game_info: &GameInfo, location_prios: &[Location]) -> Vec<Location> {
+        location_prios
+            .iter()
+            .filter(|loc| location::get_location_owner(loc, game_info).is_none())
+            .cloned()
+            .collect()
+    }
+
+    fn prefer_adjacent_to_owned(game_info: &GameInfo, candidates: &[Location]) -> Option<Location> {
+        // targets next to the own or allied territory are quicker to reach and easier to hold,
+        // otherwise the priority order decides
+        let owned_locations = location::get_allied_locations(game_info);
+        let next_to_owned = game_info
+            .location_graph
+            .frontier(candidates, &owned_locations);
+        next_to_owned.first().or(candidates.first()).cloned()
+    }
+
     fn repair_structures(game_info: &GameInfo, command_scheduler: &mut CommandScheduler) {
         // check power slots
         for power_slot in game_info.bot.power_slots.values() {
@@ -691,8 +699,9 @@ impl MacroController {
         }
     }
 
-    fn tempo_advantage(game_info: &GameInfo) -> bool {
-        game_info.bot.get_tempo() - game_info.opponent.get_tempo() >= MIN_TEMPO_DIFF_ADVANTAGE
+    fn tempo_advantage(&self, game_info: &GameInfo) -> bool {
+        game_info.bot.get_tempo() - game_info.opponent.get_tempo()
+            >= self.config.min_tempo_diff_advantage
     }
 
     fn enter_state(&mut self, new_state: MacroState) {
@@ -700,11 +709,15 @@ impl MacroController {
         self.state = new_state;
     }
 
-    fn set_attack_focus_loc(&mut self, new_loc: Location) {
+    fn set_attack_focus_loc(&mut self, new_loc: Location, game_info: &GameInfo) {
         if new_loc != self.attack_focus_loc {
+            let route = game_info
+                .location_graph
+                .route(&self.latest_owning_loc, &new_loc)
+                .unwrap_or_default();
             info!(
-                "MacroController: focussing attacks on location {:?}",
-                new_loc
+                "MacroController: focussing attacks on location {:?}, route {:?}",
+                new_loc, route
             );
             self.attack_focus_loc = new_loc;
         }
@@ -722,31 +735,27 @@ impl MacroController {
     }
 
     fn get_locations_under_attack(&self, game_info: &GameInfo) -> Vec<Location> {
-        let location_prios;
-        if game_info.bot.start_location == Location::South {
-            location_prios = LOCATION_PRIOS_AHEAD_SOUTH_START;
-        } else if game_info.bot.start_location == Location::North {
-            location_prios = LOCATION_PRIOS_AHEAD_NORTH_START;
-        } else {
-            error!("Unable to find prio locations based on start location, using south");
-            location_prios = LOCATION_PRIOS_AHEAD_SOUTH_START;
-        }
-
-        let owned_locations: Vec<Location> = location_prios
-            .clone()
-            .into_iter()
-            .filter(|loc| {
-                location::get_location_owner(loc, game_info)
-                    .is_some_and(|id| id == game_info.bot.id)
-            })
-            .collect();
+        let location_prios =
+            location_priority::get_location_prios(game_info, &self.location_priorities, true);
+
+        // own locations first, then the ones of allies
+        let (mut owned_locations, allied_locations): (Vec<Location>, Vec<Location>) =
+            location_prios
+                .into_iter()
+                .filter(|loc| {
+                    location::get_location_owner(loc, game_info)
+                        .is_some_and(|id| game_info.is_allied(&id))
+                })
+                .partition(|loc| {
+                    location::get_location_owner(loc, game_info) == Some(game_info.bot.id)
+                });
+        owned_locations.extend(allied_locations);
         let locations_under_attack: Vec<Location> = owned_locations
             .into_iter()
             .filter(|loc| {
+                // enemy squads can reach the location soon
                 let loc_pos = game_info.locations.get(loc).unwrap().position();
-                let enemies_in_range =
-                    game_info.get_enemy_squads_in_range(&loc_pos, DEFEND_LOCATION_AGGRO_RADIUS);
-                enemies_in_range.len() > 0
+                game_info.influence.is_threatened(&loc_pos)
             })
             .collect();
         locations_under_attack
@@ -783,3 +792,6 @@ impl MacroController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
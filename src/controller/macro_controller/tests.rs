@@ -0,0 +1,344 @@
+use api::sr_libs::utils::card_templates::CardTemplate::*;
+use api::*;
+
+use super::*;
+use crate::deck;
+use crate::strategy_config::StrategyConfig;
+use crate::test_fixtures::ScenarioBuilder;
+
+const POWER_SLOT_COST: f32 = 100.;
+const T2_TOKEN_SLOT_COST: f32 = 150.;
+const T3_TOKEN_SLOT_COST: f32 = 250.;
+
+// Scripted match: the bot's commands take effect instantly (squads spawn on the next tick,
+// move to their destination at once and structures are finished immediately) while the
+// opponent is fully controlled by the scenario steps.
+struct ScriptedMatch {
+    builder: ScenarioBuilder,
+    game_info: GameInfo,
+    macro_controller: MacroController,
+    command_scheduler: CommandScheduler,
+    current_tick: u32,
+}
+
+impl ScriptedMatch {
+    fn new(builder: ScenarioBuilder) -> Self {
+        let mut game_info = GameInfo::new();
+        game_info.set_map(&builder.map_info());
+        game_info.init(builder.start_state());
+
+        let config = StrategyConfig::default();
+
+        ScriptedMatch {
+            builder,
+            game_info,
+            macro_controller: MacroController::new(&config),
+            command_scheduler: CommandScheduler::new(config.command_scheduler),
+            current_tick: 0,
+        }
+    }
+
+    fn tick(&mut self) -> Vec<Command> {
+        self.current_tick += 1;
+        self.builder.tick(self.current_tick);
+        self.game_info.parse_state(self.builder.game_state());
+        self.command_scheduler.update_state(&self.game_info);
+
+        self.macro_controller
+            .tick(&mut self.game_info, &mut self.command_scheduler);
+
+        let commands = self.command_scheduler.get_scheduled_commands();
+        for command in commands.iter() {
+            self.apply_command(command);
+        }
+        commands
+    }
+
+    fn apply_command(&mut self, command: &Command) {
+        let bot_id = self.builder.bot_id();
+        match command {
+            Command::ProduceSquad { card_position, xy } => {
+                let card = deck::bot_deck().cards[*card_position as usize];
+                let card_cost = self
+                    .game_info
+                    .card_data
+                    .get_card_info(&card)
+                    .unwrap()
+                    .power_cost;
+                self.builder
+                    .bot_squads_at(card, 1, *xy, 1.)
+                    .spend_bot_power(card_cost);
+            }
+            Command::GroupGoto {
+                squads, positions, ..
+            } => {
+                self.builder.move_squads(squads, positions[0]);
+            }
+            Command::PowerSlotBuild { slot_id } => {
+                self.builder
+                    .set_power_slot_owner(*slot_id, Some(bot_id))
+                    .spend_bot_power(POWER_SLOT_COST);
+            }
+            Command::TokenSlotBuild { slot_id, color } => {
+                let orb_color = match color {
+                    CreateOrbColor::Shadow => OrbColor::Shadow,
+                    CreateOrbColor::Nature => OrbColor::Nature,
+                    CreateOrbColor::Frost => OrbColor::Frost,
+                    CreateOrbColor::Fire => OrbColor::Fire,
+                };
+                let orb_cost = match self.game_info.bot.token_slots.len() {
+                    1 => T2_TOKEN_SLOT_COST,
+                    _ => T3_TOKEN_SLOT_COST,
+                };
+                self.builder
+                    .set_token_slot_owner(*slot_id, Some(bot_id), orb_color)
+                    .spend_bot_power(orb_cost);
+            }
+            // fights are resolved by the scenario steps
+            _ => {}
+        }
+    }
+}
+
+struct Step {
+    // what happens in the match before the step is played
+    setup: fn(&mut ScenarioBuilder),
+    ticks: u32,
+    // states the MacroController is in after each tick of the step, without repetitions
+    states: Vec<MacroState>,
+    // kinds of commands which have to be scheduled during the step
+    commands: Vec<&'static str>,
+    // location the bot falls back to after the step
+    latest_owning_loc: Option<&'static str>,
+}
+
+struct Scenario {
+    name: &'static str,
+    setup: fn(&mut ScenarioBuilder),
+    steps: Vec<Step>,
+}
+
+fn command_kind(command: &Command) -> String {
+    // commands are serialized as {"<Kind>": {...}}
+    let value = serde_json::to_value(command).unwrap();
+    match value {
+        serde_json::Value::Object(map) => map.keys().next().unwrap().clone(),
+        serde_json::Value::String(kind) => kind,
+        _ => panic!("Unexpected command format {value:?}"),
+    }
+}
+
+fn run_scenario(scenario: &Scenario) {
+    let mut builder = ScenarioBuilder::new();
+    (scenario.setup)(&mut builder);
+    let mut scripted_match = ScriptedMatch::new(builder);
+
+    for (i, step) in scenario.steps.iter().enumerate() {
+        (step.setup)(&mut scripted_match.builder);
+
+        let mut states: Vec<MacroState> = vec![];
+        let mut command_kinds: Vec<String> = vec![];
+        for _ in 0..step.ticks {
+            let commands = scripted_match.tick();
+            command_kinds.extend(commands.iter().map(command_kind));
+
+            let state = scripted_match.macro_controller.state().clone();
+            if states.last() != Some(&state) {
+                states.push(state);
+            }
+        }
+
+        assert_eq!(
+            states, step.states,
+            "{}, step {i}: unexpected MacroState sequence",
+            scenario.name
+        );
+        for kind in step.commands.iter() {
+            assert!(
+                command_kinds.iter().any(|k| k == kind),
+                "{}, step {i}: expected a {kind} command, got {command_kinds:?}",
+                scenario.name
+            );
+        }
+        if let Some(name) = step.latest_owning_loc {
+            assert_eq!(
+                location::get_location_name(
+                    &scripted_match.macro_controller.latest_owning_loc,
+                    &scripted_match.game_info
+                ),
+                name,
+                "{}, step {i}: unexpected latest owning location",
+                scenario.name
+            );
+        }
+    }
+}
+
+fn no_changes(_: &mut ScenarioBuilder) {}
+
+#[test]
+fn macro_controller_scenarios() {
+    let scenarios = vec![
+        Scenario {
+            name: "early rush on the start location",
+            setup: no_changes,
+            steps: vec![
+                Step {
+                    setup: no_changes,
+                    ticks: 20,
+                    states: vec![MacroState::GroundPresenceNextLoc],
+                    commands: vec!["ProduceSquad", "GroupGoto"],
+                    latest_owning_loc: None,
+                },
+                // rush arrives at the start location before the bot took another one
+                Step {
+                    setup: |builder| {
+                        builder
+                            .opponent_squads(Dreadcharger, 2, "South", 1.)
+                            .bot_power(200.);
+                    },
+                    ticks: 20,
+                    states: vec![MacroState::Defend],
+                    commands: vec!["ProduceSquad", "GroupAttack"],
+                    latest_owning_loc: None,
+                },
+                Step {
+                    setup: |builder| {
+                        builder.kill_opponent_squads();
+                    },
+                    ticks: 5,
+                    states: vec![MacroState::GroundPresenceNextLoc],
+                    commands: vec!["GroupGoto"],
+                    latest_owning_loc: None,
+                },
+            ],
+        },
+        Scenario {
+            name: "losing the latest owning location",
+            setup: |builder| {
+                builder.bot_power(300.);
+            },
+            steps: vec![
+                Step {
+                    setup: no_changes,
+                    ticks: 20,
+                    states: vec![
+                        MacroState::GroundPresenceNextLoc,
+                        MacroState::TakeWell,
+                        MacroState::HealUnits,
+                        MacroState::GroundPresenceNextLoc,
+                    ],
+                    commands: vec!["PowerSlotBuild"],
+                    // Centersouth is next to the start location and preferred over Center
+                    latest_owning_loc: Some("Centersouth"),
+                },
+                // the well at Centersouth is destroyed -> fall back to the start location
+                Step {
+                    setup: |builder| {
+                        builder.free_location("Centersouth");
+                    },
+                    ticks: 5,
+                    states: vec![MacroState::GroundPresenceNextLoc],
+                    commands: vec![],
+                    latest_owning_loc: Some("South"),
+                },
+            ],
+        },
+        Scenario {
+            name: "opponent goes T2 first",
+            setup: |builder| {
+                // the starting orb gets the color of the first card played
+                builder.opponent_orb("North", OrbColor::Fire);
+            },
+            steps: vec![
+                Step {
+                    setup: no_changes,
+                    ticks: 20,
+                    states: vec![MacroState::GroundPresenceNextLoc],
+                    commands: vec!["ProduceSquad", "GroupGoto"],
+                    latest_owning_loc: None,
+                },
+                // bot is behind a tier and ahead in tempo -> take the orb at the next
+                // free location
+                Step {
+                    setup: |builder| {
+                        builder
+                            .opponent_orb("Northwest", OrbColor::Fire)
+                            .opponent_power(300.)
+                            .bot_power(200.);
+                    },
+                    ticks: 5,
+                    states: vec![
+                        MacroState::AdvanceTier,
+                        MacroState::HealUnits,
+                        MacroState::Defend,
+                    ],
+                    commands: vec!["TokenSlotBuild"],
+                    latest_owning_loc: Some("Centersouth"),
+                },
+            ],
+        },
+        Scenario {
+            name: "all squads die during AttackLoc",
+            setup: |builder| {
+                builder.opponent_wells("Center", 1);
+            },
+            steps: vec![
+                Step {
+                    setup: no_changes,
+                    ticks: 20,
+                    states: vec![MacroState::GroundPresenceNextLoc, MacroState::AttackLoc],
+                    commands: vec!["GroupAttack"],
+                    latest_owning_loc: None,
+                },
+                // opponent wins the fight and is ahead in tempo -> defend, then match the
+                // opponent's wells
+                Step {
+                    setup: |builder| {
+                        builder.kill_bot_squads().opponent_power(500.);
+                    },
+                    ticks: 5,
+                    states: vec![MacroState::Defend, MacroState::TakeWell],
+                    commands: vec![],
+                    latest_owning_loc: None,
+                },
+            ],
+        },
+    ];
+
+    for scenario in scenarios.iter() {
+        run_scenario(scenario);
+    }
+}
+
+#[test]
+fn defend_and_join_allies() {
+    let mut builder = ScenarioBuilder::new();
+    let ally_id = builder.add_player(true);
+    builder
+        .player_wells(ally_id, "Southwest", 1)
+        .opponent_wells("Northwest", 1)
+        .opponent_squads(Dreadcharger, 2, "Southwest", 1.);
+    let game_info = builder.game_info();
+    let macro_controller = MacroController::new(&StrategyConfig::default());
+    let loc = |name| location::get_location_by_name(name, &game_info).unwrap();
+
+    // the ally's location is attacked
+    assert_eq!(
+        macro_controller.get_locations_under_attack(&game_info),
+        vec![loc("Southwest")]
+    );
+
+    // the ally attacks a location of the opponent
+    builder
+        .kill_opponent_squads()
+        .player_squads(ally_id, Dreadcharger, 2, "Northwest", 1.);
+    let game_info = builder.game_info();
+    assert!(macro_controller
+        .get_locations_under_attack(&game_info)
+        .is_empty());
+    assert_eq!(
+        macro_controller.get_next_attack_focus_loc(&game_info),
+        loc("Northwest")
+    );
+}
@@ -3,15 +3,12 @@ use api::sr_libs::utils::card_templates::CardTemplate::*;
 use api::*;
 use log::*;
 
-use crate::bot::*;
 use crate::card_data::*;
 use crate::command_scheduler::CommandScheduler;
 use crate::controller::squad_controller::SquadController;
+use crate::deck;
 use crate::game_info::GameInfo;
-use crate::utils;
-
-// minimum difference in bound power to spawn a new squad when matching opponent's army size
-const MIN_POWER_DIFF_SPAWN: f32 = 30.;
+use crate::strategy_config::SpawnConfig;
 
 #[derive(PartialEq)]
 enum Tier {
@@ -20,11 +17,24 @@ enum Tier {
     Tier3,
 }
 
+impl Tier {
+    fn num_orbs(&self) -> i32 {
+        match self {
+            Tier::Tier1 => 1,
+            Tier::Tier2 => 2,
+            Tier::Tier3 => 3,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SpawnController {
+    config: SpawnConfig,
     state: SpawnControllerState,
     spawn_pos: Position2D,
     tier1_offense_spawn_policy: Option<Vec<CardTemplate>>,
+    // the T1 policy is based on the opponent's deck until the first orb is built
+    tier1_policy_from_deck: bool,
     tier2_offense_spawn_policy: Option<Vec<CardTemplate>>,
     tier3_offense_spawn_policy: Option<Vec<CardTemplate>>,
     in_offense: bool,
@@ -40,11 +50,13 @@ enum SpawnControllerState {
 }
 
 impl SpawnController {
-    pub fn new() -> SpawnController {
+    pub fn new(config: SpawnConfig) -> SpawnController {
         SpawnController {
+            config,
             state: SpawnControllerState::Waiting,
             spawn_pos: Position2D { x: 0., y: 0. },
             tier1_offense_spawn_policy: None,
+            tier1_policy_from_deck: false,
             tier2_offense_spawn_policy: None,
             tier3_offense_spawn_policy: None,
             in_offense: true,
@@ -83,7 +95,7 @@ impl SpawnController {
                 let bound_power_diff =
                     game_info.opponent.bound_power() - game_info.bot.bound_power();
 
-                if bound_power_diff >= MIN_POWER_DIFF_SPAWN
+                if bound_power_diff >= self.config.min_power_diff_spawn
                     && command_scheduler.card_can_be_played(next_card.clone(), game_info)
                 {
                     vec![self.spawn_squad(next_card, num_squads, game_info)]
@@ -151,18 +163,32 @@ impl SpawnController {
         let num_bot_token_slots = game_info.bot.token_slots.len();
         let num_opponent_token_slots = game_info.opponent.token_slots.len();
 
-        if num_opponent_token_slots == 1 && self.tier1_offense_spawn_policy.is_none() {
+        if num_opponent_token_slots == 1
+            && (self.tier1_offense_spawn_policy.is_none() || self.tier1_policy_from_deck)
+        {
             let token_slots: Vec<&TokenSlot> = game_info.opponent.token_slots.values().collect();
             let orb_color = token_slots[0].color;
 
-            if orb_color == OrbColor::Starting {
-                // wait for the first real orb
-                return;
+            if orb_color != OrbColor::Starting {
+                info!("Setting T1 offense spawn policy to {orb_color:?}");
+                self.tier1_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier1_offense_spawn_policy(orb_color),
+                    Tier::Tier1,
+                ));
+                self.tier1_policy_from_deck = false;
+            } else if self.tier1_offense_spawn_policy.is_none() {
+                // until the first real orb is built, expect the color of the opponent's T1 units
+                if let Some(orb_color) = game_info.opponent.deck.predict_first_orb() {
+                    info!(
+                        "Setting T1 offense spawn policy to {orb_color:?} from the opponent's deck"
+                    );
+                    self.tier1_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                        SpawnController::get_tier1_offense_spawn_policy(orb_color),
+                        Tier::Tier1,
+                    ));
+                    self.tier1_policy_from_deck = true;
+                }
             }
-
-            info!("Setting T1 offense spawn policy to {orb_color:?}");
-            self.tier1_offense_spawn_policy =
-                Some(SpawnController::get_tier1_offense_spawn_policy(orb_color));
         }
 
         if (num_bot_token_slots == 2 && self.tier2_offense_spawn_policy.is_none())
@@ -174,12 +200,22 @@ impl SpawnController {
                     game_info.opponent.token_slots.values().collect();
                 let orb_colors = (token_slots[0].color, token_slots[1].color);
                 info!("Setting T2 offense spawn policy to {orb_colors:?}");
-                self.tier2_offense_spawn_policy =
-                    Some(SpawnController::get_tier2_offense_spawn_policy(orb_colors));
+                self.tier2_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier2_offense_spawn_policy(orb_colors),
+                    Tier::Tier2,
+                ));
+            } else if let Some(orb_colors) = SpawnController::predict_tier2_colors(game_info) {
+                info!("Setting T2 offense spawn policy to predicted {orb_colors:?}");
+                self.tier2_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier2_offense_spawn_policy(orb_colors),
+                    Tier::Tier2,
+                ));
             } else {
                 info!("Setting T2 offense spawn policy to universal");
-                self.tier2_offense_spawn_policy =
-                    Some(SpawnController::get_tier2_univeral_spawn_policy());
+                self.tier2_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier2_univeral_spawn_policy(),
+                    Tier::Tier2,
+                ));
             }
         }
 
@@ -196,16 +232,30 @@ impl SpawnController {
                     token_slots[2].color,
                 );
                 info!("Setting T3 offense spawn policy to {orb_colors:?}");
-                self.tier3_offense_spawn_policy =
-                    Some(SpawnController::get_tier3_offense_spawn_policy(orb_colors));
+                self.tier3_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier3_offense_spawn_policy(orb_colors),
+                    Tier::Tier3,
+                ));
             } else {
                 info!("Setting T3 offense spawn policy to universal");
-                self.tier3_offense_spawn_policy =
-                    Some(SpawnController::get_tier3_univeral_spawn_policy());
+                self.tier3_offense_spawn_policy = Some(SpawnController::get_deck_policy(
+                    SpawnController::get_tier3_univeral_spawn_policy(),
+                    Tier::Tier3,
+                ));
             }
         }
     }
 
+    fn predict_tier2_colors(game_info: &GameInfo) -> Option<(OrbColor, OrbColor)> {
+        // the opponent is still T1, expect the second orb from their deck
+        match game_info.opponent.get_orbs().as_slice() {
+            [first_color] if *first_color != OrbColor::Starting => {
+                Some((*first_color, game_info.opponent.predict_next_orb()?))
+            }
+            _ => None,
+        }
+    }
+
     pub fn set_in_offense(&mut self, in_offense: bool) {
         if in_offense != self.in_offense {
             info!("SpawnController setting offense to {in_offense:?}");
@@ -213,7 +263,7 @@ impl SpawnController {
         self.in_offense = in_offense;
     }
 
-    fn get_tier1_card_policy(&self, game_info: &mut GameInfo) -> Option<Vec<CardTemplate>> {
+    fn get_tier1_card_policy(&self, game_info: &GameInfo) -> Option<Vec<CardTemplate>> {
         if self.in_offense {
             self.tier1_offense_spawn_policy.clone()
         } else {
@@ -221,7 +271,7 @@ impl SpawnController {
         }
     }
 
-    fn get_tier2_card_policy(&self, game_info: &mut GameInfo) -> Option<Vec<CardTemplate>> {
+    fn get_tier2_card_policy(&self, game_info: &GameInfo) -> Option<Vec<CardTemplate>> {
         if self.in_offense {
             self.tier2_offense_spawn_policy.clone()
         } else {
@@ -229,7 +279,7 @@ impl SpawnController {
         }
     }
 
-    fn get_tier3_card_policy(&self, game_info: &mut GameInfo) -> Option<Vec<CardTemplate>> {
+    fn get_tier3_card_policy(&self, game_info: &GameInfo) -> Option<Vec<CardTemplate>> {
         if self.in_offense {
             self.tier3_offense_spawn_policy.clone()
         } else {
@@ -239,7 +289,7 @@ impl SpawnController {
 
     fn get_next_card(
         &self,
-        game_info: &mut GameInfo,
+        game_info: &GameInfo,
         command_scheduler: &CommandScheduler,
     ) -> CardTemplate {
         let card_policy: Option<Vec<CardTemplate>>;
@@ -267,7 +317,7 @@ impl SpawnController {
         }
 
         if card_policy.is_none() {
-            return Dreadcharger;
+            return deck::bot_deck().get_tier_units(1)[0];
         }
 
         let num_squads = game_info.bot.squads.len();
@@ -280,70 +330,89 @@ impl SpawnController {
         }
     }
 
-    fn get_defense_spawn_policy(&self, game_info: &mut GameInfo, tier: Tier) -> Vec<CardTemplate> {
+    fn get_defense_spawn_policy(&self, game_info: &GameInfo, tier: Tier) -> Vec<CardTemplate> {
         // choose defending units based on attacking ones
-        let defender_indices: Vec<usize> = match tier {
-            Tier::Tier1 => BOT_T1_UNITS.to_vec(),
-            Tier::Tier2 => BOT_T2_UNITS.to_vec(),
-            Tier::Tier3 => BOT_T3_UNITS.to_vec(),
-        };
-
-        let opponent_squads: Vec<&Squad> = game_info.opponent.squads.values().collect();
-
-        if opponent_squads.len() == 0 {
-            return vec![BOT_CARDS[defender_indices[0]]];
-        }
-
-        let squad_ids: Vec<u32> = opponent_squads
+        let defenders = SpawnController::get_tier_units(&tier);
+        let defender_infos: Vec<(CardTemplate, CardInfo)> = defenders
             .iter()
-            .map(|&s| game_info.card_data.get_card_info_from_id(s.card_id.0).id)
+            .filter_map(|&card| Some((card, game_info.card_data.get_card_info(&card).ok()?)))
             .collect();
-        let most_common_squad_id = utils::most_frequent_element(squad_ids);
-
-        if let Some(squad_id) = most_common_squad_id {
-            let attacker = game_info.card_data.get_card_info_from_id(squad_id);
-
-            // best case: defender does not have matching defense type for attacker but
-            // it's offense type matches
-            for i in &defender_indices {
-                let card_id = BOT_DECK.cards[*i];
-                let defender = game_info.card_data.get_card_info_from_id(card_id.0);
-                if attacker.offense_type.to_string() != defender.defense_type.to_string()
-                    && attacker.defense_type.to_string() == defender.offense_type.to_string()
-                {
-                    return vec![BOT_CARDS[*i]];
-                }
-            }
 
-            // next best case: defender has correct offense type, but not when attacker is
-            // ranged and defender is melee (e.g. do not defend Sunstriders with Dreadcharger)
-            for i in &defender_indices {
-                let card_id = BOT_DECK.cards[*i];
-                let defender = game_info.card_data.get_card_info_from_id(card_id.0);
-                if attacker.defense_type.to_string() == defender.offense_type.to_string()
-                    && !(!attacker.melee
-                        && defender.melee
-                        && attacker.offense_type.to_string() == defender.defense_type.to_string())
-                {
-                    return vec![BOT_CARDS[*i]];
-                }
+        // predicted attacker squads with their weights, None if no units were played yet
+        let mut attackers: Option<Vec<(CardInfo, f32)>> = None;
+        if game_info.opponent.played_cards.num_played(CardType::Unit) > 0 {
+            // the units played so far, recent ones weigh more, even if they already died
+            let current_tick = game_info.current_tick.map_or(0, |tick| tick.0.get());
+            let predicted_units: Vec<(CardInfo, f32)> = game_info
+                .opponent
+                .predict_next_units(current_tick)
+                .into_iter()
+                .filter_map(|(card_id, weight)| {
+                    let card_info = game_info.card_data.get_card_info_from_id(card_id).ok()?;
+                    Some((card_info, weight))
+                })
+                .collect();
+            if predicted_units.is_empty() {
+                warn!("Unable to predict the next attacker squads");
+                return vec![defenders[0]];
             }
+            attackers = Some(predicted_units);
+        }
 
-            // least best case: defender does not have matching defense type
-            for i in &defender_indices {
-                let card_id = BOT_DECK.cards[*i];
-                let defender = game_info.card_data.get_card_info_from_id(card_id.0);
-                if attacker.offense_type.to_string() != defender.defense_type.to_string() {
-                    return vec![BOT_CARDS[*i]];
+        // defender which fares best against the attackers, earlier cards of the deck win ties
+        let mut best_defender: Option<(CardTemplate, f32)> = None;
+        for (card, defender) in defender_infos.iter() {
+            let score = match attackers.as_ref() {
+                Some(attackers) => {
+                    let total_weight: f32 = attackers.iter().map(|(_, weight)| weight).sum();
+                    let score: f32 = attackers
+                        .iter()
+                        .map(|(attacker, weight)| {
+                            weight * matchup::get_matchup_score(defender, attacker)
+                        })
+                        .sum();
+                    score / total_weight
                 }
+                // no units to react to yet, expect the units of the opponent's deck
+                None => {
+                    let deck = &game_info.opponent.deck;
+                    let Some(score) = deck.get_matchup_against(defender, tier.num_orbs()) else {
+                        continue;
+                    };
+                    score
+                }
+            };
+            if best_defender.map_or(true, |(_, best_score)| score > best_score) {
+                best_defender = Some((*card, score));
             }
+        }
 
+        match best_defender {
+            Some((card, _)) => vec![card],
             // still no defender found -> return the first one
-            vec![BOT_CARDS[defender_indices[0]]]
-        } else {
-            warn!("Unable to find the most common attacker squad");
-            return vec![BOT_CARDS[defender_indices[0]]];
+            None => vec![defenders[0]],
+        }
+    }
+
+    fn get_tier_units(tier: &Tier) -> &'static Vec<CardTemplate> {
+        match tier {
+            Tier::Tier1 => deck::bot_deck().get_tier_units(1),
+            Tier::Tier2 => deck::bot_deck().get_tier_units(2),
+            Tier::Tier3 => deck::bot_deck().get_tier_units(3),
+        }
+    }
+
+    fn get_deck_policy(policy: Vec<CardTemplate>, tier: Tier) -> Vec<CardTemplate> {
+        // only cards of the played deck can be spawned, otherwise fall back to all units of
+        // the tier
+        let deck_policy: Vec<CardTemplate> = policy
+            .into_iter()
+            .filter(|&card| deck::bot_deck().contains(card))
+            .collect();
+        if deck_policy.len() == 0 {
+            return SpawnController::get_tier_units(&tier).clone();
         }
+        deck_policy
     }
 
     fn get_tier1_offense_spawn_policy(opponent_color: OrbColor) -> Vec<CardTemplate> {
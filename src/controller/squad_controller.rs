@@ -1,16 +1,18 @@
 use api::sr_libs::utils::card_templates::CardTemplate;
-use api::Upgrade::U3;
 use api::*;
 use log::*;
 use std::num::NonZeroU32;
 
 use crate::game_info::GameInfo;
 
-use crate::bot::BOT_DECK;
 use crate::controller::Controller;
+use crate::deck;
+use crate::pathfinding;
 use crate::utils;
 
 const DEST_REACHED_MARGIN: f32 = 5.;
+// increase of the threat along the remaining route at which a new route is planned
+const REPLAN_THREAT_MARGIN: f32 = 50.;
 // max number of ticks to wait until the squad is spawned
 const SPAWN_WAIT_TIMEOUT_TICKS: u32 = 10;
 
@@ -20,6 +22,12 @@ pub struct SquadController {
     state: SquadControllerState,
     commands: Vec<Command>,
     current_destination: Option<Position2D>,
+    // remaining waypoints towards the current destination, the destination is the last one
+    waypoints: Vec<Position2D>,
+    // highest threat along the route when it was planned
+    route_threat: f32,
+    route_planned: bool,
+    force_move: bool,
     name: String,
     current_target: Option<EntityId>,
     command_sent_tick: Option<Tick>,
@@ -43,6 +51,10 @@ impl SquadController {
             state: SquadControllerState::NotInitialized,
             commands: vec![],
             current_destination: None,
+            waypoints: vec![],
+            route_threat: 0.,
+            route_planned: false,
+            force_move: false,
             name,
             current_target: None,
             command_sent_tick: None,
@@ -51,8 +63,7 @@ impl SquadController {
 
     pub fn spawn(&mut self, card: CardTemplate, position: Position2D, game_info: &GameInfo) {
         if self.state == SquadControllerState::NotInitialized {
-            let card_id = CardId::new(card, U3);
-            if let Some(card_pos) = BOT_DECK.cards.iter().position(|&c_id| c_id == card_id) {
+            if let Some(card_pos) = deck::bot_deck().get_card_position(card) {
                 self.commands.push(Command::ProduceSquad {
                     card_position: card_pos as u8,
                     xy: position,
@@ -60,7 +71,7 @@ impl SquadController {
                 self.command_sent_tick = game_info.current_tick;
                 self.enter_state(SquadControllerState::SpawnCommandSent);
             } else {
-                warn!("Unable to find deck position for card {:?}", card_id);
+                warn!("Unable to find deck position for card {:?}", card);
             }
         }
     }
@@ -70,7 +81,7 @@ impl SquadController {
             && self.state != SquadControllerState::SpawnCommandSent
     }
 
-    pub fn move_squad(&mut self, new_dest: Position2D, force: bool) {
+    pub fn move_squad(&mut self, new_dest: Position2D, force: bool, game_info: &GameInfo) {
         let new_destination_provided: bool;
         if let Some(cur_dest) = self.current_destination {
             if utils::dist(&cur_dest, &new_dest) < DEST_REACHED_MARGIN {
@@ -89,22 +100,19 @@ impl SquadController {
             && new_destination_provided)
             || self.state == SquadControllerState::Attacking
         {
-            self.commands.push(Command::GroupGoto {
-                squads: vec![self.entity_id],
-                positions: vec![new_dest],
-                walk_mode: if force {
-                    WalkMode::Force
-                } else {
-                    WalkMode::Normal
-                },
-                orientation: 0.,
-            });
             self.current_destination = Some(new_dest);
+            self.waypoints.clear();
+            self.route_planned = false;
+            self.force_move = force;
             self.enter_state(SquadControllerState::Moving);
             debug!(
                 "{:?} ({:?})) moving towards {:?}",
                 self.name, self.entity_id, self.current_destination
             );
+            // otherwise the route is planned in the tick once the squad position is known
+            if let Some(squad) = game_info.bot.squads.get(&self.entity_id) {
+                self.plan_route(&squad.entity.position.to_2d(), game_info);
+            }
         }
     }
 
@@ -143,6 +151,71 @@ impl SquadController {
         self.state == SquadControllerState::SpawnError
     }
 
+    fn plan_route(&mut self, squad_pos: &Position2D, game_info: &GameInfo) {
+        let Some(dest) = self.current_destination else {
+            return;
+        };
+        self.waypoints = pathfinding::find_path(squad_pos, &dest, game_info).unwrap_or_else(|| {
+            // fall back to walking there directly
+            vec![dest]
+        });
+        self.route_threat =
+            pathfinding::get_route_threat(squad_pos, &self.waypoints, &game_info.influence);
+        self.route_planned = true;
+        debug!(
+            "{:?} ({:?}) planned route {:?}",
+            self.name, self.entity_id, self.waypoints
+        );
+        self.goto_next_waypoint();
+    }
+
+    fn route_is_safe(&self, squad_pos: &Position2D, game_info: &GameInfo) -> bool {
+        // enemies did not move onto the remaining route since it was planned
+        let threat =
+            pathfinding::get_route_threat(squad_pos, &self.waypoints, &game_info.influence);
+        threat <= self.route_threat + REPLAN_THREAT_MARGIN
+    }
+
+    fn follow_route(&mut self, squad_pos: &Position2D) {
+        let num_reached = self
+            .waypoints
+            .iter()
+            .take_while(|waypoint| utils::dist(squad_pos, waypoint) < DEST_REACHED_MARGIN)
+            .count();
+        if num_reached == 0 {
+            return;
+        }
+
+        self.waypoints.drain(..num_reached);
+        if self.waypoints.is_empty() {
+            // squad is close enough to the destination that we handle this as if it
+            // reached it's destination
+            self.enter_state(SquadControllerState::Idling);
+            debug!(
+                "{:?} ({:?}) reached destination {:?}",
+                self.name, self.entity_id, self.current_destination
+            );
+        } else {
+            self.goto_next_waypoint();
+        }
+    }
+
+    fn goto_next_waypoint(&mut self) {
+        let Some(waypoint) = self.waypoints.first() else {
+            return;
+        };
+        self.commands.push(Command::GroupGoto {
+            squads: vec![self.entity_id],
+            positions: vec![*waypoint],
+            walk_mode: if self.force_move {
+                WalkMode::Force
+            } else {
+                WalkMode::Normal
+            },
+            orientation: 0.,
+        });
+    }
+
     fn enter_state(&mut self, new_state: SquadControllerState) {
         debug!(
             "{:?} ({:?}) entered state {:?}",
@@ -154,9 +227,6 @@ impl SquadController {
 
 impl Controller for SquadController {
     fn tick(&mut self, game_info: &GameInfo) -> Vec<Command> {
-        let new_commands = self.commands.clone();
-        self.commands.clear();
-
         if self.state == SquadControllerState::SpawnCommandSent {
             let num_new_squads = game_info.bot.new_squad_ids.len();
             if num_new_squads == 1 {
@@ -190,22 +260,24 @@ impl Controller for SquadController {
 
         if self.state == SquadControllerState::Moving {
             if let Some(squad) = game_info.bot.squads.get(&self.entity_id) {
-                if let Some(dest) = self.current_destination {
-                    if utils::dist(&squad.entity.position.to_2d(), &dest) < DEST_REACHED_MARGIN {
-                        // squad is close enough to the destination that we handle this as if it
-                        // reached it's destination
-                        self.enter_state(SquadControllerState::Idling);
-                        debug!(
-                            "{:?} ({:?}) reached destination {:?}",
-                            self.name, self.entity_id, self.current_destination
-                        );
-                    }
+                let squad_pos = squad.entity.position.to_2d();
+                if !self.route_planned {
+                    self.plan_route(&squad_pos, game_info);
+                } else if !self.route_is_safe(&squad_pos, game_info) {
+                    debug!(
+                        "{:?} ({:?}) route became unsafe, planning a new one",
+                        self.name, self.entity_id
+                    );
+                    self.plan_route(&squad_pos, game_info);
                 }
+                self.follow_route(&squad_pos);
             } else {
                 warn!("Unable to find squad for controller in game info");
             }
         }
 
+        let new_commands = self.commands.clone();
+        self.commands.clear();
         new_commands
     }
 }
@@ -0,0 +1,170 @@
+use api::sr_libs::utils::card_templates::CardTemplate;
+use api::Upgrade::U3;
+use api::*;
+use log::*;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::card_data::{CardData, CardInfo};
+use crate::orb_planner::OrbPlanner;
+
+// deck which is played when the strategy does not name a deck file
+const DEFAULT_DECK: &'static str = include_str!("../data/decks/shadow_frost.json");
+// the bot plays up to T3
+pub const NUM_TIERS: usize = 3;
+
+// set once at startup when the strategy names a deck file, otherwise the default deck is used
+static BOT_DECK: OnceLock<BotDeck> = OnceLock::new();
+
+pub fn set_bot_deck(deck: BotDeck) {
+    BOT_DECK.get_or_init(|| deck);
+}
+
+pub fn bot_deck() -> &'static BotDeck {
+    BOT_DECK
+        .get_or_init(|| BotDeck::from_json(DEFAULT_DECK).expect("Unable to load the default deck"))
+}
+
+#[derive(Deserialize)]
+struct DeckFile {
+    name: String,
+    #[serde(default)]
+    cover_card_index: u8,
+    cards: Vec<CardTemplate>,
+}
+
+#[derive(Debug)]
+pub struct BotDeck {
+    // deck as it is sent to the game
+    pub deck: Deck,
+    // same order as the cards of the deck, so the index is the card position
+    pub cards: Vec<CardTemplate>,
+    // units available at each tier, in deck order
+    tier_units: Vec<Vec<CardTemplate>>,
+    pub orb_planner: OrbPlanner,
+}
+
+impl BotDeck {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Unable to read {path}: {err}"))?;
+        BotDeck::from_json(&content).map_err(|err| format!("Invalid deck {path}: {err}"))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let deck_file: DeckFile = serde_json::from_str(content).map_err(|err| err.to_string())?;
+
+        let card_ids: Vec<CardId> = deck_file
+            .cards
+            .iter()
+            .map(|&card| CardId::new(card, U3))
+            .collect();
+        let num_cards = card_ids.len();
+        let cards: [CardId; 20] = card_ids
+            .try_into()
+            .map_err(|_| format!("A deck needs 20 cards, got {num_cards}"))?;
+
+        let mut card_data = CardData::new();
+        card_data.load();
+        let card_infos: Vec<CardInfo> = deck_file
+            .cards
+            .iter()
+            .map(|card| card_data.get_card_info(card))
+            .collect::<Result<_, String>>()?;
+
+        let mut tier_units = vec![];
+        for tier in 1..=NUM_TIERS {
+            // spells and buildings have no squads
+            let units: Vec<CardTemplate> = deck_file
+                .cards
+                .iter()
+                .zip(card_infos.iter())
+                .filter(|(_, info)| info.squad_size > 0)
+                .filter(|(_, info)| info.orb_requirements.total == tier as i32)
+                .map(|(&card, _)| card)
+                .collect();
+            if units.len() == 0 {
+                return Err(format!("No units for T{tier}"));
+            }
+            tier_units.push(units);
+        }
+
+        let orb_planner = OrbPlanner::new(&card_infos);
+
+        let bot_deck = BotDeck {
+            deck: Deck {
+                name: Cow::Owned(deck_file.name),
+                cover_card_index: deck_file.cover_card_index,
+                cards,
+            },
+            cards: deck_file.cards,
+            tier_units,
+            orb_planner,
+        };
+        info!(
+            "Loaded deck {:?}: orbs {:?}, units {:?}",
+            bot_deck.deck.name,
+            bot_deck.orb_planner.plan(&[], None),
+            bot_deck.tier_units
+        );
+        Ok(bot_deck)
+    }
+
+    pub fn get_card_position(&self, card: CardTemplate) -> Option<usize> {
+        self.cards.iter().position(|&c| c == card)
+    }
+
+    pub fn contains(&self, card: CardTemplate) -> bool {
+        self.cards.contains(&card)
+    }
+
+    pub fn get_tier_units(&self, tier: usize) -> &Vec<CardTemplate> {
+        &self.tier_units[tier.clamp(1, NUM_TIERS) - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn default_deck_tiers_and_orbs() {
+        let deck = BotDeck::from_json(DEFAULT_DECK).unwrap();
+
+        assert_eq!(
+            deck.get_tier_units(1),
+            &vec![Dreadcharger, Forsaken, NoxTrooper, Executor]
+        );
+        assert_eq!(
+            deck.get_tier_units(2),
+            &vec![
+                DarkelfAssassins,
+                Nightcrawler,
+                StormsingerANature,
+                LostReaverAShadow
+            ]
+        );
+        assert_eq!(
+            deck.get_tier_units(3),
+            &vec![TimelessOne, SilverwindLancers, Tremor, LostGrigoriAFire]
+        );
+        assert_eq!(
+            deck.orb_planner.plan(&[], None),
+            vec![
+                CreateOrbColor::Shadow,
+                CreateOrbColor::Frost,
+                CreateOrbColor::Frost
+            ]
+        );
+        assert_eq!(deck.get_card_position(Nightcrawler), Some(11));
+    }
+
+    #[test]
+    fn deck_needs_20_cards() {
+        let result = BotDeck::from_json(r#"{"name": "Small", "cards": ["Dreadcharger"]}"#);
+        assert!(result.is_err());
+    }
+}
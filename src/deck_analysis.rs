@@ -0,0 +1,312 @@
+use api::*;
+use log::*;
+use std::collections::BTreeMap;
+
+use crate::card_data::*;
+
+// Summary of a player's deck from the GameStartState. The cards of the opponent are known from
+// the start of the match, so spawns can be chosen against them before the first squad is seen.
+
+#[derive(Debug, Clone)]
+pub struct DeckAnalysis {
+    // card ids of the units by the number of orbs they require
+    pub units_by_tier: BTreeMap<i32, Vec<u32>>,
+    pub offense_types: BTreeMap<CardOffenseType, usize>,
+    pub defense_types: BTreeMap<CardDefenseType, usize>,
+    pub num_melee: usize,
+    pub num_ranged: usize,
+    pub num_flying: usize,
+    pub num_siege: usize,
+    pub num_buildings: usize,
+    pub spells: BTreeMap<SpellClass, usize>,
+    // summed up orb requirements of all cards
+    pub orb_requirements: CardOrbRequirements,
+    // orb requirements of the T1 units, which decide the first orb
+    tier1_orb_requirements: CardOrbRequirements,
+    // all cards of the deck
+    cards: Vec<CardInfo>,
+}
+
+impl DeckAnalysis {
+    pub fn new() -> DeckAnalysis {
+        DeckAnalysis {
+            units_by_tier: BTreeMap::new(),
+            offense_types: BTreeMap::new(),
+            defense_types: BTreeMap::new(),
+            num_melee: 0,
+            num_ranged: 0,
+            num_flying: 0,
+            num_siege: 0,
+            num_buildings: 0,
+            spells: BTreeMap::new(),
+            orb_requirements: CardInfo::new().orb_requirements,
+            tier1_orb_requirements: CardInfo::new().orb_requirements,
+            cards: vec![],
+        }
+    }
+
+    pub fn from_deck(cards: &[CardId], card_data: &CardData) -> DeckAnalysis {
+        let mut analysis = DeckAnalysis::new();
+
+        // empty deck slots have the id 0
+        for card_id in cards.iter().filter(|card_id| card_id.0 != 0) {
+            let card_info = match card_data.get_card_info_from_id(card_id.0) {
+                Ok(card_info) => card_info,
+                Err(err) => {
+                    warn!("Skipping card of the deck: {err}");
+                    continue;
+                }
+            };
+            add_orb_requirements(&mut analysis.orb_requirements, &card_info.orb_requirements);
+
+            match card_info.card_type {
+                CardType::Unit => analysis.add_unit(&card_info),
+                CardType::Building => analysis.num_buildings += 1,
+                CardType::Spell => {
+                    if let Some(spell_class) = card_info.spell_class {
+                        *analysis.spells.entry(spell_class).or_default() += 1;
+                    }
+                }
+            }
+            analysis.cards.push(card_info);
+        }
+
+        analysis
+    }
+
+    fn add_unit(&mut self, card_info: &CardInfo) {
+        let tier = card_info.orb_requirements.total;
+        self.units_by_tier
+            .entry(tier)
+            .or_default()
+            .push(card_info.id);
+        *self
+            .offense_types
+            .entry(card_info.offense_type)
+            .or_default() += 1;
+        *self
+            .defense_types
+            .entry(card_info.defense_type)
+            .or_default() += 1;
+
+        if card_info.melee {
+            self.num_melee += 1;
+        } else {
+            self.num_ranged += 1;
+        }
+        if card_info.flying {
+            self.num_flying += 1;
+        }
+        if card_info.siege {
+            self.num_siege += 1;
+        }
+        if tier == 1 {
+            add_orb_requirements(
+                &mut self.tier1_orb_requirements,
+                &card_info.orb_requirements,
+            );
+        }
+    }
+
+    pub fn num_units(&self) -> usize {
+        self.units_by_tier.values().map(|units| units.len()).sum()
+    }
+
+    pub fn get_tier_units(&self, tier: i32) -> &[u32] {
+        self.units_by_tier
+            .get(&tier)
+            .map(|units| units.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn get_matchup_against(&self, card: &CardInfo, tier: i32) -> Option<f32> {
+        // average matchup score against the units of the tier, falls back to lower tiers
+        for tier in (1..=tier).rev() {
+            let tier_units = self.get_tier_units(tier);
+            let units: Vec<CardInfo> = self
+                .cards
+                .iter()
+                .filter(|card| tier_units.contains(&card.id))
+                .cloned()
+                .collect();
+            if !units.is_empty() {
+                return Some(matchup::get_army_matchup_score(card, &units));
+            }
+        }
+        None
+    }
+
+    pub fn predict_first_orb(&self) -> Option<OrbColor> {
+        // color most required by the T1 units
+        let requirements = &self.tier1_orb_requirements;
+        let colors = [
+            (OrbColor::Fire, requirements.fire),
+            (OrbColor::Shadow, requirements.shadow),
+            (OrbColor::Nature, requirements.nature),
+            (OrbColor::Frost, requirements.frost),
+        ];
+        let mut predicted: Option<(OrbColor, i32)> = None;
+        for (color, num) in colors {
+            if num > 0 && predicted.map_or(true, |(_, max)| num > max) {
+                predicted = Some((color, num));
+            }
+        }
+        predicted.map(|(color, _)| color)
+    }
+
+    pub fn get_playable_cards(&self, card_type: CardType, orbs: &[OrbColor]) -> Vec<&CardInfo> {
+        self.cards
+            .iter()
+            .filter(|card| card.card_type == card_type)
+            .filter(|card| get_missing_orbs(&card.orb_requirements, orbs).total == 0)
+            .collect()
+    }
+
+    pub fn predict_next_orb(&self, orbs: &[OrbColor]) -> Option<OrbColor> {
+        // color missing for the most cards, cards which need fewer orbs count more
+        let mut scores = [0.; 4];
+        for card in self.cards.iter() {
+            let missing = get_missing_orbs(&card.orb_requirements, orbs);
+            if missing.total == 0 {
+                continue;
+            }
+            let mut colors =
+                [missing.fire, missing.shadow, missing.nature, missing.frost].map(|num| num as f32);
+            // a missing hybrid orb counts half for both of its colors
+            for color in missing.hybrid.into_iter().flatten() {
+                let index = match color {
+                    OrbColor::Fire => 0,
+                    OrbColor::Shadow => 1,
+                    OrbColor::Nature => 2,
+                    _ => 3,
+                };
+                colors[index] += 0.5;
+            }
+            for (score, num) in scores.iter_mut().zip(colors) {
+                *score += num / missing.total as f32;
+            }
+        }
+
+        let colors = [
+            OrbColor::Fire,
+            OrbColor::Shadow,
+            OrbColor::Nature,
+            OrbColor::Frost,
+        ];
+        let mut predicted: Option<(OrbColor, f32)> = None;
+        for (color, score) in colors.into_iter().zip(scores) {
+            if score > 0. && predicted.map_or(true, |(_, max)| score > max) {
+                predicted = Some((color, score));
+            }
+        }
+        predicted.map(|(color, _)| color)
+    }
+
+    pub fn log_summary(&self, player_id: &EntityId) {
+        let units_per_tier: BTreeMap<i32, usize> = self
+            .units_by_tier
+            .iter()
+            .map(|(tier, units)| (*tier, units.len()))
+            .collect();
+        info!(
+            "Deck of player {:?}: {} units {:?} per tier, offense {:?}, defense {:?}, \
+             {} melee, {} ranged, {} flying, {} siege, {} buildings, spells {:?}, \
+             predicted first orb {:?}",
+            player_id,
+            self.num_units(),
+            units_per_tier,
+            self.offense_types,
+            self.defense_types,
+            self.num_melee,
+            self.num_ranged,
+            self.num_flying,
+            self.num_siege,
+            self.num_buildings,
+            self.spells,
+            self.predict_first_orb()
+        );
+    }
+}
+
+fn add_orb_requirements(total: &mut CardOrbRequirements, requirements: &CardOrbRequirements) {
+    // hybrid orbs are only counted in the total
+    total.total += requirements.total;
+    total.neutral += requirements.neutral;
+    total.fire += requirements.fire;
+    total.shadow += requirements.shadow;
+    total.nature += requirements.nature;
+    total.frost += requirements.frost;
+}
+
+pub fn get_missing_orbs(
+    requirements: &CardOrbRequirements,
+    orbs: &[OrbColor],
+) -> CardOrbRequirements {
+    // orbs a player still has to build to play a card, the total includes the colored ones
+    let num_orbs = |color| orbs.iter().filter(|&&orb| orb == color).count() as i32;
+    let fire = (requirements.fire - num_orbs(OrbColor::Fire)).max(0);
+    let shadow = (requirements.shadow - num_orbs(OrbColor::Shadow)).max(0);
+    let nature = (requirements.nature - num_orbs(OrbColor::Nature)).max(0);
+    let frost = (requirements.frost - num_orbs(OrbColor::Frost)).max(0);
+    // the hybrid orb is missing if no orb of either color is left after the others
+    let required = |color| match color {
+        OrbColor::Fire => requirements.fire,
+        OrbColor::Shadow => requirements.shadow,
+        OrbColor::Nature => requirements.nature,
+        _ => requirements.frost,
+    };
+    let hybrid = requirements.hybrid.filter(|colors| {
+        colors
+            .iter()
+            .all(|&color| num_orbs(color) <= required(color))
+    });
+    let colored = fire + shadow + nature + frost + hybrid.map_or(0, |_| 1);
+    let total = (requirements.total - orbs.len() as i32).max(colored);
+    CardOrbRequirements {
+        total,
+        neutral: total - colored,
+        fire,
+        shadow,
+        nature,
+        frost,
+        hybrid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn analyse_recorded_opponent_deck() {
+        // Frost/Fire deck of the recorded start state
+        let builder = ScenarioBuilder::new();
+        let start_state = builder.start_state();
+        let opponent = start_state
+            .players
+            .iter()
+            .find(|p| p.entity.id == builder.opponent_id())
+            .unwrap();
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        let analysis = DeckAnalysis::from_deck(&opponent.deck.cards, &card_data);
+        assert_eq!(analysis.num_units(), 5);
+        assert_eq!(analysis.get_tier_units(1).len(), 2);
+        assert_eq!(analysis.num_buildings, 1);
+        assert_eq!(analysis.spells.get(&SpellClass::Spell), Some(&1));
+        assert_eq!(analysis.num_flying, 0);
+        assert_eq!(analysis.predict_first_orb(), Some(OrbColor::Frost));
+
+        // Dreadcharger counters the S defense of both T1 units
+        let dreadcharger = card_data.get_card_info(&Dreadcharger).unwrap();
+        let forsaken = card_data.get_card_info(&Forsaken).unwrap();
+        let dreadcharger_score = analysis.get_matchup_against(&dreadcharger, 1).unwrap();
+        assert!(dreadcharger_score > analysis.get_matchup_against(&forsaken, 1).unwrap());
+        // no T4 units, falls back to T3
+        assert!(analysis.get_matchup_against(&dreadcharger, 4).unwrap() < dreadcharger_score);
+        assert_eq!(analysis.get_matchup_against(&dreadcharger, 0), None);
+    }
+}
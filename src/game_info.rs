@@ -1,12 +1,19 @@
 use crate::card_data::CardData;
+use crate::card_tracker::CardTracker;
 use api::*;
 use log::*;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::num::NonZeroU32;
 
-use crate::location::{get_location_positions, Location, LocationPosition, TokenSubLocation};
+use crate::deck_analysis::DeckAnalysis;
+use crate::influence_map::{self, InfluenceMap};
+use crate::location;
+use crate::location::{Location, LocationPosition};
+use crate::location_graph::LocationGraph;
+use crate::maps::{self, MapDefinition};
 use crate::utils;
+use crate::walkability::WalkabilityGrid;
 
 // minimum distance required to build structure
 pub const GROUND_PRESENCE_MIN_DIST: f32 = 8.;
@@ -14,9 +21,19 @@ pub const GROUND_PRESENCE_MIN_DIST: f32 = 8.;
 pub struct GameInfo {
     pub state: Option<GameState>,
     pub bot: PlayerInfo,
+    // the opponent the strategy is played against, in 2v2 the one closest to the bot
     pub opponent: PlayerInfo,
+    // further players of the bot's team
+    pub allies: Vec<PlayerInfo>,
+    // further players of the opponent's team
+    pub other_opponents: Vec<PlayerInfo>,
     pub current_tick: Option<Tick>,
     pub locations: BTreeMap<Location, LocationPosition>,
+    pub location_graph: LocationGraph,
+    pub walkability: WalkabilityGrid,
+    pub influence: InfluenceMap,
+    // None for maps without a definition
+    pub map_definition: Option<MapDefinition>,
     pub figures: Vec<Figure>,
     pub card_data: CardData,
 }
@@ -26,6 +43,8 @@ impl fmt::Debug for GameInfo {
         f.debug_struct("GameInfo")
             .field("bot", &self.bot)
             .field("opponent", &self.opponent)
+            .field("allies", &self.allies)
+            .field("other_opponents", &self.other_opponents)
             .field("current_tick", &self.current_tick)
             .finish()
     }
@@ -49,6 +68,8 @@ pub struct PlayerInfo {
     pub new_token_slot_ids: Vec<EntityId>,
     pub destroyed_power_slot_ids: Vec<EntityId>,
     pub destroyed_token_slot_ids: Vec<EntityId>,
+    pub deck: DeckAnalysis,
+    pub played_cards: CardTracker,
 }
 
 impl GameInfo {
@@ -58,49 +79,25 @@ impl GameInfo {
 
         GameInfo {
             state: None,
-            bot: PlayerInfo {
-                id: EntityId(NonZeroU32::new(1).unwrap()),
-                team: 0,
-                power_slots: BTreeMap::new(),
-                token_slots: BTreeMap::new(),
-                power: 0.,
-                void_power: 0.,
-                tempo: 0.,
-                squads: BTreeMap::new(),
-                new_squad_ids: vec![],
-                dead_squad_ids: vec![],
-                start_token: None,
-                start_location: Location::Center,
-                new_power_slot_ids: vec![],
-                new_token_slot_ids: vec![],
-                destroyed_power_slot_ids: vec![],
-                destroyed_token_slot_ids: vec![],
-            },
-            opponent: PlayerInfo {
-                id: EntityId(NonZeroU32::new(1).unwrap()),
-                team: 0,
-                power_slots: BTreeMap::new(),
-                token_slots: BTreeMap::new(),
-                power: 0.,
-                void_power: 0.,
-                tempo: 0.,
-                squads: BTreeMap::new(),
-                new_squad_ids: vec![],
-                dead_squad_ids: vec![],
-                start_token: None,
-                start_location: Location::Center,
-                new_power_slot_ids: vec![],
-                new_token_slot_ids: vec![],
-                destroyed_power_slot_ids: vec![],
-                destroyed_token_slot_ids: vec![],
-            },
+            bot: PlayerInfo::new(EntityId(NonZeroU32::new(1).unwrap()), 0),
+            opponent: PlayerInfo::new(EntityId(NonZeroU32::new(1).unwrap()), 0),
+            allies: vec![],
+            other_opponents: vec![],
             current_tick: None,
             locations: BTreeMap::new(),
+            location_graph: LocationGraph::new(&BTreeMap::new()),
+            walkability: WalkabilityGrid::new(&[], &[], &[], &[]),
+            influence: InfluenceMap::new(&[]),
+            map_definition: None,
             figures: vec![],
             card_data,
         }
     }
 
+    pub fn set_map(&mut self, map_info: &MapInfo) {
+        self.map_definition = maps::get_map_definition(&map_info.map);
+    }
+
     pub fn init(&mut self, start_state: GameStartState) {
         debug!("Starting intializing game info");
 
@@ -120,152 +117,146 @@ impl GameInfo {
             );
         }
 
-        // find the opponent player id
-        let mut opponent_ids = vec![];
-        for player in &start_state.players {
-            if player.entity.id != self.bot.id {
-                opponent_ids.push(player.entity.id);
+        // sort the other players into allies and opponents by their team
+        let mut opponents = vec![];
+        for player in start_state.players.iter() {
+            if player.entity.id == self.bot.id {
+                continue;
+            }
+            let player_info = PlayerInfo::new(player.entity.id, player.entity.team);
+            if player.entity.team == self.bot.team {
+                self.allies.push(player_info);
+            } else {
+                opponents.push(player_info);
             }
         }
-        if opponent_ids.len() > 1 {
-            warn!(
-                "Found more than one opponent, choosing the first one ({:?})",
-                opponent_ids[0]
-            );
+        if opponents.is_empty() {
+            error!("Unable to find an opponent in GameStartState");
         }
-        self.opponent.id = opponent_ids[0];
 
-        // find the opponent's team
-        if let Some(opponent_team) = start_state
-            .players
+        let power_slots = &start_state.entities.power_slots;
+        let token_slots = &start_state.entities.token_slots;
+        self.locations = match &self.map_definition {
+            Some(definition) => {
+                location::get_locations_from_definition(definition, power_slots, token_slots)
+                    .unwrap_or_else(|| {
+                        warn!("Map definition does not match the map, discovering locations");
+                        location::discover_locations(power_slots, token_slots)
+                    })
+            }
+            None => location::discover_locations(power_slots, token_slots),
+        };
+        self.location_graph = LocationGraph::new(&self.locations);
+        let location_positions: Vec<Position2D> =
+            self.locations.values().map(|l| l.position()).collect();
+        self.influence = InfluenceMap::new(&location_positions);
+        self.walkability = WalkabilityGrid::new(
+            power_slots,
+            token_slots,
+            &start_state.entities.barrier_sets,
+            &start_state.entities.barrier_modules,
+        );
+
+        // the closest opponent is the main one, in 2v2 the one on the bot's side of the map
+        let start_tokens = &start_state.entities.token_slots;
+        let bot_start = self.get_start_location(&self.bot.id, start_tokens);
+        let opponent_dists: Vec<f32> = opponents
             .iter()
-            .find(|&p| p.entity.id == self.opponent.id)
+            .map(|opponent| {
+                let opponent_start = self.get_start_location(&opponent.id, start_tokens);
+                bot_start
+                    .zip(opponent_start)
+                    .and_then(|(a, b)| self.location_graph.travel_distance(&a, &b))
+                    .unwrap_or(f32::MAX)
+            })
+            .collect();
+        if let Some(index) =
+            (0..opponents.len()).min_by(|&a, &b| opponent_dists[a].total_cmp(&opponent_dists[b]))
         {
-            self.opponent.team = opponent_team.entity.team;
-        } else {
-            error!(
-                "Unable to find team for Opponent with id {:?} in GameStartState",
-                self.opponent.id
+            self.opponent = opponents.remove(index);
+        }
+        self.other_opponents = opponents;
+        if !self.allies.is_empty() || !self.other_opponents.is_empty() {
+            info!(
+                "Playing with allies {:?} against {:?} and {:?}",
+                self.allies.iter().map(|p| p.id).collect::<Vec<EntityId>>(),
+                self.opponent.id,
+                self.other_opponents
+                    .iter()
+                    .map(|p| p.id)
+                    .collect::<Vec<EntityId>>()
             );
         }
 
-        let mut location_positions = get_location_positions();
+        // analyse the decks, the opponents' cards are known from the start
+        for match_player in start_state.players.iter() {
+            let deck = DeckAnalysis::from_deck(&match_player.deck.cards, &self.card_data);
+            if let Some(player) = self.get_player_mut(&match_player.entity.id) {
+                player.deck = deck;
+            }
+        }
+        for opponent in self.opponents() {
+            opponent.deck.log_summary(&opponent.id);
+        }
 
+        // find power slots for each player
         for power_slot in start_state.entities.power_slots {
             let slot_id = power_slot.entity.id;
-
-            // assign power slot to it's location
-            let mut found_location: Option<Location> = None;
-            let mut found_power_index: Option<usize> = None;
-            let mut found_entity_id: Option<EntityId> = None;
-            for (location, location_pos) in location_positions.iter() {
-                for (i, pos_power_slot) in location_pos.powers.iter().enumerate() {
-                    let power_slot_x = power_slot.entity.position.to_2d().x;
-                    let power_slot_y = power_slot.entity.position.to_2d().y;
-                    if power_slot_x == pos_power_slot.position.x
-                        && power_slot_y == pos_power_slot.position.y
-                    {
-                        // set the entity id in location_positions
-                        found_location = Some(*location);
-                        found_power_index = Some(i);
-                        found_entity_id = Some(slot_id);
-                        debug!(
-                            "Assigned power slot {:?} to location {:?}.{:?}",
-                            slot_id, location, i
-                        );
-                    }
-                }
-            }
-
-            if let Some(location) = found_location {
-                location_positions.get_mut(&location).unwrap().powers[found_power_index.unwrap()]
-                    .entity_id = found_entity_id;
-            } else {
-                warn!("Unable to find location for power slot {:?}", slot_id);
-            }
-
-            // find power slots for each player
-            if let Some(power_slot_player_id) = power_slot.entity.player_entity_id {
-                if power_slot_player_id == self.bot.id {
-                    self.bot.power_slots.insert(slot_id, power_slot);
-                } else if power_slot_player_id == self.opponent.id {
-                    self.opponent.power_slots.insert(slot_id, power_slot);
+            if let Some(player_id) = power_slot.entity.player_entity_id {
+                if let Some(player) = self.get_player_mut(&player_id) {
+                    player.power_slots.insert(slot_id, power_slot);
                 }
             }
         }
 
+        // find token slots for each player
         for token_slot in start_state.entities.token_slots {
             let slot_id = token_slot.entity.id;
-
-            // assign token slot to it's location
-            let mut found_location: Option<Location> = None;
-            let mut found_entity_id: Option<EntityId> = None;
-            let mut found_pos_x: Option<f32> = None;
-            let mut found_pos_y: Option<f32> = None;
-            for (location, location_pos) in location_positions.iter() {
-                if let Some(pos_token_slot) = location_pos.token {
-                    let token_slot_x = token_slot.entity.position.to_2d().x;
-                    let token_slot_y = token_slot.entity.position.to_2d().y;
-                    if token_slot_x == pos_token_slot.position.x
-                        && token_slot_y == pos_token_slot.position.y
-                    {
-                        // set the entity id in location_positions
-                        found_location = Some(*location);
-                        found_entity_id = Some(slot_id);
-                        found_pos_x = Some(token_slot_x);
-                        found_pos_y = Some(token_slot_y);
-                        debug!(
-                            "Assigned token slot {:?} to location {:?}",
-                            slot_id, location
-                        );
-                    }
-                }
-            }
-
-            if let Some(location) = found_location {
-                location_positions.get_mut(&location).unwrap().token = Some(TokenSubLocation {
-                    position: Position2D {
-                        x: found_pos_x.unwrap(),
-                        y: found_pos_y.unwrap(),
-                    },
-                    entity_id: found_entity_id,
-                });
-            } else {
-                warn!("Unable to find location for token slot {:?}", slot_id);
-            }
-
-            // find token slots for each player
-            if let Some(token_slot_player_id) = token_slot.entity.player_entity_id {
-                if token_slot_player_id == self.bot.id {
-                    self.bot.token_slots.insert(slot_id, token_slot);
-                    self.bot.start_token = Some(slot_id);
-                } else if token_slot_player_id == self.opponent.id {
-                    self.opponent.token_slots.insert(slot_id, token_slot);
-                    self.opponent.start_token = Some(slot_id);
+            if let Some(player_id) = token_slot.entity.player_entity_id {
+                if let Some(player) = self.get_player_mut(&player_id) {
+                    player.token_slots.insert(slot_id, token_slot);
+                    player.start_token = Some(slot_id);
                 }
             }
         }
 
-        self.locations = location_positions;
-
-        // set stat locations
-        let north_token_id = self
-            .locations
-            .get(&Location::North)
-            .unwrap()
-            .token
-            .unwrap()
-            .entity_id
-            .unwrap();
-        if self.bot.token_slots.contains_key(&north_token_id) {
-            self.bot.start_location = Location::North;
-            self.opponent.start_location = Location::South;
-        } else if self.opponent.token_slots.contains_key(&north_token_id) {
-            self.bot.start_location = Location::South;
-            self.opponent.start_location = Location::North;
-        } else {
+        // set start locations
+        let start_locations: Vec<(EntityId, Location)> = self
+            .players()
+            .filter_map(|player| {
+                let start_token = player.start_token?;
+                let loc = location::get_location_from_entity_id(&start_token, self)?;
+                Some((player.id, loc))
+            })
+            .collect();
+        for (player_id, loc) in start_locations {
+            self.get_player_mut(&player_id).unwrap().start_location = loc;
+        }
+        if self.bot.start_location == Location::UNKNOWN
+            || self.opponent.start_location == Location::UNKNOWN
+        {
             warn!("Unable to find start locations");
         }
+        if let Some(definition) = &self.map_definition {
+            let start_locations: Vec<Location> = definition
+                .start_locations
+                .iter()
+                .filter_map(|name| location::get_location_by_name(name, self))
+                .collect();
+            for start_location in [self.bot.start_location, self.opponent.start_location] {
+                if !start_locations.contains(&start_location) {
+                    warn!(
+                        "{} is not a start location of the map definition",
+                        location::get_location_name(&start_location, self)
+                    );
+                }
+            }
+        }
+        info!(
+            "Bot starts at {}, opponent at {}",
+            location::get_location_name(&self.bot.start_location, self),
+            location::get_location_name(&self.opponent.start_location, self)
+        );
 
         self.figures = start_state.entities.figures;
 
@@ -276,244 +267,33 @@ impl GameInfo {
         self.current_tick = Some(state.current_tick);
         debug!("{:?}", self.current_tick.unwrap());
 
-        // clear new squads as they are not new this tick anymore
-        self.bot.new_squad_ids.clear();
-        self.opponent.new_squad_ids.clear();
+        // barriers can be built or destroyed during the match
+        self.walkability.update_barriers(
+            &state.entities.barrier_sets,
+            &state.entities.barrier_modules,
+        );
 
-        // clear dead squads
-        self.bot.dead_squad_ids.clear();
-        self.opponent.dead_squad_ids.clear();
-
-        // clear new power and token slots
-        self.bot.new_power_slot_ids.clear();
-        self.opponent.new_power_slot_ids.clear();
-        self.bot.new_token_slot_ids.clear();
-        self.opponent.new_token_slot_ids.clear();
-
-        // clear destroyed power and token slots
-        self.bot.destroyed_power_slot_ids.clear();
-        self.opponent.destroyed_power_slot_ids.clear();
-        self.bot.destroyed_token_slot_ids.clear();
-        self.opponent.destroyed_token_slot_ids.clear();
-
-        // set power for each player
-        for player in &state.players {
-            if player.id == self.bot.id {
-                self.bot.power = player.power;
-                self.bot.void_power = player.void_power;
-            } else if player.id == self.opponent.id {
-                self.opponent.power = player.power;
-                self.opponent.void_power = player.void_power;
-            }
+        for player in self.players_mut() {
+            player.parse_state(&state);
         }
 
-        // assign units
         for squad in state.entities.squads.iter() {
-            let squad_entity_id = squad.entity.id;
-            if let Some(squad_player_id) = squad.entity.player_entity_id {
-                if squad_player_id == self.bot.id {
-                    if let None = self.bot.squads.insert(squad_entity_id, squad.clone()) {
-                        // the squad did not exist before
-                        debug!("New squad {:?} was spawned for bot", squad_entity_id);
-                        self.bot.new_squad_ids.push(squad_entity_id);
-                    }
-                } else if squad_player_id == self.opponent.id {
-                    if let None = self.opponent.squads.insert(squad_entity_id, squad.clone()) {
-                        // the squad did not exist before
-                        debug!("New squad {:?} was spawned for opponent", squad_entity_id);
-                        self.opponent.new_squad_ids.push(squad_entity_id);
-                    }
-                }
-            } else {
+            if squad.entity.player_entity_id.is_none() {
                 warn!("Found squad {:?} not belonging to any player", squad);
             }
         }
 
-        // assign dead units
-        for entity_id in self.bot.squads.keys() {
-            let state_entity_ids: Vec<EntityId> =
-                state.entities.squads.iter().map(|s| s.entity.id).collect();
-            if !state_entity_ids.contains(entity_id) {
-                // entity_id is not in the state anymore -> died
-                self.bot.dead_squad_ids.push(*entity_id);
-            }
-        }
-        for entity_id in self.opponent.squads.keys() {
-            let state_entity_ids: Vec<EntityId> =
-                state.entities.squads.iter().map(|s| s.entity.id).collect();
-            if !state_entity_ids.contains(entity_id) {
-                // entity_id is not in the state anymore -> died
-                self.opponent.dead_squad_ids.push(*entity_id);
-            }
-        }
-
-        // remove dead units
-        for entity_id in self.bot.dead_squad_ids.iter() {
-            if let Some(removed_entity) = self.bot.squads.remove(entity_id) {
-                debug!(
-                    "Removed dead squad {:?} from bot squads",
-                    removed_entity.entity.id
-                );
-            } else {
-                warn!("Did not find dead squad {:?} in bot squads", entity_id);
-            }
-        }
-        for entity_id in self.opponent.dead_squad_ids.iter() {
-            if let Some(removed_entity) = self.opponent.squads.remove(entity_id) {
-                debug!(
-                    "Removed dead squad {:?} from opponent squads",
-                    removed_entity.entity.id
-                );
-            } else {
-                warn!("Did not find dead squad {:?} in opponent squads", entity_id);
-            }
-        }
-
-        // asign power slots
-        for power_slot in state.entities.power_slots.iter() {
-            let slot_id = power_slot.entity.id;
-            if let Some(player_id) = power_slot.entity.player_entity_id {
-                if player_id == self.bot.id {
-                    if let None = self.bot.power_slots.insert(slot_id, power_slot.clone()) {
-                        info!("New power slot {:?} created for bot", slot_id);
-                        self.bot.new_power_slot_ids.push(slot_id);
-                    }
-                } else if player_id == self.opponent.id {
-                    if let None = self
-                        .opponent
-                        .power_slots
-                        .insert(slot_id, power_slot.clone())
-                    {
-                        info!("New power slot {:?} created for opponent", slot_id);
-                        self.opponent.new_power_slot_ids.push(slot_id);
-                    }
-                }
-            }
-        }
-
-        // assign token slots
-        for token_slot in state.entities.token_slots.iter() {
-            let slot_id = token_slot.entity.id;
-            if let Some(player_id) = token_slot.entity.player_entity_id {
-                if player_id == self.bot.id {
-                    if let None = self.bot.token_slots.insert(slot_id, token_slot.clone()) {
-                        info!("New token slot {:?} created for bot", slot_id);
-                        self.bot.new_token_slot_ids.push(slot_id);
-                    }
-                } else if player_id == self.opponent.id {
-                    if let None = self
-                        .opponent
-                        .token_slots
-                        .insert(slot_id, token_slot.clone())
-                    {
-                        info!("New token slot {:?} created for opponent", slot_id);
-                        self.opponent.new_token_slot_ids.push(slot_id);
-                    }
-                }
-            }
-        }
-
-        // assign destroyed power slots
-        for power_slot_id in self.bot.power_slots.keys() {
-            for power_slot in state.entities.power_slots.iter() {
-                if power_slot.entity.id == *power_slot_id
-                    && power_slot.entity.player_entity_id.is_none()
-                {
-                    self.bot.destroyed_power_slot_ids.push(*power_slot_id);
-                    break;
-                }
-            }
-        }
-        for power_slot_id in self.opponent.power_slots.keys() {
-            for power_slot in state.entities.power_slots.iter() {
-                if power_slot.entity.id == *power_slot_id
-                    && power_slot.entity.player_entity_id.is_none()
-                {
-                    self.opponent.destroyed_power_slot_ids.push(*power_slot_id);
-                    break;
-                }
-            }
-        }
-
-        // remove destroyed power slots
-        for slot_id in self.bot.destroyed_power_slot_ids.iter() {
-            if let Some(removed_slot) = self.bot.power_slots.remove(slot_id) {
-                info!(
-                    "Removed destroyed power slot {:?} from bot",
-                    removed_slot.entity.id
-                );
-            } else {
-                warn!("Did not find destroyed power slot {:?} for bot", slot_id);
-            }
-        }
-        for slot_id in self.opponent.destroyed_power_slot_ids.iter() {
-            if let Some(removed_slot) = self.opponent.power_slots.remove(slot_id) {
-                info!(
-                    "Removed destroyed power slot {:?} from opponent",
-                    removed_slot.entity.id
-                );
-            } else {
-                warn!(
-                    "Did not find destroyed power slot {:?} for opponent",
-                    slot_id
-                );
-            }
-        }
-
-        // assign destroyed token slots
-        for token_slot_id in self.bot.token_slots.keys() {
-            for token_slot in state.entities.token_slots.iter() {
-                if token_slot.entity.id == *token_slot_id
-                    && token_slot.entity.player_entity_id.is_none()
-                {
-                    self.bot.destroyed_token_slot_ids.push(*token_slot_id);
-                    break;
-                }
-            }
-        }
-        for token_slot_id in self.opponent.token_slots.keys() {
-            for token_slot in state.entities.token_slots.iter() {
-                if token_slot.entity.id == *token_slot_id
-                    && token_slot.entity.player_entity_id.is_none()
-                {
-                    self.opponent.destroyed_token_slot_ids.push(*token_slot_id);
-                    break;
-                }
-            }
-        }
-
-        // remove destroyed token slots
-        for slot_id in self.bot.destroyed_token_slot_ids.iter() {
-            if let Some(removed_slot) = self.bot.token_slots.remove(slot_id) {
-                info!(
-                    "Removed destroyed token slot {:?} from bot",
-                    removed_slot.entity.id
-                );
-            } else {
-                warn!("Did not find destroyed token slot {:?} for bot", slot_id);
-            }
-        }
-        for slot_id in self.opponent.destroyed_token_slot_ids.iter() {
-            if let Some(removed_slot) = self.opponent.token_slots.remove(slot_id) {
-                info!(
-                    "Removed destroyed token slot {:?} from opponent",
-                    removed_slot.entity.id
-                );
-            } else {
-                warn!(
-                    "Did not find destroyed token slot {:?} for opponent",
-                    slot_id
-                );
-            }
-        }
-
         // set figures
         self.figures = state.entities.figures;
+
+        // squad health is only known after the figures are set
+        let influence_sources = influence_map::get_influence_sources(self);
+        self.influence.update(&influence_sources);
     }
 
     pub fn get_enemy_squads_in_range(&self, center: &Position2D, radius: f32) -> Vec<Squad> {
         let mut enemy_squads_in_range: Vec<Squad> = vec![];
-        for squad in self.opponent.squads.values() {
+        for squad in self.opponents().flat_map(|p| p.squads.values()) {
             let dist = utils::dist(center, &squad.entity.position.to_2d());
             if dist < radius {
                 enemy_squads_in_range.push(squad.clone());
@@ -522,6 +302,16 @@ impl GameInfo {
         enemy_squads_in_range
     }
 
+    pub fn get_threatening_enemy_squads(&self, pos: &Position2D) -> Vec<Squad> {
+        // enemy squads whose influence reaches the position
+        let squad_ids = self.influence.get_threatening_squads(pos);
+        self.opponents()
+            .flat_map(|p| p.squads.values())
+            .filter(|squad| squad_ids.contains(&squad.entity.id))
+            .cloned()
+            .collect()
+    }
+
     pub fn get_enemy_structures_in_range(
         &self,
         center: &Position2D,
@@ -530,7 +320,7 @@ impl GameInfo {
         let mut enemy_structures_in_range: Vec<&EntityId> = vec![];
 
         // power slots
-        for (entity_id, power_slot) in self.opponent.power_slots.iter() {
+        for (entity_id, power_slot) in self.opponents().flat_map(|p| p.power_slots.iter()) {
             let dist = utils::dist(center, &power_slot.entity.position.to_2d());
             if dist < radius {
                 enemy_structures_in_range.push(entity_id);
@@ -538,7 +328,7 @@ impl GameInfo {
         }
 
         // token slots
-        for (entity_id, token_slot) in self.opponent.token_slots.iter() {
+        for (entity_id, token_slot) in self.opponents().flat_map(|p| p.token_slots.iter()) {
             let dist = utils::dist(center, &token_slot.entity.position.to_2d());
             if dist < radius {
                 enemy_structures_in_range.push(entity_id);
@@ -561,18 +351,13 @@ impl GameInfo {
 
     pub fn get_squad_health(&self, entity_id: &EntityId) -> (f32, f32) {
         // get the current and max health of a squad
-        let squad: &Squad;
-        if self.bot.squads.contains_key(entity_id) {
-            squad = self.bot.squads.get(entity_id).unwrap();
-        } else if self.opponent.squads.contains_key(entity_id) {
-            squad = self.opponent.squads.get(entity_id).unwrap();
-        } else {
+        let Some(squad) = self.players().find_map(|p| p.squads.get(entity_id)) else {
             error!(
                 "Unable to get health for squad {:?} as it does not exist",
                 entity_id
             );
             return (0., 0.);
-        }
+        };
 
         let mut cur_hp: f32 = 0.;
         let mut max_hp: f32 = 0.;
@@ -611,19 +396,16 @@ impl GameInfo {
     }
 
     pub fn get_structure_health(&self, entity_id: &EntityId) -> (f32, f32) {
-        let entity: &Entity;
-        if self.bot.power_slots.contains_key(&entity_id) {
-            entity = &self.bot.power_slots.get(&entity_id).unwrap().entity;
-        } else if self.opponent.power_slots.contains_key(&entity_id) {
-            entity = &self.opponent.power_slots.get(&entity_id).unwrap().entity;
-        } else if self.bot.token_slots.contains_key(&entity_id) {
-            entity = &self.bot.token_slots.get(&entity_id).unwrap().entity;
-        } else if self.opponent.token_slots.contains_key(&entity_id) {
-            entity = &self.opponent.token_slots.get(&entity_id).unwrap().entity;
-        } else {
+        let entity = self.players().find_map(|p| {
+            p.power_slots
+                .get(entity_id)
+                .map(|slot| &slot.entity)
+                .or(p.token_slots.get(entity_id).map(|slot| &slot.entity))
+        });
+        let Some(entity) = entity else {
             error!("Unable to get health for structure {entity_id:?} as it does not exist");
             return (0., 0.);
-        }
+        };
 
         let mut found_health_aspect = false;
         let mut cur_hp: f32 = 0.;
@@ -660,6 +442,62 @@ impl GameInfo {
         self.bot.token_slots.len() as i32 - self.opponent.token_slots.len() as i32
     }
 
+    pub fn players(&self) -> impl Iterator<Item = &PlayerInfo> {
+        // the bot first, then the main opponent
+        std::iter::once(&self.bot)
+            .chain(std::iter::once(&self.opponent))
+            .chain(self.allies.iter())
+            .chain(self.other_opponents.iter())
+    }
+
+    fn players_mut(&mut self) -> impl Iterator<Item = &mut PlayerInfo> {
+        std::iter::once(&mut self.bot)
+            .chain(std::iter::once(&mut self.opponent))
+            .chain(self.allies.iter_mut())
+            .chain(self.other_opponents.iter_mut())
+    }
+
+    pub fn opponents(&self) -> impl Iterator<Item = &PlayerInfo> {
+        std::iter::once(&self.opponent).chain(self.other_opponents.iter())
+    }
+
+    pub fn get_player(&self, player_id: &EntityId) -> Option<&PlayerInfo> {
+        self.players().find(|p| p.id == *player_id)
+    }
+
+    fn get_player_mut(&mut self, player_id: &EntityId) -> Option<&mut PlayerInfo> {
+        self.players_mut().find(|p| p.id == *player_id)
+    }
+
+    pub fn is_allied(&self, player_id: &EntityId) -> bool {
+        // the bot itself or one of its allies
+        self.get_player(player_id)
+            .is_some_and(|p| p.team == self.bot.team)
+    }
+
+    pub fn is_opponent(&self, player_id: &EntityId) -> bool {
+        self.opponents().any(|p| p.id == *player_id)
+    }
+
+    pub fn get_opponent_power_slot(&self, entity_id: &EntityId) -> Option<&PowerSlot> {
+        self.opponents().find_map(|p| p.power_slots.get(entity_id))
+    }
+
+    pub fn get_opponent_token_slot(&self, entity_id: &EntityId) -> Option<&TokenSlot> {
+        self.opponents().find_map(|p| p.token_slots.get(entity_id))
+    }
+
+    fn get_start_location(
+        &self,
+        player_id: &EntityId,
+        token_slots: &[TokenSlot],
+    ) -> Option<Location> {
+        let start_token = token_slots
+            .iter()
+            .find(|slot| slot.entity.player_entity_id == Some(*player_id))?;
+        location::get_location_from_entity_id(&start_token.entity.id, self)
+    }
+
     pub fn seconds_have_passed(&self, seconds: u32) -> bool {
         // 1 Tick = 100 ms -> 10 Ticks = 1s
         let seconds_in_ticks = NonZeroU32::new(seconds * 10).unwrap();
@@ -669,6 +507,197 @@ impl GameInfo {
 }
 
 impl PlayerInfo {
+    pub fn new(id: EntityId, team: u8) -> PlayerInfo {
+        PlayerInfo {
+            id,
+            team,
+            power_slots: BTreeMap::new(),
+            token_slots: BTreeMap::new(),
+            power: 0.,
+            void_power: 0.,
+            tempo: 0.,
+            squads: BTreeMap::new(),
+            new_squad_ids: vec![],
+            dead_squad_ids: vec![],
+            start_token: None,
+            start_location: Location::UNKNOWN,
+            new_power_slot_ids: vec![],
+            new_token_slot_ids: vec![],
+            destroyed_power_slot_ids: vec![],
+            destroyed_token_slot_ids: vec![],
+            deck: DeckAnalysis::new(),
+            played_cards: CardTracker::new(),
+        }
+    }
+
+    fn parse_state(&mut self, state: &GameState) {
+        // clear new squads as they are not new this tick anymore
+        self.new_squad_ids.clear();
+
+        // clear dead squads
+        self.dead_squad_ids.clear();
+
+        // clear new and destroyed power and token slots
+        self.new_power_slot_ids.clear();
+        self.new_token_slot_ids.clear();
+        self.destroyed_power_slot_ids.clear();
+        self.destroyed_token_slot_ids.clear();
+
+        // set power
+        if let Some(player) = state.players.iter().find(|p| p.id == self.id) {
+            self.power = player.power;
+            self.void_power = player.void_power;
+        }
+
+        // assign units
+        for squad in state.entities.squads.iter() {
+            if squad.entity.player_entity_id != Some(self.id) {
+                continue;
+            }
+            let squad_entity_id = squad.entity.id;
+            if let None = self.squads.insert(squad_entity_id, squad.clone()) {
+                // the squad did not exist before
+                debug!(
+                    "New squad {:?} was spawned for player {:?}",
+                    squad_entity_id, self.id
+                );
+                self.new_squad_ids.push(squad_entity_id);
+            }
+        }
+
+        // assign dead units
+        let state_entity_ids: Vec<EntityId> =
+            state.entities.squads.iter().map(|s| s.entity.id).collect();
+        for entity_id in self.squads.keys() {
+            if !state_entity_ids.contains(entity_id) {
+                // entity_id is not in the state anymore -> died
+                self.dead_squad_ids.push(*entity_id);
+            }
+        }
+
+        // remove dead units
+        for entity_id in self.dead_squad_ids.iter() {
+            if let Some(removed_entity) = self.squads.remove(entity_id) {
+                debug!(
+                    "Removed dead squad {:?} from squads of player {:?}",
+                    removed_entity.entity.id, self.id
+                );
+            } else {
+                warn!(
+                    "Did not find dead squad {:?} in squads of player {:?}",
+                    entity_id, self.id
+                );
+            }
+        }
+
+        // asign power slots
+        for power_slot in state.entities.power_slots.iter() {
+            if power_slot.entity.player_entity_id != Some(self.id) {
+                continue;
+            }
+            let slot_id = power_slot.entity.id;
+            if let None = self.power_slots.insert(slot_id, power_slot.clone()) {
+                info!(
+                    "New power slot {:?} created for player {:?}",
+                    slot_id, self.id
+                );
+                self.new_power_slot_ids.push(slot_id);
+            }
+        }
+
+        // assign token slots
+        for token_slot in state.entities.token_slots.iter() {
+            if token_slot.entity.player_entity_id != Some(self.id) {
+                continue;
+            }
+            let slot_id = token_slot.entity.id;
+            if let None = self.token_slots.insert(slot_id, token_slot.clone()) {
+                info!(
+                    "New token slot {:?} created for player {:?}",
+                    slot_id, self.id
+                );
+                self.new_token_slot_ids.push(slot_id);
+            }
+        }
+
+        // assign destroyed power slots
+        for power_slot_id in self.power_slots.keys() {
+            for power_slot in state.entities.power_slots.iter() {
+                if power_slot.entity.id == *power_slot_id
+                    && power_slot.entity.player_entity_id.is_none()
+                {
+                    self.destroyed_power_slot_ids.push(*power_slot_id);
+                    break;
+                }
+            }
+        }
+
+        // remove destroyed power slots
+        for slot_id in self.destroyed_power_slot_ids.iter() {
+            if let Some(removed_slot) = self.power_slots.remove(slot_id) {
+                info!(
+                    "Removed destroyed power slot {:?} from player {:?}",
+                    removed_slot.entity.id, self.id
+                );
+            } else {
+                warn!(
+                    "Did not find destroyed power slot {:?} for player {:?}",
+                    slot_id, self.id
+                );
+            }
+        }
+
+        // assign destroyed token slots
+        for token_slot_id in self.token_slots.keys() {
+            for token_slot in state.entities.token_slots.iter() {
+                if token_slot.entity.id == *token_slot_id
+                    && token_slot.entity.player_entity_id.is_none()
+                {
+                    self.destroyed_token_slot_ids.push(*token_slot_id);
+                    break;
+                }
+            }
+        }
+
+        // remove destroyed token slots
+        for slot_id in self.destroyed_token_slot_ids.iter() {
+            if let Some(removed_slot) = self.token_slots.remove(slot_id) {
+                info!(
+                    "Removed destroyed token slot {:?} from player {:?}",
+                    removed_slot.entity.id, self.id
+                );
+            } else {
+                warn!(
+                    "Did not find destroyed token slot {:?} for player {:?}",
+                    slot_id, self.id
+                );
+            }
+        }
+
+        // track the played cards, squads keep their card after they died
+        let new_squad_card_ids: Vec<u32> = self
+            .new_squad_ids
+            .iter()
+            .map(|squad_id| self.squads[squad_id].card_id.0)
+            .collect();
+        let orbs = self.get_orbs();
+        self.played_cards
+            .update(&self.id, state, &new_squad_card_ids, &self.deck, &orbs);
+    }
+
+    pub fn get_orbs(&self) -> Vec<OrbColor> {
+        self.token_slots.values().map(|slot| slot.color).collect()
+    }
+
+    pub fn predict_next_units(&self, current_tick: u32) -> Vec<(u32, f32)> {
+        self.played_cards
+            .predict_next_units(&self.deck, &self.get_orbs(), current_tick)
+    }
+
+    pub fn predict_next_orb(&self) -> Option<OrbColor> {
+        self.deck.predict_next_orb(&self.get_orbs())
+    }
+
     pub fn get_closest_slot(&self, pos: &Position2D) -> Option<EntityId> {
         // find closest power slot
         let mut nearest_slot: Option<EntityId> = None;
@@ -0,0 +1,292 @@
+use api::*;
+use log::*;
+
+use crate::game_info::GameInfo;
+use crate::utils;
+
+// Grid of the map holding the combined strength of the bot's and allied squads and the threat
+// of the opponents' squads. Every squad projects its strength onto the cells it can reach soon,
+// fading out linearly with the distance. It is recomputed every tick.
+
+// edge length of a grid cell
+const CELL_SIZE: f32 = 8.;
+// margin around the outermost locations which is still part of the grid
+const MAP_MARGIN: f32 = 60.;
+// attack ranges, the card data does not include them
+const MELEE_RANGE: f32 = 5.;
+const RANGED_RANGE: f32 = 25.;
+// distance a squad can cover before it has to be taken into account, on top of its range
+const INFLUENCE_MARGIN: f32 = 35.;
+// influence below which a spot counts as free of enemies
+const MIN_INFLUENCE: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InfluenceSource {
+    pub entity_id: EntityId,
+    pub position: Position2D,
+    pub strength: f32,
+    pub radius: f32,
+    pub enemy: bool,
+}
+
+#[derive(Debug)]
+pub struct InfluenceMap {
+    // position of the lower left corner of the first cell
+    origin: Position2D,
+    width: usize,
+    height: usize,
+    // influence of the bot's and allied squads
+    strength: Vec<f32>,
+    // influence of the opponents' squads
+    threat: Vec<f32>,
+    // sources of the last update
+    sources: Vec<InfluenceSource>,
+}
+
+impl InfluenceMap {
+    pub fn new(positions: &[Position2D]) -> Self {
+        if positions.is_empty() {
+            return InfluenceMap {
+                origin: Position2D { x: 0., y: 0. },
+                width: 0,
+                height: 0,
+                strength: vec![],
+                threat: vec![],
+                sources: vec![],
+            };
+        }
+
+        let min_x = positions.iter().map(|p| p.x).fold(f32::MAX, f32::min) - MAP_MARGIN;
+        let min_y = positions.iter().map(|p| p.y).fold(f32::MAX, f32::min) - MAP_MARGIN;
+        let max_x = positions.iter().map(|p| p.x).fold(f32::MIN, f32::max) + MAP_MARGIN;
+        let max_y = positions.iter().map(|p| p.y).fold(f32::MIN, f32::max) + MAP_MARGIN;
+        let width = ((max_x - min_x) / CELL_SIZE).ceil() as usize;
+        let height = ((max_y - min_y) / CELL_SIZE).ceil() as usize;
+
+        InfluenceMap {
+            origin: Position2D { x: min_x, y: min_y },
+            width,
+            height,
+            strength: vec![0.; width * height],
+            threat: vec![0.; width * height],
+            sources: vec![],
+        }
+    }
+
+    pub fn update(&mut self, sources: &[InfluenceSource]) {
+        self.strength.iter_mut().for_each(|value| *value = 0.);
+        self.threat.iter_mut().for_each(|value| *value = 0.);
+
+        for source in sources.iter() {
+            let min_x = self.get_cell_index(source.position.x - source.radius, self.origin.x);
+            let max_x = self.get_cell_index(source.position.x + source.radius, self.origin.x);
+            let min_y = self.get_cell_index(source.position.y - source.radius, self.origin.y);
+            let max_y = self.get_cell_index(source.position.y + source.radius, self.origin.y);
+
+            for x in min_x.max(0)..(max_x + 1).min(self.width as i32) {
+                for y in min_y.max(0)..(max_y + 1).min(self.height as i32) {
+                    let cell = (x as usize, y as usize);
+                    let dist = utils::dist(&source.position, &self.get_cell_center(cell));
+                    if dist >= source.radius {
+                        continue;
+                    }
+                    let value = source.strength * (1. - dist / source.radius);
+                    let index = cell.1 * self.width + cell.0;
+                    if source.enemy {
+                        self.threat[index] += value;
+                    } else {
+                        self.strength[index] += value;
+                    }
+                }
+            }
+        }
+        self.sources = sources.to_vec();
+    }
+
+    pub fn threat_at(&self, pos: &Position2D) -> f32 {
+        self.get_index(pos).map_or(0., |index| self.threat[index])
+    }
+
+    pub fn strength_at(&self, pos: &Position2D) -> f32 {
+        self.get_index(pos).map_or(0., |index| self.strength[index])
+    }
+
+    pub fn balance_at(&self, pos: &Position2D) -> f32 {
+        // positive where the bot is stronger
+        self.strength_at(pos) - self.threat_at(pos)
+    }
+
+    pub fn max_threat_on_line(&self, from: &Position2D, to: &Position2D) -> f32 {
+        // sample the line with half the cell size
+        let num_steps = (utils::dist(from, to) / (CELL_SIZE / 2.)).ceil().max(1.) as usize;
+        (0..=num_steps)
+            .map(|step| {
+                let t = step as f32 / num_steps as f32;
+                self.threat_at(&Position2D {
+                    x: from.x + (to.x - from.x) * t,
+                    y: from.y + (to.y - from.y) * t,
+                })
+            })
+            .fold(0., f32::max)
+    }
+
+    pub fn is_threatened(&self, pos: &Position2D) -> bool {
+        self.threat_at(pos) > MIN_INFLUENCE
+    }
+
+    pub fn is_safe(&self, pos: &Position2D) -> bool {
+        // no enemies around or the bot is stronger
+        !self.is_threatened(pos) || self.balance_at(pos) > 0.
+    }
+
+    pub fn get_threatening_squads(&self, pos: &Position2D) -> Vec<EntityId> {
+        // enemy squads which reach the position soon, ranged squads from further away
+        self.sources
+            .iter()
+            .filter(|source| source.enemy && utils::dist(&source.position, pos) < source.radius)
+            .map(|source| source.entity_id)
+            .collect()
+    }
+
+    pub fn get_front_line(&self) -> Vec<Position2D> {
+        // cells which both sides reach and where neither is clearly stronger
+        let mut front_line = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let (strength, threat) = (self.strength[index], self.threat[index]);
+                if strength > MIN_INFLUENCE
+                    && threat > MIN_INFLUENCE
+                    && (strength - threat).abs() < 0.5 * strength.max(threat)
+                {
+                    front_line.push(self.get_cell_center((x, y)));
+                }
+            }
+        }
+        front_line
+    }
+
+    fn get_cell_index(&self, value: f32, origin: f32) -> i32 {
+        ((value - origin) / CELL_SIZE).floor() as i32
+    }
+
+    fn get_index(&self, pos: &Position2D) -> Option<usize> {
+        let x = self.get_cell_index(pos.x, self.origin.x);
+        let y = self.get_cell_index(pos.y, self.origin.y);
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    fn get_cell_center(&self, cell: (usize, usize)) -> Position2D {
+        Position2D {
+            x: self.origin.x + (cell.0 as f32 + 0.5) * CELL_SIZE,
+            y: self.origin.y + (cell.1 as f32 + 0.5) * CELL_SIZE,
+        }
+    }
+}
+
+pub fn get_influence_sources(game_info: &GameInfo) -> Vec<InfluenceSource> {
+    // (squad id, card id, position, is enemy)
+    let squads: Vec<(EntityId, u32, Position2D, bool)> = game_info
+        .players()
+        .flat_map(|player| {
+            let enemy = game_info.is_opponent(&player.id);
+            player.squads.values().map(move |squad| (squad, enemy))
+        })
+        .map(|(squad, enemy)| {
+            let pos = squad.entity.position.to_2d();
+            (squad.entity.id, squad.card_id.0, pos, enemy)
+        })
+        .collect();
+
+    let mut sources = vec![];
+    for (squad_id, card_id, position, enemy) in squads {
+        let card_info = match game_info.card_data.get_card_info_from_id(card_id) {
+            Ok(card_info) => card_info,
+            Err(err) => {
+                error!("Ignoring squad {squad_id:?} in the influence map: {err}");
+                continue;
+            }
+        };
+        let (cur_hp, _) = game_info.get_squad_health(&squad_id);
+        let dps = card_info.dps();
+        let range = if card_info.melee {
+            MELEE_RANGE
+        } else {
+            RANGED_RANGE
+        };
+
+        // strength of a group grows with the square of the summed up values (Lanchester),
+        // so single squads are added up as sqrt(dps * health)
+        sources.push(InfluenceSource {
+            entity_id: squad_id,
+            position,
+            strength: (dps * cur_hp).max(0.).sqrt(),
+            radius: range + INFLUENCE_MARGIN,
+            enemy,
+        });
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn threat_fades_with_distance() {
+        let mut builder = ScenarioBuilder::new();
+        builder.opponent_squads(Dreadcharger, 2, "Center", 1.);
+        let game_info = builder.game_info();
+
+        let center = game_info
+            .locations
+            .values()
+            .find(|l| l.name == "Center")
+            .unwrap()
+            .position();
+        let near = Position2D {
+            x: center.x + 20.,
+            y: center.y,
+        };
+        let far = Position2D {
+            x: center.x + 100.,
+            y: center.y,
+        };
+
+        let influence = &game_info.influence;
+        assert!(influence.threat_at(&center) > influence.threat_at(&near));
+        assert!(influence.is_threatened(&near));
+        assert!(!influence.is_threatened(&far));
+        assert!(!influence.is_safe(&center));
+        assert_eq!(influence.strength_at(&center), 0.);
+        assert_eq!(game_info.get_threatening_enemy_squads(&near).len(), 2);
+        assert!(game_info.get_threatening_enemy_squads(&far).is_empty());
+    }
+
+    #[test]
+    fn stronger_side_is_safe() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .bot_squads(Dreadcharger, 3, "Center", 1.)
+            .opponent_squads(Dreadcharger, 1, "Center", 1.)
+            .opponent_squads(Dreadcharger, 1, "Centernorth", 1.);
+        let game_info = builder.game_info();
+        let position = |name| {
+            game_info
+                .locations
+                .values()
+                .find(|l| l.name == name)
+                .unwrap()
+                .position()
+        };
+
+        assert!(game_info.influence.is_safe(&position("Center")));
+        assert!(!game_info.influence.is_safe(&position("Centernorth")));
+        assert!(!game_info.influence.get_front_line().is_empty());
+    }
+}
@@ -3,21 +3,40 @@ use log::*;
 use std::collections::BTreeMap;
 
 use crate::game_info::GameInfo;
+use crate::maps::MapDefinition;
 use crate::utils;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
-pub enum Location {
-    North,
-    Northeast,
-    East,
-    Southeast,
-    South,
-    Southwest,
-    West,
-    Northwest,
-    Centernorth,
-    Centersouth,
-    Center,
+// max distance between two slots of the same location
+const LOCATION_CLUSTER_RADIUS: f32 = 25.;
+// max difference in y of locations which are numbered as one row
+const LOCATION_ROW_TOLERANCE: f32 = 15.;
+// max distance between a slot of a map definition and the slot in the match
+const MAX_DEFINED_SLOT_OFFSET: f32 = 5.;
+// share of the map radius around the map center where locations are called "Center"
+const CENTER_AREA_SHARE: f32 = 0.2;
+// share of the map radius up to which locations are called e.g. "Centersouth"
+const INNER_AREA_SHARE: f32 = 0.55;
+// compass directions counterclockwise in steps of 45 degrees, starting at east
+const DIRECTION_NAMES: [&'static str; 8] = [
+    "East",
+    "Northeast",
+    "North",
+    "Northwest",
+    "West",
+    "Southwest",
+    "South",
+    "Southeast",
+];
+
+// Group of power and token slots which are close to each other. The ids are generated at
+// match start by ordering the locations by their position, so they are the same in every
+// match on the same map.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct Location(pub u16);
+
+impl Location {
+    // placeholder until the locations of the map are known
+    pub const UNKNOWN: Location = Location(u16::MAX);
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -34,6 +53,9 @@ pub struct PowerSubLocation {
 
 #[derive(Debug)]
 pub struct LocationPosition {
+    // from the map definition or generated from the direction relative to the map center,
+    // e.g. "North" or "Centersouth"
+    pub name: String,
     pub token: Option<TokenSubLocation>,
     pub powers: Vec<PowerSubLocation>,
 }
@@ -51,27 +73,13 @@ impl LocationPosition {
 }
 
 pub fn get_squad_position(entity_id: EntityId, game_info: &GameInfo) -> Position2D {
-    if game_info.bot.squads.contains_key(&entity_id) {
-        game_info
-            .bot
-            .squads
-            .get(&entity_id)
-            .unwrap()
-            .entity
-            .position
-            .to_2d()
-    } else if game_info.opponent.squads.contains_key(&entity_id) {
-        game_info
-            .opponent
-            .squads
-            .get(&entity_id)
-            .unwrap()
-            .entity
-            .position
-            .to_2d()
-    } else {
-        error!("Unable to get position for entity {:?}", entity_id);
-        Position2D { x: 0., y: 0. }
+    // allies and other opponents included
+    match game_info.players().find_map(|p| p.squads.get(&entity_id)) {
+        Some(squad) => squad.entity.position.to_2d(),
+        None => {
+            error!("Unable to get position for entity {:?}", entity_id);
+            Position2D { x: 0., y: 0. }
+        }
     }
 }
 
@@ -82,34 +90,39 @@ pub fn get_location_owner(location: &Location, game_info: &GameInfo) -> Option<E
 
     // check owner if there is an orb
     if let Some(token) = loc.token {
-        if game_info
-            .bot
-            .token_slots
-            .contains_key(&token.entity_id.unwrap())
-        {
-            return Some(game_info.bot.id);
-        }
-        if game_info
-            .opponent
-            .token_slots
-            .contains_key(&token.entity_id.unwrap())
+        let token_id = token.entity_id.unwrap();
+        if let Some(player) = game_info
+            .players()
+            .find(|p| p.token_slots.contains_key(&token_id))
         {
-            return Some(game_info.opponent.id);
+            return Some(player.id);
         }
     }
 
     for power_slot_id in &power_slot_ids {
-        if game_info.bot.power_slots.contains_key(&power_slot_id) {
-            return Some(game_info.bot.id);
-        }
-        if game_info.opponent.power_slots.contains_key(&power_slot_id) {
-            return Some(game_info.opponent.id);
+        if let Some(player) = game_info
+            .players()
+            .find(|p| p.power_slots.contains_key(power_slot_id))
+        {
+            return Some(player.id);
         }
     }
 
     None
 }
 
+pub fn get_allied_locations(game_info: &GameInfo) -> Vec<Location> {
+    // locations owned by the bot or one of its allies
+    game_info
+        .locations
+        .keys()
+        .filter(|loc| {
+            get_location_owner(loc, game_info).is_some_and(|owner| game_info.is_allied(&owner))
+        })
+        .cloned()
+        .collect()
+}
+
 pub fn get_next_free_power_slot(location: &Location, game_info: &GameInfo) -> Option<EntityId> {
     let loc = game_info.locations.get(location).unwrap();
 
@@ -176,296 +189,464 @@ pub fn get_location_from_entity_id(entity_id: &EntityId, game_info: &GameInfo) -
     None
 }
 
-pub fn get_location_positions() -> BTreeMap<Location, LocationPosition> {
-    BTreeMap::from([
-        (
-            Location::North,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 176.4518,
-                        y: 317.31332,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 183.4518,
-                            y: 317.31332,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 169.4518,
-                            y: 317.31332,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Northeast,
-            LocationPosition {
-                token: None,
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 239.8502,
-                            y: 254.33868,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 233.54659,
-                            y: 254.29355,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::East,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 292.79623,
-                        y: 181.02464,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 296.37576,
-                            y: 176.02464,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 289.29202,
-                            y: 176.02464,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Southeast,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 238.3221,
-                        y: 93.76264,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![PowerSubLocation {
-                    position: Position2D {
-                        x: 245.30684,
-                        y: 87.248436,
-                    },
-                    entity_id: None,
-                }],
-            },
-        ),
-        (
-            Location::South,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 177.34,
-                        y: 37.702557,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 185.02084,
-                            y: 37.60539,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 169.35567,
-                            y: 37.71217,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Southwest,
-            LocationPosition {
-                token: None,
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 120.78301,
-                            y: 98.97056,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 114.183,
-                            y: 98.97056,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::West,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 72.055,
-                        y: 176.0,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 75.434,
-                            y: 181.315,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 69.05,
-                            y: 181.31488,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Northwest,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 113.50901,
-                        y: 261.2001,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![PowerSubLocation {
-                    position: Position2D {
-                        x: 106.23168,
-                        y: 267.45325,
-                    },
-                    entity_id: None,
-                }],
-            },
-        ),
-        (
-            Location::Centernorth,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 176.4,
-                        y: 238.6631,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 183.4,
-                            y: 237.1595,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 169.4,
-                            y: 237.1595,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Centersouth,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D {
-                        x: 176.67712,
-                        y: 116.7765,
-                    },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 182.99454,
-                            y: 117.625984,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 170.42061,
-                            y: 117.62589,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-        (
-            Location::Center,
-            LocationPosition {
-                token: Some(TokenSubLocation {
-                    position: Position2D { x: 176.4, y: 177.8 },
-                    entity_id: None,
-                }),
-                powers: vec![
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 184.6759,
-                            y: 181.14935,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 180.30853,
-                            y: 185.29858,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 168.40605,
-                            y: 174.49603,
-                        },
-                        entity_id: None,
-                    },
-                    PowerSubLocation {
-                        position: Position2D {
-                            x: 172.74492,
-                            y: 169.98474,
-                        },
-                        entity_id: None,
-                    },
-                ],
-            },
-        ),
-    ])
+pub fn get_location_by_name(name: &str, game_info: &GameInfo) -> Option<Location> {
+    game_info
+        .locations
+        .iter()
+        .find(|(_, loc_pos)| loc_pos.name == name)
+        .map(|(loc, _)| *loc)
+}
+
+pub fn get_location_name<'a>(location: &Location, game_info: &'a GameInfo) -> &'a str {
+    match game_info.locations.get(location) {
+        Some(loc_pos) => &loc_pos.name,
+        None => "Unknown",
+    }
+}
+
+pub fn discover_locations(
+    power_slots: &[PowerSlot],
+    token_slots: &[TokenSlot],
+) -> BTreeMap<Location, LocationPosition> {
+    // (position, entity id, is token slot)
+    let slots: Vec<(Position2D, EntityId, bool)> = power_slots
+        .iter()
+        .map(|s| (s.entity.position.to_2d(), s.entity.id, false))
+        .chain(
+            token_slots
+                .iter()
+                .map(|s| (s.entity.position.to_2d(), s.entity.id, true)),
+        )
+        .collect();
+
+    // a slot belongs to a location when it's close to any other slot of the location
+    let mut assigned = vec![false; slots.len()];
+    let mut location_positions: Vec<LocationPosition> = vec![];
+    for first in 0..slots.len() {
+        if assigned[first] {
+            continue;
+        }
+        assigned[first] = true;
+
+        let mut cluster = vec![first];
+        let mut i = 0;
+        while i < cluster.len() {
+            let current_pos = slots[cluster[i]].0;
+            for other in 0..slots.len() {
+                if !assigned[other]
+                    && utils::dist(&current_pos, &slots[other].0) < LOCATION_CLUSTER_RADIUS
+                {
+                    assigned[other] = true;
+                    cluster.push(other);
+                }
+            }
+            i += 1;
+        }
+
+        let mut loc_pos = LocationPosition {
+            name: String::new(),
+            token: None,
+            powers: vec![],
+        };
+        for index in cluster {
+            let (position, entity_id, is_token_slot) = slots[index];
+            if !is_token_slot {
+                loc_pos.powers.push(PowerSubLocation {
+                    position,
+                    entity_id: Some(entity_id),
+                });
+            } else if loc_pos.token.is_none() {
+                loc_pos.token = Some(TokenSubLocation {
+                    position,
+                    entity_id: Some(entity_id),
+                });
+            } else {
+                warn!("Ignoring second token slot {entity_id:?} at {position:?}");
+            }
+        }
+        location_positions.push(loc_pos);
+    }
+
+    // names are numbered in the same order as the ids
+    sort_locations(&mut location_positions);
+    let positions: Vec<Position2D> = location_positions.iter().map(|l| l.position()).collect();
+    for (loc_pos, name) in location_positions
+        .iter_mut()
+        .zip(get_location_names(&positions))
+    {
+        loc_pos.name = name;
+    }
+
+    number_locations(location_positions)
+}
+
+pub fn get_locations_from_definition(
+    definition: &MapDefinition,
+    power_slots: &[PowerSlot],
+    token_slots: &[TokenSlot],
+) -> Option<BTreeMap<Location, LocationPosition>> {
+    let mut used_slot_ids: Vec<EntityId> = vec![];
+    let mut find_slot = |position: Position2D, slots: Vec<(Position2D, EntityId)>| {
+        let (slot_pos, slot_id) = slots
+            .into_iter()
+            .filter(|(_, id)| !used_slot_ids.contains(id))
+            .min_by(|a, b| utils::dist(&position, &a.0).total_cmp(&utils::dist(&position, &b.0)))?;
+        if utils::dist(&position, &slot_pos) > MAX_DEFINED_SLOT_OFFSET {
+            return None;
+        }
+        used_slot_ids.push(slot_id);
+        Some((slot_pos, slot_id))
+    };
+
+    let mut location_positions: Vec<LocationPosition> = vec![];
+    for (name, location) in definition.locations.iter() {
+        let mut loc_pos = LocationPosition {
+            name: name.clone(),
+            token: None,
+            powers: vec![],
+        };
+        for power in location.powers.values() {
+            let slots = power_slots
+                .iter()
+                .map(|s| (s.entity.position.to_2d(), s.entity.id))
+                .collect();
+            let Some((position, entity_id)) = find_slot(power.to_2d(), slots) else {
+                warn!("No power slot at {power:?} of {name}");
+                return None;
+            };
+            loc_pos.powers.push(PowerSubLocation {
+                position,
+                entity_id: Some(entity_id),
+            });
+        }
+        if let Some(token) = location.token {
+            let slots = token_slots
+                .iter()
+                .map(|s| (s.entity.position.to_2d(), s.entity.id))
+                .collect();
+            let Some((position, entity_id)) = find_slot(token.to_2d(), slots) else {
+                warn!("No token slot at {token:?} of {name}");
+                return None;
+            };
+            loc_pos.token = Some(TokenSubLocation {
+                position,
+                entity_id: Some(entity_id),
+            });
+        }
+        location_positions.push(loc_pos);
+    }
+
+    let num_slots = power_slots.len() + token_slots.len();
+    if used_slot_ids.len() < num_slots {
+        warn!(
+            "{} slots are not part of a defined location",
+            num_slots - used_slot_ids.len()
+        );
+        return None;
+    }
+
+    Some(number_locations(location_positions))
+}
+
+fn sort_locations(location_positions: &mut Vec<LocationPosition>) {
+    // order by rows from south to north and by x within a row to get the same ids in every
+    // match, locations with almost the same y are one row so small offsets can't swap them
+    location_positions.sort_by(|a, b| a.position().y.total_cmp(&b.position().y));
+    let mut rows: Vec<Vec<LocationPosition>> = vec![];
+    let mut last_y = f32::NEG_INFINITY;
+    for loc_pos in location_positions.drain(..) {
+        let y = loc_pos.position().y;
+        match rows.last_mut() {
+            Some(row) if y - last_y < LOCATION_ROW_TOLERANCE => row.push(loc_pos),
+            _ => rows.push(vec![loc_pos]),
+        }
+        last_y = y;
+    }
+    for mut row in rows {
+        row.sort_by(|a, b| a.position().x.total_cmp(&b.position().x));
+        location_positions.extend(row);
+    }
+}
+
+fn number_locations(
+    mut location_positions: Vec<LocationPosition>,
+) -> BTreeMap<Location, LocationPosition> {
+    sort_locations(&mut location_positions);
+
+    let mut locations = BTreeMap::new();
+    for (i, loc_pos) in location_positions.into_iter().enumerate() {
+        debug!(
+            "Found location {} {:?} at {:?}",
+            loc_pos.name,
+            Location(i as u16),
+            loc_pos.position()
+        );
+        locations.insert(Location(i as u16), loc_pos);
+    }
+    locations
+}
+
+fn get_location_names(positions: &[Position2D]) -> Vec<String> {
+    let map_center = utils::average_pos(positions.to_vec());
+    let map_radius = positions
+        .iter()
+        .map(|pos| utils::dist(pos, &map_center))
+        .fold(0., f32::max);
+
+    let mut names: Vec<String> = vec![];
+    for pos in positions {
+        let dist = utils::dist(pos, &map_center);
+        let angle = (pos.y - map_center.y).atan2(pos.x - map_center.x);
+        let direction_index = (angle / std::f32::consts::FRAC_PI_4).round() as i32;
+        let direction = DIRECTION_NAMES[direction_index.rem_euclid(8) as usize];
+
+        let mut name = if dist < CENTER_AREA_SHARE * map_radius {
+            "Center".to_string()
+        } else if dist < INNER_AREA_SHARE * map_radius {
+            format!("Center{}", direction.to_lowercase())
+        } else {
+            direction.to_string()
+        };
+
+        if names.contains(&name) {
+            // add a number to locations in the same direction
+            let mut number = 2;
+            while names.contains(&format!("{name}{number}")) {
+                number += 1;
+            }
+            name = format!("{name}{number}");
+        }
+        names.push(name);
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+    use std::num::NonZeroU32;
+
+    fn location(name: &str, game_info: &GameInfo) -> Location {
+        get_location_by_name(name, game_info).unwrap()
+    }
+
+    #[test]
+    fn location_owner_from_orb_or_wells() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .opponent_wells("Northeast", 1)
+            .free_location("North");
+        let game_info = builder.game_info();
+
+        // start orb
+        assert_eq!(
+            get_location_owner(&location("South", &game_info), &game_info),
+            Some(game_info.bot.id)
+        );
+        // location without orb
+        assert_eq!(
+            get_location_owner(&location("Northeast", &game_info), &game_info),
+            Some(game_info.opponent.id)
+        );
+        assert_eq!(
+            get_location_owner(&location("North", &game_info), &game_info),
+            None
+        );
+        assert_eq!(
+            get_location_owner(&location("Center", &game_info), &game_info),
+            None
+        );
+    }
+
+    #[test]
+    fn allied_locations_in_2v2() {
+        let mut builder = ScenarioBuilder::new();
+        let ally_id = builder.add_player(true);
+        let other_opponent_id = builder.add_player(false);
+        builder.player_wells(ally_id, "Southwest", 1).player_wells(
+            other_opponent_id,
+            "Northeast",
+            1,
+        );
+        let game_info = builder.game_info();
+
+        assert_eq!(game_info.allies.len(), 1);
+        assert_eq!(game_info.other_opponents.len(), 1);
+        assert!(game_info.is_allied(&ally_id));
+        assert!(game_info.is_opponent(&other_opponent_id));
+        assert!(!game_info.is_opponent(&ally_id));
+
+        assert_eq!(
+            get_location_owner(&location("Northeast", &game_info), &game_info),
+            Some(other_opponent_id)
+        );
+        let allied_locations: Vec<&str> = get_allied_locations(&game_info)
+            .iter()
+            .map(|loc| get_location_name(loc, &game_info))
+            .collect();
+        assert_eq!(allied_locations, vec!["South", "Southwest"]);
+    }
+
+    #[test]
+    fn squad_positions_of_all_players_in_2v2() {
+        let mut builder = ScenarioBuilder::new();
+        let ally_id = builder.add_player(true);
+        let other_opponent_id = builder.add_player(false);
+        builder
+            .player_squads(ally_id, Dreadcharger, 1, "Southwest", 1.)
+            .player_squads(other_opponent_id, Dreadcharger, 1, "Northeast", 1.);
+        let game_info = builder.game_info();
+
+        for (player_id, name) in [(ally_id, "Southwest"), (other_opponent_id, "Northeast")] {
+            let squad_id = builder.squad_ids(player_id)[0];
+            let expected = game_info.locations[&location(name, &game_info)].position();
+            assert_eq!(get_squad_position(squad_id, &game_info), expected);
+        }
+    }
+
+    #[test]
+    fn no_free_slots_at_enemy_location() {
+        let mut builder = ScenarioBuilder::new();
+        builder.opponent_orb("East", OrbColor::Fire);
+        let game_info = builder.game_info();
+
+        assert_eq!(
+            get_next_free_power_slot(&location("East", &game_info), &game_info),
+            None
+        );
+        assert_eq!(
+            get_next_free_token_slot(&location("East", &game_info), &game_info),
+            None
+        );
+        assert!(get_next_free_power_slot(&location("West", &game_info), &game_info).is_some());
+    }
+
+    #[test]
+    fn discovered_elyon_locations() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let locations = discover_locations(
+            &start_state.entities.power_slots,
+            &start_state.entities.token_slots,
+        );
+
+        let mut names: Vec<&str> = locations.values().map(|l| l.name.as_str()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Center",
+                "Centernorth",
+                "Centersouth",
+                "East",
+                "North",
+                "Northeast",
+                "Northwest",
+                "South",
+                "Southeast",
+                "Southwest",
+                "West"
+            ]
+        );
+        // only two locations have no orb
+        let without_token: Vec<&str> = locations
+            .values()
+            .filter(|l| l.token.is_none())
+            .map(|l| l.name.as_str())
+            .collect();
+        assert_eq!(without_token, vec!["Southwest", "Northeast"]);
+    }
+
+    fn assert_same_names(
+        locations: &BTreeMap<Location, LocationPosition>,
+        power_slots: &[PowerSlot],
+        token_slots: &[TokenSlot],
+    ) {
+        let changed_locations = discover_locations(power_slots, token_slots);
+        assert_eq!(changed_locations.len(), locations.len());
+        for (location, loc_pos) in locations.iter() {
+            assert_eq!(changed_locations[location].name, loc_pos.name);
+        }
+    }
+
+    #[test]
+    fn location_names_are_stable() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let power_slots = start_state.entities.power_slots.clone();
+        let token_slots = start_state.entities.token_slots.clone();
+        let locations = discover_locations(&power_slots, &token_slots);
+
+        // slightly moved slots, e.g. on a different version of the map
+        let mut moved_power_slots = power_slots.clone();
+        let mut moved_token_slots = token_slots.clone();
+        for slot in moved_power_slots.iter_mut() {
+            slot.entity.position.x += 3.;
+            slot.entity.position.z -= 2.;
+        }
+        for slot in moved_token_slots.iter_mut() {
+            slot.entity.position.x += 3.;
+            slot.entity.position.z -= 2.;
+        }
+        assert_same_names(&locations, &moved_power_slots, &moved_token_slots);
+
+        // every slot moved differently, which changes the geometry between the locations
+        let mut jittered_power_slots = power_slots.clone();
+        let mut jittered_token_slots = token_slots.clone();
+        let jitter = |i: usize| [(2.5, -1.5), (-2., 3.), (1., 2.), (-3., -2.5)][i % 4];
+        for (i, slot) in jittered_power_slots.iter_mut().enumerate() {
+            let (dx, dz) = jitter(i);
+            slot.entity.position.x += dx;
+            slot.entity.position.z += dz;
+        }
+        for (i, slot) in jittered_token_slots.iter_mut().enumerate() {
+            let (dx, dz) = jitter(i + 1);
+            slot.entity.position.x += dx;
+            slot.entity.position.z += dz;
+        }
+        assert_same_names(&locations, &jittered_power_slots, &jittered_token_slots);
+
+        // a location with one well less
+        let removed = locations
+            .values()
+            .find(|loc_pos| loc_pos.powers.len() > 1)
+            .unwrap()
+            .powers[0]
+            .entity_id;
+        let fewer_power_slots: Vec<PowerSlot> = power_slots
+            .iter()
+            .filter(|slot| Some(slot.entity.id) != removed)
+            .cloned()
+            .collect();
+        assert_same_names(&locations, &fewer_power_slots, &token_slots);
+
+        // a location with an additional well next to an existing one
+        let mut more_power_slots = power_slots.clone();
+        let mut added = power_slots[0].clone();
+        added.entity.id = EntityId(NonZeroU32::new(999_999).unwrap());
+        added.entity.position.x += 5.;
+        more_power_slots.push(added);
+        assert_same_names(&locations, &more_power_slots, &token_slots);
+    }
+
+    #[test]
+    fn defined_locations_match_discovered() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let power_slots = &start_state.entities.power_slots;
+        let token_slots = &start_state.entities.token_slots;
+        let definition = maps::get_map_definition(&Maps::ElyonSpectator).unwrap();
+
+        let defined = get_locations_from_definition(&definition, power_slots, token_slots).unwrap();
+        let discovered = discover_locations(power_slots, token_slots);
+        for (location, loc_pos) in defined.iter() {
+            assert_eq!(discovered[location].name, loc_pos.name);
+            assert_eq!(discovered[location].powers.len(), loc_pos.powers.len());
+        }
+
+        // a different map does not match the definition
+        let mut moved_slots = power_slots.clone();
+        moved_slots[0].entity.position.x += 20.;
+        assert!(get_locations_from_definition(&definition, &moved_slots, token_slots).is_none());
+    }
 }
@@ -0,0 +1,179 @@
+use api::*;
+use std::collections::BTreeMap;
+
+use crate::location::{Location, LocationPosition};
+use crate::utils;
+
+// Graph of the locations of a map. Two locations are adjacent when no other location lies
+// within the circle spanned by them (Gabriel graph), so squads moving between adjacent
+// locations don't pass any other location on their way.
+#[derive(Debug)]
+pub struct LocationGraph {
+    positions: BTreeMap<Location, Position2D>,
+    adjacency: BTreeMap<Location, Vec<Location>>,
+    // shortest distance along the edges of the graph for every pair of connected locations
+    distances: BTreeMap<(Location, Location), f32>,
+    // next location on the shortest route from the first to the second location
+    next_hops: BTreeMap<(Location, Location), Location>,
+}
+
+impl LocationGraph {
+    pub fn new(locations: &BTreeMap<Location, LocationPosition>) -> Self {
+        let positions: BTreeMap<Location, Position2D> = locations
+            .iter()
+            .map(|(loc, loc_pos)| (*loc, loc_pos.position()))
+            .collect();
+
+        let mut adjacency: BTreeMap<Location, Vec<Location>> =
+            positions.keys().map(|loc| (*loc, vec![])).collect();
+        for (a, pos_a) in positions.iter() {
+            for (b, pos_b) in positions.iter().filter(|(b, _)| *b > a) {
+                let dist_squared = utils::dist(pos_a, pos_b).powi(2);
+                let blocked =
+                    positions
+                        .iter()
+                        .filter(|(c, _)| *c != a && *c != b)
+                        .any(|(_, pos_c)| {
+                            utils::dist(pos_a, pos_c).powi(2) + utils::dist(pos_b, pos_c).powi(2)
+                                < dist_squared
+                        });
+                if !blocked {
+                    adjacency.get_mut(a).unwrap().push(*b);
+                    adjacency.get_mut(b).unwrap().push(*a);
+                }
+            }
+        }
+
+        let mut graph = LocationGraph {
+            positions,
+            adjacency,
+            distances: BTreeMap::new(),
+            next_hops: BTreeMap::new(),
+        };
+        graph.compute_routes();
+        graph
+    }
+
+    fn compute_routes(&mut self) {
+        // Floyd-Warshall, maps only have a few locations
+        let locations: Vec<Location> = self.positions.keys().cloned().collect();
+        for &a in locations.iter() {
+            self.distances.insert((a, a), 0.);
+            self.next_hops.insert((a, a), a);
+            for &b in self.adjacency[&a].iter() {
+                let dist = utils::dist(&self.positions[&a], &self.positions[&b]);
+                self.distances.insert((a, b), dist);
+                self.next_hops.insert((a, b), b);
+            }
+        }
+
+        for &via in locations.iter() {
+            for &a in locations.iter() {
+                let Some(&dist_a_via) = self.distances.get(&(a, via)) else {
+                    continue;
+                };
+                for &b in locations.iter() {
+                    let Some(&dist_via_b) = self.distances.get(&(via, b)) else {
+                        continue;
+                    };
+                    let new_dist = dist_a_via + dist_via_b;
+                    if self.distances.get(&(a, b)).map_or(true, |&d| new_dist < d) {
+                        self.distances.insert((a, b), new_dist);
+                        let next_hop = self.next_hops[&(a, via)];
+                        self.next_hops.insert((a, b), next_hop);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn neighbors(&self, location: &Location) -> &[Location] {
+        self.adjacency
+            .get(location)
+            .map(|neighbors| neighbors.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn are_adjacent(&self, a: &Location, b: &Location) -> bool {
+        self.neighbors(a).contains(b)
+    }
+
+    pub fn is_adjacent_to_any(&self, location: &Location, others: &[Location]) -> bool {
+        others
+            .iter()
+            .any(|other| self.are_adjacent(location, other))
+    }
+
+    pub fn travel_distance(&self, from: &Location, to: &Location) -> Option<f32> {
+        self.distances.get(&(*from, *to)).cloned()
+    }
+
+    pub fn route(&self, from: &Location, to: &Location) -> Option<Vec<Location>> {
+        // both locations are included
+        let mut route = vec![*from];
+        let mut current = *from;
+        while current != *to {
+            current = *self.next_hops.get(&(current, *to))?;
+            route.push(current);
+        }
+        Some(route)
+    }
+
+    pub fn frontier(&self, own: &[Location], opponent: &[Location]) -> Vec<Location> {
+        // own locations which are adjacent to a location of the opponent
+        own.iter()
+            .filter(|loc| self.is_adjacent_to_any(loc, opponent))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location;
+    use crate::test_fixtures::ScenarioBuilder;
+
+    #[test]
+    fn elyon_adjacency_and_routes() {
+        let game_info = ScenarioBuilder::new().game_info();
+        let graph = &game_info.location_graph;
+        let loc = |name| location::get_location_by_name(name, &game_info).unwrap();
+
+        assert!(graph.are_adjacent(&loc("South"), &loc("Centersouth")));
+        assert!(graph.are_adjacent(&loc("Centersouth"), &loc("Center")));
+        assert!(!graph.are_adjacent(&loc("South"), &loc("Center")));
+        assert!(!graph.are_adjacent(&loc("West"), &loc("East")));
+
+        let route = graph.route(&loc("South"), &loc("North")).unwrap();
+        assert_eq!(route.first(), Some(&loc("South")));
+        assert_eq!(route.last(), Some(&loc("North")));
+        assert!(route.len() > 2);
+        for hops in route.windows(2) {
+            assert!(graph.are_adjacent(&hops[0], &hops[1]));
+        }
+
+        // travelling along the graph is never shorter than the direct way
+        let direct = utils::dist(
+            &game_info.locations[&loc("South")].position(),
+            &game_info.locations[&loc("North")].position(),
+        );
+        let travel = graph.travel_distance(&loc("South"), &loc("North")).unwrap();
+        assert!(travel >= direct);
+        assert_eq!(
+            graph.travel_distance(&loc("North"), &loc("South")),
+            Some(travel)
+        );
+    }
+
+    #[test]
+    fn frontier_between_territories() {
+        let game_info = ScenarioBuilder::new().game_info();
+        let graph = &game_info.location_graph;
+        let loc = |name| location::get_location_by_name(name, &game_info).unwrap();
+
+        let own = vec![loc("South"), loc("Centersouth"), loc("Southeast")];
+        let opponent = vec![loc("North"), loc("Center")];
+        assert_eq!(graph.frontier(&own, &opponent), vec![loc("Centersouth")]);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::game_info::GameInfo;
+use crate::location::{self, Location};
+use crate::strategy_config::LocationPriorityConfig;
+
+// Orders the locations of any map by how valuable they are for the bot. The own start location
+// is always first and the opponent's start location always last, every other location is
+// scored by its position, slots, owner and the enemy squads around it. A map definition can
+// override the order with fixed priorities.
+
+// bound power which counts as one unit of enemy strength
+const ENEMY_STRENGTH_POWER: f32 = 100.;
+
+pub fn get_location_prios(
+    game_info: &GameInfo,
+    config: &LocationPriorityConfig,
+    ahead: bool,
+) -> Vec<Location> {
+    let own_start = game_info.bot.start_location;
+    let opponent_start = game_info.opponent.start_location;
+
+    let mut scored_locations: Vec<(Location, f32)> = game_info
+        .locations
+        .keys()
+        .filter(|&&loc| loc != own_start && loc != opponent_start)
+        .map(|loc| (*loc, get_location_score(loc, game_info, config, ahead)))
+        .collect();
+    // sort_by is stable, so equal scores keep the order of the ids
+    scored_locations.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut location_prios = vec![];
+    if game_info.locations.contains_key(&own_start) {
+        location_prios.push(own_start);
+    }
+    location_prios.extend(scored_locations.into_iter().map(|(loc, _)| loc));
+    if let Some(fixed_prios) = get_fixed_location_prios(game_info, ahead) {
+        // locations missing in the fixed priorities keep their scored order after them
+        location_prios.retain(|loc| !fixed_prios.contains(loc));
+        location_prios.splice(0..0, fixed_prios);
+    }
+    if game_info.locations.contains_key(&opponent_start) {
+        location_prios.push(opponent_start);
+    }
+    location_prios
+}
+
+fn get_fixed_location_prios(game_info: &GameInfo, ahead: bool) -> Option<Vec<Location>> {
+    // priorities of the map definition for the own start location, without the opponent's
+    let definition = game_info.map_definition.as_ref()?;
+    let start_name = location::get_location_name(&game_info.bot.start_location, game_info);
+    let names = definition.get_location_prios(start_name, ahead)?;
+    Some(
+        names
+            .iter()
+            .filter_map(|name| location::get_location_by_name(name, game_info))
+            .filter(|&loc| loc != game_info.opponent.start_location)
+            .collect(),
+    )
+}
+
+pub fn get_location_score(
+    location: &Location,
+    game_info: &GameInfo,
+    config: &LocationPriorityConfig,
+    ahead: bool,
+) -> f32 {
+    let Some(loc_pos) = game_info.locations.get(location) else {
+        return 0.;
+    };
+
+    let start_proximity_weight = if ahead {
+        config.start_proximity_weight_ahead
+    } else {
+        config.start_proximity_weight_behind
+    };
+    let mut score = start_proximity_weight * get_start_proximity(location, game_info);
+
+    score += config.power_slot_weight * loc_pos.powers.len() as f32;
+    if loc_pos.token.is_some() {
+        score += config.token_slot_weight;
+    }
+
+    match location::get_location_owner(location, game_info) {
+        Some(owner) if game_info.is_allied(&owner) => score += config.own_location_weight,
+        Some(_) if ahead => score += config.opponent_location_weight_ahead,
+        Some(_) => score += config.opponent_location_weight_behind,
+        None => {}
+    }
+
+    let enemy_power: f32 = game_info
+        .get_enemy_squads_in_range(&loc_pos.position(), config.enemy_strength_radius)
+        .iter()
+        .map(|squad| squad.bound_power)
+        .sum();
+    score += config.enemy_strength_weight * enemy_power / ENEMY_STRENGTH_POWER;
+
+    score
+}
+
+fn get_start_proximity(location: &Location, game_info: &GameInfo) -> f32 {
+    // 1 at the own start location, -1 at the opponent's and 0 halfway between them
+    let graph = &game_info.location_graph;
+    let own_dist = graph.travel_distance(&game_info.bot.start_location, location);
+    let opponent_dist = graph.travel_distance(&game_info.opponent.start_location, location);
+    match (own_dist, opponent_dist) {
+        (Some(own), Some(opponent)) if own + opponent > 0. => (opponent - own) / (opponent + own),
+        _ => 0.,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    fn names(location_prios: &[Location], game_info: &GameInfo) -> Vec<String> {
+        location_prios
+            .iter()
+            .map(|loc| location::get_location_name(loc, game_info).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn start_locations_first_and_last() {
+        let game_info = ScenarioBuilder::new().game_info();
+        let config = LocationPriorityConfig::default();
+
+        for ahead in [true, false] {
+            let prios = names(&get_location_prios(&game_info, &config, ahead), &game_info);
+            assert_eq!(prios.len(), 11);
+            assert_eq!(prios.first().unwrap(), "South");
+            assert_eq!(prios.last().unwrap(), "North");
+        }
+    }
+
+    #[test]
+    fn behind_prefers_locations_near_the_start() {
+        let game_info = ScenarioBuilder::new().game_info();
+        let config = LocationPriorityConfig::default();
+
+        let ahead = names(&get_location_prios(&game_info, &config, true), &game_info);
+        let behind = names(&get_location_prios(&game_info, &config, false), &game_info);
+        let position = |prios: &Vec<String>, name: &str| prios.iter().position(|n| n == name);
+
+        // the center with 4 wells is contested when ahead
+        assert_eq!(ahead[1], "Center");
+        assert!(position(&behind, "Center") > position(&behind, "Centersouth"));
+        assert!(position(&behind, "Centernorth") > position(&behind, "Southeast"));
+    }
+
+    #[test]
+    fn map_definition_overrides_the_priorities() {
+        let mut game_info = ScenarioBuilder::new().game_info();
+        let config = LocationPriorityConfig::default();
+        let definition = game_info.map_definition.as_mut().unwrap();
+        definition.location_prios_behind.insert(
+            "South".to_string(),
+            vec!["South", "West", "East", "North"]
+                .into_iter()
+                .map(|name| name.to_string())
+                .collect(),
+        );
+
+        let behind = names(&get_location_prios(&game_info, &config, false), &game_info);
+        assert_eq!(behind.len(), 11);
+        assert_eq!(behind[..3], ["South", "West", "East"]);
+        assert_eq!(behind.last().unwrap(), "North");
+
+        // no fixed priorities when ahead
+        let ahead = names(&get_location_prios(&game_info, &config, true), &game_info);
+        assert_eq!(ahead[1], "Center");
+    }
+
+    #[test]
+    fn enemy_squads_lower_the_priority() {
+        let mut builder = ScenarioBuilder::new();
+        let config = LocationPriorityConfig::default();
+        let game_info = builder.game_info();
+        let center = location::get_location_by_name("Center", &game_info).unwrap();
+        let score = get_location_score(&center, &game_info, &config, true);
+
+        builder.opponent_squads(Dreadcharger, 3, "Center", 1.);
+        let game_info = builder.game_info();
+        assert!(get_location_score(&center, &game_info, &config, true) < score);
+    }
+}
@@ -1,17 +1,37 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use env_logger::Builder;
-use log::{info, LevelFilter};
+use log::{error, info, LevelFilter};
 use std::fs::File;
 
 use api::*;
 
 mod bot;
 mod card_data;
+mod card_query;
+mod card_tracker;
 mod command_scheduler;
 mod controller;
+mod deck;
+mod deck_analysis;
 mod game_info;
+mod influence_map;
 mod location;
+mod location_graph;
+mod location_priority;
+mod maps;
+mod mock_client;
+mod orb_planner;
+mod pathfinding;
+mod replay;
+mod rush_bot;
+mod simulator;
+mod strategy_config;
+#[cfg(test)]
+mod test_fixtures;
+mod tournament;
+mod tuner;
 mod utils;
+mod walkability;
 
 #[derive(Parser)]
 struct Args {
@@ -19,12 +39,138 @@ struct Args {
     port: u16,
     #[arg(short, long, value_enum, default_value = "skylords-rebot")]
     implementation: BotImplementations,
+    /// Record every match to a compressed replay file next to the log file
+    #[arg(short, long)]
+    record: bool,
+    /// Json file with strategy parameters, missing values use the built-in defaults
+    #[arg(short, long)]
+    strategy: Option<String>,
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+#[derive(clap::Subcommand)]
+enum Mode {
+    /// Play an offline match against an idle opponent in the simulator
+    Simulate {
+        #[arg(long, default_value = "scripts/requests/start.json")]
+        start_state: String,
+        #[arg(long, default_value = "scripts/requests/prepare.json")]
+        prepare: String,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value_t = 6000)]
+        max_ticks: u32,
+    },
+    /// Replay a recorded match and report the first tick where the bot acts differently
+    Replay { file: String },
+    /// Play a match against a running bot over HTTP like the game client does
+    MockClient {
+        #[arg(long, default_value = "http://127.0.0.1:7273")]
+        url: String,
+        #[arg(long, default_value = "scripts/requests/hello.json")]
+        hello: String,
+        #[arg(long, default_value = "scripts/requests/prepare.json")]
+        prepare: String,
+        #[arg(long, default_value = "scripts/requests/start.json")]
+        start_state: String,
+        /// Send the game states of a recorded match instead of simulating one
+        #[arg(long)]
+        replay: Option<String>,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value_t = 6000)]
+        max_ticks: u32,
+    },
+    /// Let bot implementations play against each other in the simulator
+    Tournament {
+        /// Bots taking part, all implementations if none are given
+        #[arg(long, value_enum, value_delimiter = ',')]
+        bots: Vec<BotImplementations>,
+        /// Number of matches per pairing and start location
+        #[arg(long, default_value_t = 10)]
+        matches: u32,
+        #[arg(long, default_value = "scripts/requests/start.json")]
+        start_state: String,
+        #[arg(long, default_value = "scripts/requests/prepare.json")]
+        prepare: String,
+        #[arg(long, default_value_t = 12000)]
+        max_ticks: u32,
+    },
+    /// Search for better strategy parameters by playing against scripted opponents
+    Tune {
+        /// Number of candidate strategies to evaluate
+        #[arg(long, default_value_t = 50)]
+        iterations: u32,
+        /// Number of matches per opponent and start location for each candidate
+        #[arg(long, default_value_t = 2)]
+        matches: u32,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value = "scripts/requests/start.json")]
+        start_state: String,
+        #[arg(long, default_value = "scripts/requests/prepare.json")]
+        prepare: String,
+        #[arg(long, default_value_t = 12000)]
+        max_ticks: u32,
+        /// File the best strategy is written to
+        #[arg(long, default_value = "strategy.json")]
+        output: String,
+    },
+    /// Search the card database, all filters have to match
+    Cards {
+        /// Official card id, an upgrade in the id is ignored
+        #[arg(long)]
+        id: Option<u32>,
+        /// Part of the card name, case insensitive
+        #[arg(long)]
+        name: Option<String>,
+        /// Number of orbs the card requires
+        #[arg(long)]
+        tier: Option<i32>,
+        /// Color of the card, e.g. frost or shadow
+        #[arg(long)]
+        color: Option<String>,
+        /// unit, building or spell
+        #[arg(long = "type")]
+        card_type: Option<String>,
+        /// Offense size, e.g. m
+        #[arg(long)]
+        offense: Option<String>,
+        /// Defense size, e.g. xl
+        #[arg(long)]
+        defense: Option<String>,
+        /// Name of a card, only cards which win the matchup against it are shown, best first
+        #[arg(long)]
+        counters: Option<String>,
+        /// Only cards of the bot's deck
+        #[arg(long)]
+        deck: bool,
+        /// Upgrade level of the shown stats, from 0 to 3
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=3))]
+        upgrade: u8,
+        /// Print the full stats as json instead of a table
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, PartialEq, Eq, clap::ValueEnum)]
 enum BotImplementations {
     #[default]
     SkylordsRebot,
+    RushBot,
+}
+
+fn load_strategy(path: &str) -> Result<(), String> {
+    let config = strategy_config::StrategyConfig::load(path)?;
+    info!("using strategy {path:?}: {config:?}");
+
+    if let Some(deck_path) = &config.deck {
+        deck::set_bot_deck(deck::BotDeck::load(deck_path)?);
+    }
+    bot::set_strategy_config(config);
+    Ok(())
 }
 
 #[tokio::main]
@@ -41,10 +187,151 @@ async fn main() {
 
     let args = Args::parse();
 
+    if args.record {
+        bot::enable_replay_recording();
+    }
+
+    if let Some(path) = &args.strategy {
+        if let Err(err) = load_strategy(path) {
+            error!("{err}");
+            println!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    match args.mode {
+        Some(Mode::Simulate {
+            start_state,
+            prepare,
+            seed,
+            max_ticks,
+        }) => {
+            info!("running simulation");
+            simulator::run_against_idle_opponent(&start_state, &prepare, seed, max_ticks);
+            return;
+        }
+        Some(Mode::Replay { file }) => {
+            info!("running replay {file:?}");
+            if !replay::run_regression(&file) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Mode::MockClient {
+            url,
+            hello,
+            prepare,
+            start_state,
+            replay,
+            seed,
+            max_ticks,
+        }) => {
+            info!("running mock client against {url:?}");
+            let tick_source = match replay {
+                Some(file) => mock_client::TickSource::Replay(file),
+                None => mock_client::TickSource::Simulation { seed, max_ticks },
+            };
+            let mut client = mock_client::MockClient::new(url);
+            let result = client
+                .run(&hello, &prepare, &start_state, tick_source)
+                .await;
+            client.print_report();
+            if let Err(err) = result {
+                error!("{err}");
+                println!("Mock client failed: {err}");
+                std::process::exit(1);
+            }
+            if client.has_malformed_commands() {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Mode::Tournament {
+            mut bots,
+            matches,
+            start_state,
+            prepare,
+            max_ticks,
+        }) => {
+            if bots.is_empty() {
+                bots = BotImplementations::value_variants().to_vec();
+            }
+            info!("running tournament with {bots:?}");
+            tournament::run_tournament(bots, matches, &start_state, &prepare, max_ticks);
+            return;
+        }
+        Some(Mode::Tune {
+            iterations,
+            matches,
+            seed,
+            start_state,
+            prepare,
+            max_ticks,
+            output,
+        }) => {
+            info!("running parameter tuning for {iterations} iterations");
+            tuner::run_random_search(
+                iterations,
+                matches,
+                seed,
+                &start_state,
+                &prepare,
+                max_ticks,
+                &output,
+            );
+            return;
+        }
+        Some(Mode::Cards {
+            id,
+            name,
+            tier,
+            color,
+            card_type,
+            offense,
+            defense,
+            counters,
+            deck,
+            upgrade,
+            json,
+        }) => {
+            let upgrade = match upgrade {
+                0 => Upgrade::U0,
+                1 => Upgrade::U1,
+                2 => Upgrade::U2,
+                _ => Upgrade::U3,
+            };
+            let query = card_query::CardQuery {
+                id,
+                name,
+                tier,
+                color,
+                card_type,
+                offense,
+                defense,
+                counters,
+                deck,
+                upgrade,
+            };
+            info!("running card query {query:?}");
+            if let Err(err) = card_query::run_query(&query, json) {
+                // keep stdout clean for the json output
+                error!("{err}");
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
     match args.implementation {
         BotImplementations::SkylordsRebot => {
             info!("running example bot");
             warp_wrapper::run::<bot::SkylordsRebot>(args.port).await
         }
+        BotImplementations::RushBot => {
+            info!("running rush bot");
+            warp_wrapper::run::<rush_bot::RushBot>(args.port).await
+        }
     };
 }
@@ -0,0 +1,220 @@
+use api::*;
+use log::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::OnceLock;
+
+// Definitions of known maps, extending the format of scripts/locations.json with the start
+// locations and optionally the strategic priorities of a map. Maps without a definition fall
+// back to discovering the locations at match start.
+
+// directory with one json file per map, new maps only need a file there
+const MAP_DIRECTORY: &'static str = "data/maps";
+// used when the bot is not started from the repository, e.g. only the binary was copied
+const BUILT_IN_DEFINITIONS: [&'static str; 1] = [include_str!("../data/maps/elyon.json")];
+
+// loaded and parsed once on first use
+static MAP_DEFINITIONS: OnceLock<Vec<MapDefinition>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlotPosition {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl SlotPosition {
+    pub fn to_2d(&self) -> Position2D {
+        Position2D {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocationDefinition {
+    // power slots by their index, same as in scripts/locations.json
+    pub powers: BTreeMap<u32, SlotPosition>,
+    pub token: Option<SlotPosition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapDefinition {
+    // values of MapInfo.map the definition is used for
+    pub maps: Vec<String>,
+    pub start_locations: Vec<String>,
+    // location names by priority for each start location, override the computed priorities
+    #[serde(default)]
+    pub location_prios_ahead: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub location_prios_behind: BTreeMap<String, Vec<String>>,
+    pub locations: BTreeMap<String, LocationDefinition>,
+}
+
+impl MapDefinition {
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let definition: MapDefinition =
+            serde_json::from_str(content).map_err(|err| err.to_string())?;
+
+        // every referenced location has to be defined
+        let referenced = definition.start_locations.iter().chain(
+            definition
+                .location_prios_ahead
+                .iter()
+                .chain(definition.location_prios_behind.iter())
+                .flat_map(|(start, prios)| std::iter::once(start).chain(prios.iter())),
+        );
+        for name in referenced {
+            if !definition.locations.contains_key(name) {
+                return Err(format!("Unknown location {name}"));
+            }
+        }
+
+        Ok(definition)
+    }
+
+    pub fn get_location_prios(&self, start_location: &str, ahead: bool) -> Option<&Vec<String>> {
+        if ahead {
+            self.location_prios_ahead.get(start_location)
+        } else {
+            self.location_prios_behind.get(start_location)
+        }
+    }
+}
+
+pub fn get_map_definition(map: &Maps) -> Option<MapDefinition> {
+    let map_name = format!("{map:?}");
+
+    let definitions = MAP_DEFINITIONS.get_or_init(|| match load_map_definitions(MAP_DIRECTORY) {
+        Ok(definitions) => definitions,
+        Err(err) => {
+            warn!("{err}, using the built-in map definitions");
+            parse_definitions(
+                BUILT_IN_DEFINITIONS
+                    .iter()
+                    .map(|content| ("built-in", *content)),
+            )
+        }
+    });
+    let definition = definitions
+        .iter()
+        .find(|definition| definition.maps.contains(&map_name));
+    if definition.is_none() {
+        warn!("No definition for map {map_name}, locations are discovered at match start");
+    }
+    definition.cloned()
+}
+
+pub fn load_map_definitions(directory: &str) -> Result<Vec<MapDefinition>, String> {
+    let entries =
+        fs::read_dir(directory).map_err(|err| format!("Unable to read {directory}: {err}"))?;
+    let mut files: Vec<(String, String)> = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            match fs::read_to_string(&path) {
+                Ok(content) => files.push((path.display().to_string(), content)),
+                Err(err) => error!("Unable to read {}: {err}", path.display()),
+            }
+        }
+    }
+    // same order on every system
+    files.sort();
+
+    let definitions = parse_definitions(
+        files
+            .iter()
+            .map(|(path, content)| (path.as_str(), content.as_str())),
+    );
+    if definitions.is_empty() {
+        return Err(format!("No valid map definitions in {directory}"));
+    }
+    info!(
+        "Loaded {} map definitions from {directory}",
+        definitions.len()
+    );
+    Ok(definitions)
+}
+
+fn parse_definitions<'a>(files: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<MapDefinition> {
+    // invalid files are skipped, the maps fall back to discovering their locations
+    files
+        .filter_map(|(path, content)| match MapDefinition::from_json(content) {
+            Ok(definition) => Some(definition),
+            Err(err) => {
+                error!("Invalid map definition {path}: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elyon_definition() {
+        let definition = get_map_definition(&Maps::ElyonSpectator).unwrap();
+
+        assert_eq!(definition.locations.len(), 11);
+        assert_eq!(definition.start_locations, vec!["South", "North"]);
+        assert_eq!(definition.locations["Center"].powers.len(), 4);
+        assert!(definition.locations["Southwest"].token.is_none());
+        // the priorities are computed
+        assert!(definition.get_location_prios("South", true).is_none());
+    }
+
+    #[test]
+    fn definitions_are_loaded_from_the_directory() {
+        let definitions = load_map_definitions(MAP_DIRECTORY).unwrap();
+        assert_eq!(definitions.len(), 1);
+
+        // a new map only needs a file, invalid ones are skipped
+        let directory = std::env::temp_dir().join(format!(
+            "skylords_rebot_{}_definitions_are_loaded_from_the_directory",
+            std::process::id()
+        ));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("invalid.json"), "{}").unwrap();
+        fs::write(directory.join("notes.txt"), "not a map").unwrap();
+        // without a valid definition the built-in ones are used
+        let only_invalid = load_map_definitions(directory.to_str().unwrap());
+        fs::copy("data/maps/elyon.json", directory.join("elyon.json")).unwrap();
+        let definitions = load_map_definitions(directory.to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(only_invalid.is_err());
+        assert_eq!(definitions.unwrap().len(), 1);
+
+        assert!(load_map_definitions("no/such/directory").is_err());
+    }
+
+    #[test]
+    fn undefined_locations_are_rejected() {
+        let result = MapDefinition::from_json(
+            r#"{
+                "maps": ["Test"],
+                "start_locations": ["South"],
+                "locations": {}
+            }"#,
+        );
+        assert!(result.is_err());
+
+        let result = MapDefinition::from_json(
+            r#"{
+                "maps": ["Test"],
+                "start_locations": ["South"],
+                "location_prios_ahead": {"South": ["South", "North"]},
+                "locations": {"South": {"powers": {}, "token": null}}
+            }"#,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use api::*;
+use log::*;
+use serde::Serialize;
+use std::fs;
+use std::time::Duration;
+
+use crate::deck;
+use crate::replay::{self, ReplayRecord};
+use crate::simulator::{self, Simulator};
+
+// max time to wait for the bot to answer a single request
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+// Talks to the warp_wrapper HTTP server the same way the game client does:
+// hello, prepare and start followed by one tick request for every game state.
+pub struct MockClient {
+    client: reqwest::Client,
+    url: String,
+    num_ticks: u32,
+    num_commands: usize,
+    malformed_commands: Vec<(u32, String)>,
+    rejected_commands: Vec<(u32, Command, String)>,
+}
+
+pub enum TickSource {
+    // game states are generated by the simulator with an idle opponent
+    Simulation { seed: u64, max_ticks: u32 },
+    // game states are taken from a recorded match
+    Replay(String),
+}
+
+impl MockClient {
+    pub fn new(url: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .build()
+            .expect("Unable to create http client");
+
+        MockClient {
+            client,
+            url,
+            num_ticks: 0,
+            num_commands: 0,
+            malformed_commands: vec![],
+            rejected_commands: vec![],
+        }
+    }
+
+    pub async fn run(
+        &mut self,
+        hello_path: &str,
+        prepare_path: &str,
+        start_state_path: &str,
+        tick_source: TickSource,
+    ) -> Result<(), String> {
+        let hello = load_json(hello_path)?;
+        let response = self.post("hello", &hello).await?;
+        info!("Bot answered hello with {response}");
+
+        let mut prepare = load_json(prepare_path)?;
+
+        match tick_source {
+            TickSource::Simulation { seed, max_ticks } => {
+                self.post("prepare", &prepare).await?;
+                let start_state = simulator::load_start_state(start_state_path);
+                self.run_simulation(start_state, seed, max_ticks).await
+            }
+            TickSource::Replay(file_name) => {
+                for record in replay::load_replay(&file_name) {
+                    match record {
+                        ReplayRecord::MapInfo(map_info) => {
+                            // use the recorded map with the deck from the prepare file
+                            prepare["map_info"] = serde_json::to_value(map_info).unwrap();
+                            self.post("prepare", &prepare).await?;
+                        }
+                        ReplayRecord::Strategy { .. } => {
+                            // the bot under test plays with its own strategy
+                        }
+                        ReplayRecord::MatchStart(start_state) => {
+                            self.post("start", &start_state).await?;
+                        }
+                        ReplayRecord::Tick { state, commands: _ } => {
+                            let tick = state.current_tick.0.get();
+                            self.tick(tick, &state).await?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn run_simulation(
+        &mut self,
+        start_state: GameStartState,
+        seed: u64,
+        max_ticks: u32,
+    ) -> Result<(), String> {
+        let bot_id = start_state.your_player_id;
+        let mut simulator = Simulator::new(start_state.clone(), seed);
+        // the simulator needs to know the deck to resolve the card positions of ProduceSquad
+        simulator.set_deck(bot_id, &deck::bot_deck().deck);
+
+        self.post("start", &start_state).await?;
+
+        while simulator.current_tick() <= max_ticks && !simulator.is_finished() {
+            let tick = simulator.current_tick();
+            let commands = self.tick(tick, &simulator.game_state()).await?;
+            for (command, reason) in simulator.apply_commands(bot_id, commands) {
+                warn!("Tick {tick}: command {command:?} was rejected: {reason}");
+                self.rejected_commands.push((tick, command, reason));
+            }
+            simulator.step();
+        }
+
+        info!("Simulation winner: {:?}", simulator.winner());
+        Ok(())
+    }
+
+    async fn tick(&mut self, tick: u32, state: &GameState) -> Result<Vec<Command>, String> {
+        let response = self.post("tick", state).await?;
+        self.num_ticks += 1;
+
+        let values = match serde_json::from_str::<serde_json::Value>(&response) {
+            Ok(serde_json::Value::Array(values)) => values,
+            _ => {
+                error!("Tick {tick}: response is not a list of commands: {response}");
+                self.malformed_commands.push((tick, response));
+                return Ok(vec![]);
+            }
+        };
+
+        let mut commands = vec![];
+        for value in values {
+            match serde_json::from_value::<Command>(value.clone()) {
+                Ok(command) => commands.push(command),
+                Err(err) => {
+                    error!("Tick {tick}: malformed command {value}: {err}");
+                    self.malformed_commands.push((tick, value.to_string()));
+                }
+            }
+        }
+        self.num_commands += commands.len();
+
+        Ok(commands)
+    }
+
+    async fn post<T: Serialize>(&self, endpoint: &str, body: &T) -> Result<String, String> {
+        let url = format!("{}/{endpoint}", self.url);
+        let response = self
+            .client
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|err| format!("Request to {url} failed: {err}"))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|err| format!("Unable to read response from {url}: {err}"))?;
+
+        if !status.is_success() {
+            return Err(format!("{url} returned {status}: {text}"));
+        }
+        Ok(text)
+    }
+
+    pub fn print_report(&self) {
+        println!(
+            "Sent {} ticks, received {} valid commands",
+            self.num_ticks, self.num_commands
+        );
+
+        println!("Malformed commands: {}", self.malformed_commands.len());
+        for (tick, command) in self.malformed_commands.iter() {
+            println!("  tick {tick}: {command}");
+        }
+
+        println!("Rejected commands: {}", self.rejected_commands.len());
+        for (tick, command, reason) in self.rejected_commands.iter() {
+            println!("  tick {tick}: {command:?} ({reason})");
+        }
+    }
+
+    pub fn has_malformed_commands(&self) -> bool {
+        self.malformed_commands.len() > 0
+    }
+}
+
+fn load_json(path: &str) -> Result<serde_json::Value, String> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Unable to read {path}: {err}"))?;
+    serde_json::from_str(&content).map_err(|err| format!("Unable to parse {path}: {err}"))
+}
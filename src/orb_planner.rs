@@ -0,0 +1,219 @@
+use api::*;
+
+use crate::card_data::CardInfo;
+use crate::deck::NUM_TIERS;
+use crate::deck_analysis::{self, DeckAnalysis};
+
+// Plans the order in which the orb colors are built. Every possible sequence of the missing orbs
+// is scored by the cards it makes playable at each tier, so cards which are unlocked earlier
+// count more often. The plan always starts from the orbs a player currently owns, which allows
+// to re-plan during the match, e.g. after an orb was destroyed.
+
+// order in which colors are preferred when two sequences are scored equally
+const ORB_COLORS: [OrbColor; 4] = [
+    OrbColor::Fire,
+    OrbColor::Shadow,
+    OrbColor::Nature,
+    OrbColor::Frost,
+];
+// change of a card's value per matchup point against the units of the opponent's deck
+const OPPONENT_MATCHUP_WEIGHT: f32 = 0.2;
+// value of a card which is countered by the opponent's deck, cards keep some value for the tier
+const MIN_CARD_VALUE: f32 = 0.1;
+
+#[derive(Debug)]
+pub struct OrbPlanner {
+    cards: Vec<CardInfo>,
+}
+
+impl OrbPlanner {
+    pub fn new(cards: &[CardInfo]) -> OrbPlanner {
+        OrbPlanner {
+            cards: cards.to_vec(),
+        }
+    }
+
+    pub fn plan(&self, orbs: &[OrbColor], opponent: Option<&DeckAnalysis>) -> Vec<CreateOrbColor> {
+        // the color of the starting orb is only known after the first card was played
+        let mut planned: Vec<OrbColor> = orbs
+            .iter()
+            .copied()
+            .filter(|orb| ORB_COLORS.contains(orb))
+            .collect();
+        let num_missing = NUM_TIERS.saturating_sub(planned.len());
+        let card_values = self.get_card_values(opponent);
+
+        let mut best_plan: Option<(Vec<OrbColor>, f32)> = None;
+        for sequence in get_sequences(num_missing) {
+            let mut candidate = planned.clone();
+            candidate.extend(sequence);
+            let score = self.get_score(&candidate, &card_values);
+            if best_plan
+                .as_ref()
+                .map_or(true, |(_, best_score)| score > *best_score)
+            {
+                best_plan = Some((candidate, score));
+            }
+        }
+        if let Some((best, _)) = best_plan {
+            planned = best;
+        }
+
+        planned
+            .into_iter()
+            .filter_map(to_create_orb_color)
+            .collect()
+    }
+
+    pub fn get_next_orb(
+        &self,
+        orbs: &[OrbColor],
+        opponent: Option<&DeckAnalysis>,
+    ) -> Option<CreateOrbColor> {
+        // None once all tiers are reached, the plan only contains the colored orbs
+        let num_colored = orbs.iter().filter(|orb| ORB_COLORS.contains(orb)).count();
+        self.plan(orbs, opponent).get(num_colored).copied()
+    }
+
+    fn get_card_values(&self, opponent: Option<&DeckAnalysis>) -> Vec<f32> {
+        // every card is worth the same, unless it counters or is countered by the opponent
+        self.cards
+            .iter()
+            .map(|card| {
+                let Some(opponent) = opponent else {
+                    return 1.;
+                };
+                match opponent.get_matchup_against(card, card.orb_requirements.total) {
+                    Some(score) => (1. + OPPONENT_MATCHUP_WEIGHT * score).max(MIN_CARD_VALUE),
+                    None => 1.,
+                }
+            })
+            .collect()
+    }
+
+    fn get_score(&self, orbs: &[OrbColor], card_values: &[f32]) -> f32 {
+        // sum of the values of the playable cards after each built orb
+        let mut score = 0.;
+        for num_orbs in 1..=orbs.len() {
+            for (card, value) in self.cards.iter().zip(card_values) {
+                let missing =
+                    deck_analysis::get_missing_orbs(&card.orb_requirements, &orbs[..num_orbs]);
+                if missing.total == 0 {
+                    score += value;
+                }
+            }
+        }
+        score
+    }
+}
+
+fn get_sequences(length: usize) -> Vec<Vec<OrbColor>> {
+    // all color sequences of the length, in the order of ORB_COLORS
+    let mut sequences: Vec<Vec<OrbColor>> = vec![vec![]];
+    for _ in 0..length {
+        sequences = sequences
+            .into_iter()
+            .flat_map(|sequence| {
+                ORB_COLORS.iter().map(move |color| {
+                    let mut next = sequence.clone();
+                    next.push(*color);
+                    next
+                })
+            })
+            .collect();
+    }
+    sequences
+}
+
+fn to_create_orb_color(color: OrbColor) -> Option<CreateOrbColor> {
+    match color {
+        OrbColor::Fire => Some(CreateOrbColor::Fire),
+        OrbColor::Shadow => Some(CreateOrbColor::Shadow),
+        OrbColor::Nature => Some(CreateOrbColor::Nature),
+        OrbColor::Frost => Some(CreateOrbColor::Frost),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_data::CardData;
+    use crate::deck;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn replan_after_destroyed_orb() {
+        let planner = &deck::bot_deck().orb_planner;
+        let plan = vec![
+            CreateOrbColor::Shadow,
+            CreateOrbColor::Frost,
+            CreateOrbColor::Frost,
+        ];
+        assert_eq!(planner.plan(&[], None), plan);
+        assert_eq!(planner.plan(&[OrbColor::Starting], None), plan);
+        assert_eq!(
+            planner.get_next_orb(&[OrbColor::Starting], None),
+            Some(CreateOrbColor::Shadow)
+        );
+        assert_eq!(
+            planner.get_next_orb(&[OrbColor::Shadow], None),
+            Some(CreateOrbColor::Frost)
+        );
+        assert_eq!(
+            planner.get_next_orb(&[OrbColor::Shadow, OrbColor::Frost, OrbColor::Frost], None),
+            None
+        );
+
+        // the Shadow orb was destroyed, the plan continues with the orbs left
+        assert_eq!(
+            planner.plan(&[OrbColor::Frost], None),
+            vec![
+                CreateOrbColor::Frost,
+                CreateOrbColor::Shadow,
+                CreateOrbColor::Frost
+            ]
+        );
+        assert_eq!(
+            planner.get_next_orb(&[OrbColor::Frost], None),
+            Some(CreateOrbColor::Shadow)
+        );
+    }
+
+    #[test]
+    fn plan_against_opponent_deck() {
+        let builder = ScenarioBuilder::new();
+        let start_state = builder.start_state();
+        let opponent = start_state
+            .players
+            .iter()
+            .find(|p| p.entity.id == builder.opponent_id())
+            .unwrap();
+        let mut card_data = CardData::new();
+        card_data.load();
+        let opponent_deck = DeckAnalysis::from_deck(&opponent.deck.cards, &card_data);
+
+        let planner = &deck::bot_deck().orb_planner;
+        let plan = planner.plan(&[], Some(&opponent_deck));
+        assert_eq!(plan.len(), NUM_TIERS);
+        // started orbs are kept
+        assert_eq!(
+            planner.plan(&[OrbColor::Nature], Some(&opponent_deck))[0],
+            CreateOrbColor::Nature
+        );
+
+        // both T1 units are worth the same on their own, the Fire one comes first by the color
+        // order. Dreadcharger counters the T1 units of the opponent, so Shadow is built first
+        let cards = vec![
+            card_data.get_card_info(&Dreadcharger).unwrap(),
+            card_data.get_card_info(&Sunstriders).unwrap(),
+        ];
+        let planner = OrbPlanner::new(&cards);
+        assert_eq!(planner.plan(&[], None)[0], CreateOrbColor::Fire);
+        assert_eq!(
+            planner.plan(&[], Some(&opponent_deck))[0],
+            CreateOrbColor::Shadow
+        );
+    }
+}
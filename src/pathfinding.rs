@@ -0,0 +1,229 @@
+use api::*;
+use log::*;
+use ordered_float::OrderedFloat;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use crate::game_info::GameInfo;
+use crate::influence_map::InfluenceMap;
+use crate::utils;
+
+// A* search over the walkability grid. Cells threatened by the opponent's squads are more
+// expensive to cross, so routes go around enemy armies if there is a reasonable detour. The
+// found path is shortened to the few waypoints where the direction changes.
+
+// additional cost factor per unit of threat on a cell
+const THREAT_COST_FACTOR: f32 = 0.02;
+// max distance to search for a walkable cell if start or destination are not walkable
+const MAX_WALKABLE_CELL_DIST: f32 = 20.;
+// limit of expanded cells before the search is given up
+const MAX_EXPANDED_CELLS: usize = 100_000;
+// threat along a shortcut may exceed the threat of the replaced path by this amount
+const SHORTCUT_THREAT_TOLERANCE: f32 = 1.;
+
+type Cell = (usize, usize);
+
+pub fn find_path(
+    from: &Position2D,
+    to: &Position2D,
+    game_info: &GameInfo,
+) -> Option<Vec<Position2D>> {
+    // return the waypoints from the start to the destination, the destination is the last one
+    let grid = &game_info.walkability;
+    let influence = &game_info.influence;
+
+    let start = grid.get_closest_walkable_cell(from, MAX_WALKABLE_CELL_DIST)?;
+    let goal = grid.get_closest_walkable_cell(to, MAX_WALKABLE_CELL_DIST)?;
+    let goal_pos = grid.get_cell_center(goal);
+
+    let mut open: BinaryHeap<Reverse<(OrderedFloat<f32>, Cell)>> = BinaryHeap::new();
+    let mut costs: BTreeMap<Cell, f32> = BTreeMap::new();
+    let mut previous: BTreeMap<Cell, Cell> = BTreeMap::new();
+    // cells which were already expanded with their lowest cost
+    let mut closed: BTreeSet<Cell> = BTreeSet::new();
+    open.push(Reverse((OrderedFloat(0.), start)));
+    costs.insert(start, 0.);
+
+    let mut num_expanded = 0;
+    while let Some(Reverse((_, cell))) = open.pop() {
+        if cell == goal {
+            break;
+        }
+        // cells are pushed again when a cheaper way is found, skip the outdated entries
+        if !closed.insert(cell) {
+            continue;
+        }
+
+        num_expanded += 1;
+        if num_expanded > MAX_EXPANDED_CELLS {
+            warn!("No path found from {from:?} to {to:?} within {MAX_EXPANDED_CELLS} cells");
+            return None;
+        }
+
+        let cell_pos = grid.get_cell_center(cell);
+        let cell_cost = costs[&cell];
+        for neighbor in grid.get_walkable_neighbors(cell) {
+            let neighbor_pos = grid.get_cell_center(neighbor);
+            let threat = influence.threat_at(&neighbor_pos);
+            let step_cost =
+                utils::dist(&cell_pos, &neighbor_pos) * (1. + THREAT_COST_FACTOR * threat);
+            let new_cost = cell_cost + step_cost;
+            if costs.get(&neighbor).map_or(true, |&cost| new_cost < cost) {
+                costs.insert(neighbor, new_cost);
+                previous.insert(neighbor, cell);
+                // the direct distance never overestimates as each step costs at least its length
+                let estimate = new_cost + utils::dist(&neighbor_pos, &goal_pos);
+                open.push(Reverse((OrderedFloat(estimate), neighbor)));
+            }
+        }
+    }
+
+    if !costs.contains_key(&goal) {
+        debug!("Destination {to:?} is not reachable from {from:?}");
+        return None;
+    }
+
+    let mut cells = vec![goal];
+    while let Some(cell) = previous.get(cells.last().unwrap()) {
+        cells.push(*cell);
+    }
+    cells.reverse();
+
+    let mut path: Vec<Position2D> = cells.iter().map(|c| grid.get_cell_center(*c)).collect();
+    path[0] = *from;
+    path.push(*to);
+    Some(shorten_path(&path, game_info))
+}
+
+pub fn get_route_threat(
+    from: &Position2D,
+    waypoints: &[Position2D],
+    influence: &InfluenceMap,
+) -> f32 {
+    // highest threat along the route above the threat at the start. Enemies which already reach
+    // the squad, e.g. when chasing it, can't be avoided by any other route
+    let mut threat: f32 = 0.;
+    let mut current = from;
+    for waypoint in waypoints.iter() {
+        threat = threat.max(influence.max_threat_on_line(current, waypoint));
+        current = waypoint;
+    }
+    (threat - influence.threat_at(from)).max(0.)
+}
+
+fn shorten_path(path: &[Position2D], game_info: &GameInfo) -> Vec<Position2D> {
+    // skip points as long as the direct line is walkable and not more threatened than the path
+    let grid = &game_info.walkability;
+    let influence = &game_info.influence;
+
+    let mut waypoints = vec![];
+    let mut anchor = 0;
+    while anchor < path.len() - 1 {
+        let mut next = anchor + 1;
+        let mut path_threat = influence.max_threat_on_line(&path[anchor], &path[next]);
+        for candidate in (anchor + 2)..path.len() {
+            path_threat = path_threat
+                .max(influence.max_threat_on_line(&path[candidate - 1], &path[candidate]));
+            let shortcut_threat = influence.max_threat_on_line(&path[anchor], &path[candidate]);
+            // the start and the destination may be inside of a slot footprint
+            let walkable = candidate == path.len() - 1
+                || grid.is_line_walkable(&path[anchor.max(1)], &path[candidate]);
+            if !walkable || shortcut_threat > path_threat + SHORTCUT_THREAT_TOLERANCE {
+                break;
+            }
+            next = candidate;
+        }
+        waypoints.push(path[next]);
+        anchor = next;
+    }
+    waypoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    fn position(name: &str, game_info: &GameInfo) -> Position2D {
+        let loc = location::get_location_by_name(name, game_info).unwrap();
+        game_info.locations[&loc].position()
+    }
+
+    #[test]
+    fn free_path_is_direct() {
+        let game_info = ScenarioBuilder::new().game_info();
+        let south = position("South", &game_info);
+        let from = Position2D {
+            x: south.x,
+            y: south.y - 10.,
+        };
+        let to = Position2D {
+            x: south.x + 20.,
+            y: south.y - 30.,
+        };
+
+        let path = find_path(&from, &to, &game_info).unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(path.last().unwrap().y, to.y);
+    }
+
+    #[test]
+    fn path_avoids_enemy_squads() {
+        let mut builder = ScenarioBuilder::new();
+        let game_info = builder.game_info();
+        let from = position("South", &game_info);
+        let to = position("North", &game_info);
+        let free_path = find_path(&from, &to, &game_info).unwrap();
+        assert_eq!(
+            get_route_threat(&from, &free_path, &game_info.influence),
+            0.
+        );
+
+        // enemies stand in the middle of the longest straight part of the free path
+        let mut points = vec![from];
+        points.extend(free_path.iter().cloned());
+        let longest = points
+            .windows(2)
+            .max_by(|a, b| utils::dist(&a[0], &a[1]).total_cmp(&utils::dist(&b[0], &b[1])))
+            .unwrap();
+        let enemy_pos = Position2D {
+            x: (longest[0].x + longest[1].x) / 2.,
+            y: (longest[0].y + longest[1].y) / 2.,
+        };
+        builder.opponent_squads(Dreadcharger, 3, "Center", 1.);
+        let enemy_ids = builder.squad_ids(builder.opponent_id());
+        builder.move_squads(&enemy_ids, enemy_pos);
+        let game_info = builder.game_info();
+
+        let path = find_path(&from, &to, &game_info).unwrap();
+        assert_eq!(path.last().unwrap().y, to.y);
+        let path_threat = get_route_threat(&from, &path, &game_info.influence);
+        let free_path_threat = get_route_threat(&from, &free_path, &game_info.influence);
+        assert!(path_threat < free_path_threat);
+    }
+
+    #[test]
+    fn chasing_enemies_do_not_raise_the_route_threat() {
+        let mut builder = ScenarioBuilder::new();
+        let game_info = builder.game_info();
+        let from = position("South", &game_info);
+        let to = position("North", &game_info);
+        let path = find_path(&from, &to, &game_info).unwrap();
+
+        // enemies right behind the squad
+        let dist = utils::dist(&from, &path[0]);
+        let chaser_pos = Position2D {
+            x: from.x - (path[0].x - from.x) / dist * 3.,
+            y: from.y - (path[0].y - from.y) / dist * 3.,
+        };
+        builder.opponent_squads(Dreadcharger, 3, "Center", 1.);
+        let enemy_ids = builder.squad_ids(builder.opponent_id());
+        builder.move_squads(&enemy_ids, chaser_pos);
+        let game_info = builder.game_info();
+
+        assert!(game_info.influence.is_threatened(&from));
+        assert_eq!(get_route_threat(&from, &path, &game_info.influence), 0.);
+    }
+}
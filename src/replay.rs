@@ -0,0 +1,193 @@
+use api::warp_wrapper::BotImpl;
+use api::*;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use crate::bot::SkylordsRebot;
+use crate::controller::macro_controller::MacroState;
+use crate::strategy_config::StrategyConfig;
+
+pub const REPLAY_FILE_EXTENSION: &'static str = ".replay.jsonl.gz";
+
+// One line of a replay file. A replay starts with the map info, the strategy and the start state
+// followed by one record for each tick.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ReplayRecord {
+    MapInfo(MapInfo),
+    // deck name and strategy config the bot played with
+    Strategy {
+        deck: String,
+        config: StrategyConfig,
+    },
+    MatchStart(GameStartState),
+    Tick {
+        state: GameState,
+        commands: Vec<Command>,
+    },
+}
+
+pub struct ReplayRecorder {
+    file_name: String,
+    writer: GzEncoder<BufWriter<File>>,
+}
+
+impl ReplayRecorder {
+    pub fn create(file_name: String) -> Option<ReplayRecorder> {
+        match File::create(&file_name) {
+            Ok(file) => {
+                info!("Recording replay to {file_name:?}");
+                Some(ReplayRecorder {
+                    file_name,
+                    writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+                })
+            }
+            Err(err) => {
+                error!("Unable to create replay file {file_name:?}: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn record(&mut self, record: &ReplayRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Unable to serialize replay record: {err}");
+                return;
+            }
+        };
+
+        // flush after every record, the bot is usually killed without a proper shutdown and
+        // everything written up to the last flush can still be decompressed
+        if let Err(err) = writeln!(self.writer, "{line}").and_then(|_| self.writer.flush()) {
+            error!("Unable to write to replay file {:?}: {err}", self.file_name);
+        }
+    }
+}
+
+pub fn load_replay(file_name: &str) -> Vec<ReplayRecord> {
+    let file = File::open(file_name).expect("Unable to open replay file");
+    let reader = BufReader::new(GzDecoder::new(file));
+
+    let mut records = vec![];
+    for line in reader.lines() {
+        // the recording bot might have been killed in the middle of writing a record,
+        // keep everything up to the last complete one
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                warn!(
+                    "Replay file ends unexpectedly after {} records: {err}",
+                    records.len()
+                );
+                break;
+            }
+        };
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => {
+                warn!("Unable to parse replay record {}: {err}", records.len());
+                break;
+            }
+        }
+    }
+
+    records
+}
+
+// Feeds all recorded states into a fresh bot and compares the produced commands against the
+// recorded ones. Returns false if the bot diverged from the recording.
+pub fn run_regression(file_name: &str) -> bool {
+    let records = load_replay(file_name);
+    info!("Loaded {} records from {file_name:?}", records.len());
+
+    let mut bot: Option<SkylordsRebot> = None;
+    let mut map_info: Option<MapInfo> = None;
+    let mut has_strategy = false;
+    let mut num_ticks = 0;
+
+    for record in records {
+        match record {
+            ReplayRecord::MapInfo(info) => {
+                let deck = SkylordsRebot::decks_for_map(&info)
+                    .first()
+                    .expect("Bot has no deck");
+                bot = Some(SkylordsRebot::prepare_for_battle(&info, deck));
+                map_info = Some(info);
+            }
+            ReplayRecord::Strategy { deck, config } => {
+                let map_info = map_info
+                    .as_ref()
+                    .expect("Replay has no map info before the strategy");
+                let decks = SkylordsRebot::decks_for_map(map_info);
+                let deck = decks.iter().find(|d| d.name == deck).unwrap_or_else(|| {
+                    warn!("Recorded deck {deck:?} is unknown, using the first deck");
+                    decks.first().expect("Bot has no deck")
+                });
+                bot = Some(SkylordsRebot::with_strategy(map_info, deck, &config));
+                has_strategy = true;
+            }
+            ReplayRecord::MatchStart(start_state) => {
+                if !has_strategy {
+                    warn!("Replay has no recorded strategy, using the current one");
+                }
+                bot.as_mut()
+                    .expect("Replay has no map info before the match start")
+                    .match_start(start_state);
+            }
+            ReplayRecord::Tick { state, commands } => {
+                let bot = bot
+                    .as_mut()
+                    .expect("Replay has no map info before the first tick");
+                let tick = state.current_tick.0.get();
+                let produced_commands = bot.tick(state);
+                num_ticks += 1;
+
+                let recorded: Vec<serde_json::Value> =
+                    commands.iter().map(command_to_value).collect();
+                let produced: Vec<serde_json::Value> =
+                    produced_commands.iter().map(command_to_value).collect();
+
+                if recorded != produced {
+                    print_divergence(tick, bot.macro_state(), &recorded, &produced);
+                    return false;
+                }
+            }
+        }
+    }
+
+    println!("No divergence found in {num_ticks} ticks");
+    true
+}
+
+fn command_to_value(command: &Command) -> serde_json::Value {
+    // commands can not be compared directly, their json representation can
+    serde_json::to_value(command).expect("Unable to serialize command")
+}
+
+fn print_divergence(
+    tick: u32,
+    macro_state: &MacroState,
+    recorded: &Vec<serde_json::Value>,
+    produced: &Vec<serde_json::Value>,
+) {
+    println!("First divergence at tick {tick}, MacroState: {macro_state:?}");
+    for command in recorded.iter().filter(|c| !produced.contains(c)) {
+        println!("- {command}");
+    }
+    for command in produced.iter().filter(|c| !recorded.contains(c)) {
+        println!("+ {command}");
+    }
+    if recorded.iter().all(|c| produced.contains(c))
+        && produced.iter().all(|c| recorded.contains(c))
+    {
+        // same commands, but different order or number of duplicates
+        println!("recorded: {recorded:?}");
+        println!("produced: {produced:?}");
+    }
+}
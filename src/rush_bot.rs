@@ -0,0 +1,137 @@
+use api::*;
+use log::*;
+use std::collections::BTreeMap;
+
+use crate::deck;
+use crate::game_info::GameInfo;
+
+// Simple scripted opponent: spams the first T1 unit of the deck at it's start orb and attacks
+// the closest enemy structure with all squads as soon as enough squads are spawned.
+
+const NAME: &'static str = "RushBot";
+// number of squads to gather before the first attack
+const RUSH_NUM_SQUADS: usize = 3;
+// same timeout as the CommandScheduler uses between two played cards
+const CARD_PLAY_TICK_TIMEOUT: u32 = 10;
+
+pub struct RushBot {
+    game_info: GameInfo,
+    attacking: bool,
+    squad_targets: BTreeMap<EntityId, EntityId>,
+    tick_last_played_card: u32,
+}
+
+impl warp_wrapper::BotImpl for RushBot {
+    fn name() -> &'static str {
+        NAME
+    }
+
+    fn decks_for_map(_map_info: &MapInfo) -> &'static [Deck] {
+        // plays the same deck as the bot
+        std::slice::from_ref(&deck::bot_deck().deck)
+    }
+
+    fn prepare_for_battle(map_info: &MapInfo, _deck: &'static Deck) -> Self {
+        info!("{NAME} preparing for: {:?}", map_info.map);
+
+        let mut game_info = GameInfo::new();
+        game_info.set_map(map_info);
+
+        RushBot {
+            game_info,
+            attacking: false,
+            squad_targets: BTreeMap::new(),
+            tick_last_played_card: 0,
+        }
+    }
+
+    fn match_start(&mut self, state: GameStartState) {
+        self.game_info.init(state);
+    }
+
+    fn tick(&mut self, state: GameState) -> Vec<Command> {
+        let current_tick = state.current_tick.0.get();
+        self.game_info.parse_state(state);
+
+        let mut commands = vec![];
+
+        if let Some(command) = self.spawn_squad(current_tick) {
+            commands.push(command);
+        }
+
+        if self.game_info.bot.squads.len() >= RUSH_NUM_SQUADS {
+            if !self.attacking {
+                info!("{NAME} starts attacking");
+            }
+            self.attacking = true;
+        }
+
+        if self.attacking {
+            commands.append(&mut self.attack_closest_structures());
+        }
+
+        commands
+    }
+}
+
+impl RushBot {
+    fn spawn_squad(&mut self, current_tick: u32) -> Option<Command> {
+        if current_tick < self.tick_last_played_card + CARD_PLAY_TICK_TIMEOUT {
+            return None;
+        }
+
+        let bot_deck = deck::bot_deck();
+        let card = bot_deck.get_tier_units(1)[0];
+        let card_position = bot_deck.get_card_position(card)?;
+        let card_cost = self
+            .game_info
+            .card_data
+            .get_card_info(&card)
+            .ok()?
+            .power_cost;
+        if self.game_info.bot.power < card_cost {
+            return None;
+        }
+
+        // spawn at the start orb
+        let start_token = self.game_info.bot.start_token?;
+        let token_slot = self.game_info.bot.token_slots.get(&start_token)?;
+
+        self.tick_last_played_card = current_tick;
+        Some(Command::ProduceSquad {
+            card_position: card_position as u8,
+            xy: token_slot.entity.position.to_2d(),
+        })
+    }
+
+    fn attack_closest_structures(&mut self) -> Vec<Command> {
+        let mut commands = vec![];
+
+        // forget targets of dead squads
+        let squad_ids: Vec<EntityId> = self.game_info.bot.squads.keys().cloned().collect();
+        self.squad_targets.retain(|id, _| squad_ids.contains(id));
+
+        for (squad_id, squad) in self.game_info.bot.squads.iter() {
+            let current_target = self.squad_targets.get(squad_id);
+            let target_alive = current_target.is_some_and(|target| {
+                self.game_info.opponent.power_slots.contains_key(target)
+                    || self.game_info.opponent.token_slots.contains_key(target)
+            });
+            if target_alive {
+                continue;
+            }
+
+            let squad_pos = squad.entity.position.to_2d();
+            if let Some(target) = self.game_info.opponent.get_closest_slot(&squad_pos) {
+                commands.push(Command::GroupAttack {
+                    squads: vec![*squad_id],
+                    target_entity_id: target,
+                    force_attack: false,
+                });
+                self.squad_targets.insert(*squad_id, target);
+            }
+        }
+
+        commands
+    }
+}
@@ -0,0 +1,1067 @@
+pub mod entities;
+
+use api::warp_wrapper::BotImpl;
+use api::*;
+use log::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::NonZeroU32;
+
+use crate::bot::SkylordsRebot;
+use crate::card_data::{matchup, CardData, CardInfo};
+use crate::deck;
+use crate::utils;
+
+// 1 Tick = 100 ms
+const TICKS_PER_SECOND: f32 = 10.;
+// a power well pays off it's cost of 100 power after 200 seconds
+const POWER_PER_WELL_PER_SECOND: f32 = 0.5;
+// share of the void power which flows back to the player each second
+const VOID_RETURN_PER_SECOND: f32 = 0.03;
+// minimum amount of void power which flows back to the player each second
+const MIN_VOID_RETURN_PER_SECOND: f32 = 0.5;
+// share of the bound power which goes into the void when a squad dies
+const VOID_SHARE_ON_DEATH: f32 = 0.9;
+const POWER_SLOT_COST: f32 = 100.;
+const T2_TOKEN_SLOT_COST: f32 = 150.;
+const T3_TOKEN_SLOT_COST: f32 = 250.;
+// used for slots without a health aspect in the start state
+const POWER_SLOT_HEALTH: f32 = 2000.;
+const TOKEN_SLOT_HEALTH: f32 = 3000.;
+const POWER_SLOT_BUILD_TICKS: u32 = 200;
+const TOKEN_SLOT_BUILD_TICKS: u32 = 300;
+// health restored for each repair command
+const REPAIR_HP_PER_COMMAND: f32 = 2.;
+// distance a squad walks per second
+const SQUAD_SPEED: f32 = 6.;
+const MELEE_RANGE: f32 = 4.;
+const RANGED_RANGE: f32 = 20.;
+// radius in which idle squads attack enemies on their own
+const AGGRO_RADIUS: f32 = 20.;
+// max distance to an own structure or squad when spawning squads or building slots
+const BUILD_RADIUS: f32 = 25.;
+// damage values in cards.json are given per 20 seconds
+const CARD_DAMAGE_SECONDS: f32 = 20.;
+// random deviation of the damage dealt each tick
+const DAMAGE_VARIANCE: f32 = 0.2;
+
+#[derive(Debug)]
+struct SimPlayer {
+    id: EntityId,
+    team: u8,
+    deck: Vec<CardId>,
+    power: f32,
+    void_power: f32,
+}
+
+#[derive(Debug)]
+struct SimSquad {
+    id: EntityId,
+    owner: EntityId,
+    card_id: CardId,
+    card_info: CardInfo,
+    position: Position2D,
+    hp: f32,
+    bound_power: f32,
+    figure_ids: Vec<EntityId>,
+    destination: Option<Position2D>,
+    force_move: bool,
+    target: Option<EntityId>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SlotKind {
+    Power,
+    Token(OrbColor),
+}
+
+#[derive(Debug)]
+struct Construction {
+    slot_id: EntityId,
+    owner: EntityId,
+    kind: SlotKind,
+    finished_tick: u32,
+}
+
+pub struct Simulator {
+    start_state: GameStartState,
+    current_tick: u32,
+    rng: StdRng,
+    card_data: CardData,
+    next_entity_id: u32,
+    players: BTreeMap<EntityId, SimPlayer>,
+    squads: BTreeMap<EntityId, SimSquad>,
+    power_slots: Vec<PowerSlot>,
+    token_slots: Vec<TokenSlot>,
+    structure_health: BTreeMap<EntityId, (f32, f32)>, // (current hp, max hp)
+    constructions: Vec<Construction>,
+}
+
+impl Simulator {
+    pub fn new(start_state: GameStartState, seed: u64) -> Simulator {
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        let mut players = BTreeMap::new();
+        for player in start_state.players.iter() {
+            players.insert(
+                player.entity.id,
+                SimPlayer {
+                    id: player.entity.id,
+                    team: player.entity.team,
+                    deck: player.deck.cards.to_vec(),
+                    power: player.entity.power,
+                    void_power: player.entity.void_power,
+                },
+            );
+        }
+
+        let mut structure_health = BTreeMap::new();
+        let mut max_entity_id: u32 = start_state.your_player_id.0.get();
+        for power_slot in start_state.entities.power_slots.iter() {
+            let max_hp =
+                entities::get_entity_max_health(&power_slot.entity).unwrap_or(POWER_SLOT_HEALTH);
+            structure_health.insert(power_slot.entity.id, (max_hp, max_hp));
+            max_entity_id = max_entity_id.max(power_slot.entity.id.0.get());
+        }
+        for token_slot in start_state.entities.token_slots.iter() {
+            let max_hp =
+                entities::get_entity_max_health(&token_slot.entity).unwrap_or(TOKEN_SLOT_HEALTH);
+            structure_health.insert(token_slot.entity.id, (max_hp, max_hp));
+            max_entity_id = max_entity_id.max(token_slot.entity.id.0.get());
+        }
+        for player_id in players.keys() {
+            max_entity_id = max_entity_id.max(player_id.0.get());
+        }
+        for barrier_module in start_state.entities.barrier_modules.iter() {
+            max_entity_id = max_entity_id.max(barrier_module.entity.id.0.get());
+        }
+
+        Simulator {
+            power_slots: start_state.entities.power_slots.clone(),
+            token_slots: start_state.entities.token_slots.clone(),
+            start_state,
+            current_tick: 1,
+            rng: StdRng::seed_from_u64(seed),
+            card_data,
+            next_entity_id: max_entity_id + 1,
+            players,
+            squads: BTreeMap::new(),
+            structure_health,
+            constructions: vec![],
+        }
+    }
+
+    pub fn set_deck(&mut self, player_id: EntityId, deck: &Deck) {
+        if let Some(player) = self.players.get_mut(&player_id) {
+            player.deck = deck.cards.to_vec();
+        } else {
+            error!("Unable to set deck for unknown player {player_id:?}");
+        }
+    }
+
+    pub fn current_tick(&self) -> u32 {
+        self.current_tick
+    }
+
+    pub fn game_state(&self) -> GameState {
+        let players = self
+            .start_state
+            .players
+            .iter()
+            .map(|p| {
+                let mut player_entity = p.entity.clone();
+                if let Some(player) = self.players.get(&p.entity.id) {
+                    player_entity.power = player.power;
+                    player_entity.void_power = player.void_power;
+                }
+                player_entity
+            })
+            .collect();
+
+        let mut state_entities = self.start_state.entities.clone();
+        state_entities.power_slots = self
+            .power_slots
+            .iter()
+            .map(|power_slot| {
+                let (hp, max_hp) = self.structure_health[&power_slot.entity.id];
+                let mut power_slot = power_slot.clone();
+                entities::set_entity_health(&mut power_slot.entity, hp, max_hp);
+                power_slot
+            })
+            .collect();
+        state_entities.token_slots = self
+            .token_slots
+            .iter()
+            .map(|token_slot| {
+                let (hp, max_hp) = self.structure_health[&token_slot.entity.id];
+                let mut token_slot = token_slot.clone();
+                entities::set_entity_health(&mut token_slot.entity, hp, max_hp);
+                token_slot
+            })
+            .collect();
+
+        state_entities.squads = vec![];
+        state_entities.figures = vec![];
+        for squad in self.squads.values() {
+            let max_hp = squad.card_info.health;
+            state_entities.squads.push(entities::new_squad(
+                squad.id,
+                squad.owner,
+                squad.card_id,
+                &squad.position,
+                squad.bound_power,
+                &squad.figure_ids,
+                squad.hp,
+                max_hp,
+            ));
+
+            // health is split evenly between all figures of a squad
+            let num_figures = squad.figure_ids.len() as f32;
+            for figure_id in squad.figure_ids.iter() {
+                state_entities.figures.push(entities::new_figure(
+                    *figure_id,
+                    squad.owner,
+                    squad.id,
+                    &squad.position,
+                    squad.hp / num_figures,
+                    max_hp / num_figures,
+                ));
+            }
+        }
+
+        // rejected commands are returned by apply_commands instead
+        GameState {
+            current_tick: Tick(NonZeroU32::new(self.current_tick).unwrap()),
+            rejected_commands: vec![],
+            players,
+            entities: state_entities,
+        }
+    }
+
+    pub fn apply_commands(
+        &mut self,
+        player_id: EntityId,
+        commands: Vec<Command>,
+    ) -> Vec<(Command, String)> {
+        let mut rejected_commands = vec![];
+        for command in commands {
+            if let Err(reason) = self.apply_command(player_id, &command) {
+                debug!("Simulator rejected {command:?} of player {player_id:?}: {reason}");
+                rejected_commands.push((command, reason));
+            }
+        }
+        rejected_commands
+    }
+
+    fn apply_command(&mut self, player_id: EntityId, command: &Command) -> Result<(), String> {
+        if !self.players.contains_key(&player_id) {
+            return Err(format!("unknown player {player_id:?}"));
+        }
+
+        match command {
+            Command::ProduceSquad { card_position, xy } => {
+                self.produce_squad(player_id, *card_position as usize, xy)
+            }
+            Command::GroupGoto {
+                squads,
+                positions,
+                walk_mode,
+                orientation: _,
+            } => {
+                if positions.len() == 0 {
+                    return Err("no position given".to_string());
+                }
+                for (i, squad_id) in squads.iter().enumerate() {
+                    let squad = self.get_own_squad(player_id, squad_id)?;
+                    // use the last position if there are less positions than squads
+                    squad.destination = Some(positions[i.min(positions.len() - 1)]);
+                    squad.force_move = matches!(walk_mode, WalkMode::Force);
+                    squad.target = None;
+                }
+                Ok(())
+            }
+            Command::GroupAttack {
+                squads,
+                target_entity_id,
+                force_attack: _,
+            } => {
+                if self
+                    .get_target_position(player_id, target_entity_id)
+                    .is_none()
+                {
+                    return Err(format!("{target_entity_id:?} is not a valid target"));
+                }
+                for squad_id in squads.iter() {
+                    let squad = self.get_own_squad(player_id, squad_id)?;
+                    squad.target = Some(*target_entity_id);
+                    squad.destination = None;
+                }
+                Ok(())
+            }
+            Command::PowerSlotBuild { slot_id } => self.build_slot(player_id, slot_id, None),
+            Command::TokenSlotBuild { slot_id, color } => {
+                let orb_color = match color {
+                    CreateOrbColor::Fire => OrbColor::Fire,
+                    CreateOrbColor::Shadow => OrbColor::Shadow,
+                    CreateOrbColor::Nature => OrbColor::Nature,
+                    CreateOrbColor::Frost => OrbColor::Frost,
+                };
+                self.build_slot(player_id, slot_id, Some(orb_color))
+            }
+            Command::RepairBuilding { building_id } => {
+                if self.get_structure_owner(building_id) != Some(player_id) {
+                    return Err(format!("{building_id:?} is not owned by the player"));
+                }
+                let (hp, max_hp) = self.structure_health.get_mut(building_id).unwrap();
+                *hp = (*hp + REPAIR_HP_PER_COMMAND).min(*max_hp);
+                Ok(())
+            }
+            _ => {
+                debug!("Simulator ignores command {command:?}");
+                Ok(())
+            }
+        }
+    }
+
+    fn produce_squad(
+        &mut self,
+        player_id: EntityId,
+        card_position: usize,
+        position: &Position2D,
+    ) -> Result<(), String> {
+        let card_id = match self.players[&player_id].deck.get(card_position) {
+            Some(card_id) if card_id.0 != 0 => *card_id,
+            _ => return Err(format!("no card at deck position {card_position}")),
+        };
+
+        let card_info = self.card_data.get_card_info_from_id(card_id.0)?;
+        if card_info.squad_size == 0 {
+            return Err(format!("card {card_id:?} is not a unit"));
+        }
+
+        let orb_colors = self.get_orb_colors(player_id);
+        if !self
+            .card_data
+            .orb_colors_fullfill_requirements(card_id.0, &orb_colors)
+        {
+            return Err(format!("orb requirements for {card_id:?} are not met"));
+        }
+
+        if self.players[&player_id].power < card_info.power_cost {
+            return Err(format!("not enough power to play {card_id:?}"));
+        }
+
+        if !self.has_presence(player_id, position, BUILD_RADIUS) {
+            return Err(format!("{position:?} is too far away from own entities"));
+        }
+
+        // the starting orb takes the color of the first played card
+        if orb_colors.contains(&OrbColor::Starting) {
+            if let Some(color) = Simulator::get_card_color(&card_info) {
+                for token_slot in self.token_slots.iter_mut() {
+                    if token_slot.entity.player_entity_id == Some(player_id)
+                        && token_slot.color == OrbColor::Starting
+                    {
+                        token_slot.color = color;
+                    }
+                }
+            }
+        }
+
+        let player = self.players.get_mut(&player_id).unwrap();
+        player.power -= card_info.power_cost;
+
+        let squad_id = self.new_entity_id();
+        let figure_ids = (0..card_info.squad_size)
+            .map(|_| self.new_entity_id())
+            .collect();
+        self.squads.insert(
+            squad_id,
+            SimSquad {
+                id: squad_id,
+                owner: player_id,
+                card_id,
+                position: *position,
+                hp: card_info.health,
+                bound_power: card_info.power_cost,
+                card_info,
+                figure_ids,
+                destination: None,
+                force_move: false,
+                target: None,
+            },
+        );
+        debug!("Simulator spawned squad {squad_id:?} ({card_id:?}) for {player_id:?}");
+        Ok(())
+    }
+
+    fn build_slot(
+        &mut self,
+        player_id: EntityId,
+        slot_id: &EntityId,
+        orb_color: Option<OrbColor>,
+    ) -> Result<(), String> {
+        let slot_position = match orb_color {
+            None => self
+                .power_slots
+                .iter()
+                .find(|s| s.entity.id == *slot_id)
+                .map(|s| s.entity.position.to_2d()),
+            Some(_) => self
+                .token_slots
+                .iter()
+                .find(|s| s.entity.id == *slot_id)
+                .map(|s| s.entity.position.to_2d()),
+        };
+        let Some(slot_position) = slot_position else {
+            return Err(format!("{slot_id:?} is not a slot of this kind"));
+        };
+
+        if self.get_structure_owner(slot_id).is_some()
+            || self.constructions.iter().any(|c| c.slot_id == *slot_id)
+        {
+            return Err(format!("{slot_id:?} is already taken"));
+        }
+
+        if !self.has_presence(player_id, &slot_position, BUILD_RADIUS) {
+            return Err(format!("no ground presence at {slot_id:?}"));
+        }
+
+        let (cost, build_ticks, kind) = match orb_color {
+            None => (POWER_SLOT_COST, POWER_SLOT_BUILD_TICKS, SlotKind::Power),
+            Some(color) => {
+                let num_token_slots = self.get_orb_colors(player_id).len()
+                    + self
+                        .constructions
+                        .iter()
+                        .filter(|c| c.owner == player_id && matches!(c.kind, SlotKind::Token(_)))
+                        .count();
+                let cost = match num_token_slots {
+                    1 => T2_TOKEN_SLOT_COST,
+                    2 => T3_TOKEN_SLOT_COST,
+                    _ => return Err("no more token slots can be built".to_string()),
+                };
+                (cost, TOKEN_SLOT_BUILD_TICKS, SlotKind::Token(color))
+            }
+        };
+
+        let player = self.players.get_mut(&player_id).unwrap();
+        if player.power < cost {
+            return Err(format!("not enough power to build {slot_id:?}"));
+        }
+        // power spent on slots does not go into the void
+        player.power -= cost;
+
+        self.constructions.push(Construction {
+            slot_id: *slot_id,
+            owner: player_id,
+            kind,
+            finished_tick: self.current_tick + build_ticks,
+        });
+        Ok(())
+    }
+
+    pub fn step(&mut self) {
+        self.finish_constructions();
+        self.update_power();
+        self.update_squads();
+        self.current_tick += 1;
+    }
+
+    fn finish_constructions(&mut self) {
+        let current_tick = self.current_tick;
+        let (finished, in_progress): (Vec<Construction>, Vec<Construction>) = self
+            .constructions
+            .drain(..)
+            .partition(|c| c.finished_tick <= current_tick);
+        self.constructions = in_progress;
+
+        for construction in finished {
+            match construction.kind {
+                SlotKind::Power => {
+                    if let Some(power_slot) = self
+                        .power_slots
+                        .iter_mut()
+                        .find(|s| s.entity.id == construction.slot_id)
+                    {
+                        power_slot.entity.player_entity_id = Some(construction.owner);
+                        power_slot.state = BuildState::Build;
+                    }
+                }
+                SlotKind::Token(color) => {
+                    if let Some(token_slot) = self
+                        .token_slots
+                        .iter_mut()
+                        .find(|s| s.entity.id == construction.slot_id)
+                    {
+                        token_slot.entity.player_entity_id = Some(construction.owner);
+                        token_slot.color = color;
+                        token_slot.state = BuildState::Build;
+                    }
+                }
+            }
+            debug!(
+                "Simulator finished building {:?} for {:?}",
+                construction.slot_id, construction.owner
+            );
+        }
+    }
+
+    fn update_power(&mut self) {
+        for player in self.players.values_mut() {
+            let num_wells = self
+                .power_slots
+                .iter()
+                .filter(|s| s.entity.player_entity_id == Some(player.id))
+                .count() as f32;
+            player.power += num_wells * POWER_PER_WELL_PER_SECOND / TICKS_PER_SECOND;
+
+            let void_return = (player.void_power * VOID_RETURN_PER_SECOND)
+                .max(MIN_VOID_RETURN_PER_SECOND)
+                / TICKS_PER_SECOND;
+            let void_return = void_return.min(player.void_power);
+            player.void_power -= void_return;
+            player.power += void_return;
+        }
+    }
+
+    fn update_squads(&mut self) {
+        let mut damage: Vec<(EntityId, f32)> = vec![];
+        let squad_ids: Vec<EntityId> = self.squads.keys().cloned().collect();
+
+        for squad_id in squad_ids.iter() {
+            let squad = &self.squads[squad_id];
+            let owner = squad.owner;
+
+            // drop targets which do not exist anymore
+            let mut target = squad
+                .target
+                .filter(|t| self.get_target_position(owner, t).is_some());
+
+            if target.is_none() && (squad.destination.is_none() || !squad.force_move) {
+                target = self.get_closest_enemy(owner, &squad.position, AGGRO_RADIUS);
+            }
+
+            let range = if squad.card_info.melee {
+                MELEE_RANGE
+            } else {
+                RANGED_RANGE
+            };
+
+            let mut new_destination = squad.destination;
+            let mut move_to: Option<Position2D> = squad.destination;
+            if let Some(target_id) = target {
+                let target_pos = self.get_target_position(owner, &target_id).unwrap();
+                if utils::dist(&squad.position, &target_pos) > range {
+                    move_to = Some(target_pos);
+                } else {
+                    move_to = None;
+                    let card_info = squad.card_info.clone();
+                    let dmg = self.get_damage_per_tick(&card_info, &target_id);
+                    damage.push((target_id, dmg));
+                }
+            }
+
+            let squad = self.squads.get_mut(squad_id).unwrap();
+            if let Some(dest) = move_to {
+                squad.position = Simulator::move_towards(&squad.position, &dest);
+                if squad
+                    .destination
+                    .is_some_and(|d| utils::dist(&squad.position, &d) < 0.1)
+                {
+                    new_destination = None;
+                }
+            }
+            squad.destination = new_destination;
+            squad.target = target;
+        }
+
+        for (target_id, dmg) in damage {
+            if let Some(squad) = self.squads.get_mut(&target_id) {
+                squad.hp -= dmg;
+            } else if let Some((hp, _)) = self.structure_health.get_mut(&target_id) {
+                *hp -= dmg;
+            }
+        }
+
+        // remove dead squads
+        let dead_squad_ids: Vec<EntityId> = self
+            .squads
+            .values()
+            .filter(|s| s.hp <= 0.)
+            .map(|s| s.id)
+            .collect();
+        for squad_id in dead_squad_ids {
+            let squad = self.squads.remove(&squad_id).unwrap();
+            let player = self.players.get_mut(&squad.owner).unwrap();
+            player.void_power += squad.bound_power * VOID_SHARE_ON_DEATH;
+            debug!("Simulator: squad {squad_id:?} of {:?} died", squad.owner);
+        }
+
+        // destroyed structures lose their owner and have to be built again
+        let destroyed_ids: Vec<EntityId> = self
+            .structure_health
+            .iter()
+            .filter(|(_, (hp, _))| *hp <= 0.)
+            .map(|(id, _)| *id)
+            .collect();
+        for slot_id in destroyed_ids {
+            let (hp, max_hp) = self.structure_health.get_mut(&slot_id).unwrap();
+            *hp = *max_hp;
+            for power_slot in self.power_slots.iter_mut() {
+                if power_slot.entity.id == slot_id {
+                    power_slot.entity.player_entity_id = None;
+                    power_slot.state = BuildState::Destroyed;
+                }
+            }
+            for token_slot in self.token_slots.iter_mut() {
+                if token_slot.entity.id == slot_id {
+                    token_slot.entity.player_entity_id = None;
+                    token_slot.state = BuildState::Destroyed;
+                }
+            }
+            debug!("Simulator: structure {slot_id:?} was destroyed");
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    pub fn winner(&self) -> Option<EntityId> {
+        // a player is defeated when all of it's structures are destroyed
+        let remaining_players: Vec<&SimPlayer> = self
+            .players
+            .values()
+            .filter(|p| self.num_structures(p.id) > 0)
+            .collect();
+        let first_team = remaining_players.first()?.team;
+        if remaining_players.iter().all(|p| p.team == first_team)
+            && remaining_players.len() < self.players.len()
+        {
+            Some(remaining_players[0].id)
+        } else {
+            None
+        }
+    }
+
+    pub fn num_structures(&self, player_id: EntityId) -> usize {
+        let num_power_slots = self
+            .power_slots
+            .iter()
+            .filter(|s| s.entity.player_entity_id == Some(player_id))
+            .count();
+        let num_token_slots = self
+            .token_slots
+            .iter()
+            .filter(|s| s.entity.player_entity_id == Some(player_id))
+            .count();
+        num_power_slots + num_token_slots
+    }
+
+    pub fn get_tempo(&self, player_id: EntityId) -> f32 {
+        // same definition as PlayerInfo::get_tempo
+        let Some(player) = self.players.get(&player_id) else {
+            return 0.;
+        };
+        let bound_power: f32 = self
+            .squads
+            .values()
+            .filter(|s| s.owner == player_id)
+            .map(|s| s.bound_power)
+            .sum();
+        player.power + bound_power - player.void_power
+    }
+
+    fn get_own_squad(
+        &mut self,
+        player_id: EntityId,
+        squad_id: &EntityId,
+    ) -> Result<&mut SimSquad, String> {
+        match self.squads.get_mut(squad_id) {
+            Some(squad) if squad.owner == player_id => Ok(squad),
+            _ => Err(format!("{squad_id:?} is not a squad of the player")),
+        }
+    }
+
+    fn get_orb_colors(&self, player_id: EntityId) -> Vec<OrbColor> {
+        self.token_slots
+            .iter()
+            .filter(|s| s.entity.player_entity_id == Some(player_id))
+            .map(|s| s.color)
+            .collect()
+    }
+
+    fn get_card_color(card_info: &CardInfo) -> Option<OrbColor> {
+        let requirements = card_info.orb_requirements;
+        if requirements.fire > 0 {
+            Some(OrbColor::Fire)
+        } else if requirements.shadow > 0 {
+            Some(OrbColor::Shadow)
+        } else if requirements.nature > 0 {
+            Some(OrbColor::Nature)
+        } else if requirements.frost > 0 {
+            Some(OrbColor::Frost)
+        } else {
+            None
+        }
+    }
+
+    fn get_structure_owner(&self, entity_id: &EntityId) -> Option<EntityId> {
+        if let Some(power_slot) = self.power_slots.iter().find(|s| s.entity.id == *entity_id) {
+            return power_slot.entity.player_entity_id;
+        }
+        if let Some(token_slot) = self.token_slots.iter().find(|s| s.entity.id == *entity_id) {
+            return token_slot.entity.player_entity_id;
+        }
+        None
+    }
+
+    fn is_enemy(&self, player_id: EntityId, other_id: EntityId) -> bool {
+        match (self.players.get(&player_id), self.players.get(&other_id)) {
+            (Some(player), Some(other)) => player.team != other.team,
+            _ => false,
+        }
+    }
+
+    fn get_target_position(&self, player_id: EntityId, target: &EntityId) -> Option<Position2D> {
+        // only enemy squads and enemy structures can be attacked
+        if let Some(squad) = self.squads.get(target) {
+            if self.is_enemy(player_id, squad.owner) {
+                return Some(squad.position);
+            }
+            return None;
+        }
+
+        let owner = self.get_structure_owner(target)?;
+        if !self.is_enemy(player_id, owner) {
+            return None;
+        }
+        self.power_slots
+            .iter()
+            .map(|s| &s.entity)
+            .chain(self.token_slots.iter().map(|s| &s.entity))
+            .find(|e| e.id == *target)
+            .map(|e| e.position.to_2d())
+    }
+
+    fn get_closest_enemy(
+        &self,
+        player_id: EntityId,
+        pos: &Position2D,
+        radius: f32,
+    ) -> Option<EntityId> {
+        // squads are always preferred over structures
+        let mut closest: Option<EntityId> = None;
+        let mut closest_dist = radius;
+        for squad in self.squads.values() {
+            let dist = utils::dist(pos, &squad.position);
+            if self.is_enemy(player_id, squad.owner) && dist < closest_dist {
+                closest = Some(squad.id);
+                closest_dist = dist;
+            }
+        }
+        if closest.is_some() {
+            return closest;
+        }
+
+        let structures = self
+            .power_slots
+            .iter()
+            .map(|s| &s.entity)
+            .chain(self.token_slots.iter().map(|s| &s.entity));
+        for entity in structures {
+            let dist = utils::dist(pos, &entity.position.to_2d());
+            if entity
+                .player_entity_id
+                .is_some_and(|owner| self.is_enemy(player_id, owner))
+                && dist < closest_dist
+            {
+                closest = Some(entity.id);
+                closest_dist = dist;
+            }
+        }
+        closest
+    }
+
+    fn has_presence(&self, player_id: EntityId, pos: &Position2D, radius: f32) -> bool {
+        let own_squad_nearby = self
+            .squads
+            .values()
+            .any(|s| s.owner == player_id && utils::dist(pos, &s.position) < radius);
+        let own_structure_nearby = self
+            .power_slots
+            .iter()
+            .map(|s| &s.entity)
+            .chain(self.token_slots.iter().map(|s| &s.entity))
+            .any(|e| {
+                e.player_entity_id == Some(player_id)
+                    && utils::dist(pos, &e.position.to_2d()) < radius
+            });
+        own_squad_nearby || own_structure_nearby
+    }
+
+    fn get_damage_per_tick(&mut self, attacker: &CardInfo, target: &EntityId) -> f32 {
+        let mut dmg = attacker.damage / CARD_DAMAGE_SECONDS / TICKS_PER_SECOND;
+
+        if let Some(target_squad) = self.squads.get(target) {
+            dmg *= matchup::get_damage_multiplier(attacker, &target_squad.card_info);
+        } else {
+            dmg *= matchup::get_structure_damage_multiplier(attacker);
+        }
+
+        dmg * self
+            .rng
+            .gen_range((1. - DAMAGE_VARIANCE)..(1. + DAMAGE_VARIANCE))
+    }
+
+    fn move_towards(pos: &Position2D, dest: &Position2D) -> Position2D {
+        let step = SQUAD_SPEED / TICKS_PER_SECOND;
+        let dist = utils::dist(pos, dest);
+        if dist <= step {
+            return *dest;
+        }
+        Position2D {
+            x: pos.x + (dest.x - pos.x) / dist * step,
+            y: pos.y + (dest.y - pos.y) / dist * step,
+        }
+    }
+
+    fn new_entity_id(&mut self) -> EntityId {
+        let entity_id = EntityId(NonZeroU32::new(self.next_entity_id).unwrap());
+        self.next_entity_id += 1;
+        entity_id
+    }
+}
+
+pub fn load_start_state(path: &str) -> GameStartState {
+    let content = fs::read_to_string(path).expect("Unable to read start state file");
+    serde_json::from_str(&content).expect("Unable to parse start state")
+}
+
+pub fn load_map_info(path: &str) -> MapInfo {
+    // same format as the prepare request sent by the game client
+    let content = fs::read_to_string(path).expect("Unable to read prepare file");
+    let prepare: serde_json::Value =
+        serde_json::from_str(&content).expect("Unable to parse prepare file");
+    serde_json::from_value(prepare["map_info"].clone()).expect("Unable to parse map info")
+}
+
+pub fn run_against_idle_opponent(
+    start_state_path: &str,
+    prepare_path: &str,
+    seed: u64,
+    max_ticks: u32,
+) {
+    // let the bot play against an opponent which never sends any commands
+    let start_state = load_start_state(start_state_path);
+    let map_info = load_map_info(prepare_path);
+    let bot_id = start_state.your_player_id;
+
+    let mut simulator = Simulator::new(start_state.clone(), seed);
+    simulator.set_deck(bot_id, &deck::bot_deck().deck);
+
+    let mut bot = SkylordsRebot::prepare_for_battle(&map_info, &deck::bot_deck().deck);
+    bot.match_start(start_state);
+
+    while simulator.current_tick() <= max_ticks && !simulator.is_finished() {
+        let commands = bot.tick(simulator.game_state());
+        simulator.apply_commands(bot_id, commands);
+        simulator.step();
+    }
+
+    info!(
+        "Simulation finished after {:?} ticks, winner: {:?}",
+        simulator.current_tick(),
+        simulator.winner()
+    );
+    println!(
+        "Simulation finished after {} ticks, winner: {:?}, bot structures: {}, bot tempo: {:.1}",
+        simulator.current_tick(),
+        simulator.winner(),
+        simulator.num_structures(bot_id),
+        simulator.get_tempo(bot_id)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::{self, *};
+
+    fn simulator() -> (Simulator, EntityId, EntityId) {
+        let builder = ScenarioBuilder::new();
+        let simulator = Simulator::new(builder.start_state(), 0);
+        (simulator, builder.bot_id(), builder.opponent_id())
+    }
+
+    fn add_squad(
+        simulator: &mut Simulator,
+        owner: EntityId,
+        card: CardTemplate,
+        position: Position2D,
+    ) -> EntityId {
+        let card_id = CardId::new(card, Upgrade::U3);
+        let card_info = simulator
+            .card_data
+            .get_card_info_from_id(card_id.0)
+            .unwrap();
+        let squad_id = simulator.new_entity_id();
+        simulator.squads.insert(
+            squad_id,
+            SimSquad {
+                id: squad_id,
+                owner,
+                card_id,
+                position,
+                hp: card_info.health,
+                bound_power: card_info.power_cost,
+                card_info,
+                figure_ids: vec![],
+                destination: None,
+                force_move: false,
+                target: None,
+            },
+        );
+        squad_id
+    }
+
+    fn owned_power_slot(simulator: &Simulator, owner: EntityId) -> PowerSlot {
+        simulator
+            .power_slots
+            .iter()
+            .find(|s| s.entity.player_entity_id == Some(owner))
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn power_flows_from_wells_and_void() {
+        let (mut simulator, bot_id, _) = simulator();
+        let num_wells = simulator
+            .power_slots
+            .iter()
+            .filter(|s| s.entity.player_entity_id == Some(bot_id))
+            .count() as f32;
+        let player = simulator.players.get_mut(&bot_id).unwrap();
+        player.power = 0.;
+        player.void_power = 100.;
+
+        simulator.step();
+        let player = &simulator.players[&bot_id];
+        let void_return = 100. * VOID_RETURN_PER_SECOND / TICKS_PER_SECOND;
+        let income = num_wells * POWER_PER_WELL_PER_SECOND / TICKS_PER_SECOND;
+        assert!((player.power - income - void_return).abs() < 1e-4);
+        assert!((player.void_power - (100. - void_return)).abs() < 1e-4);
+
+        // a nearly empty void returns at least the minimum and never more than it holds
+        simulator.players.get_mut(&bot_id).unwrap().void_power = 0.01;
+        simulator.step();
+        assert_eq!(simulator.players[&bot_id].void_power, 0.);
+    }
+
+    #[test]
+    fn damage_includes_counters() {
+        let (mut simulator, bot_id, opponent_id) = simulator();
+        let pos = owned_power_slot(&simulator, bot_id).entity.position.to_2d();
+        let attacker = add_squad(&mut simulator, bot_id, Dreadcharger, pos);
+        let target = add_squad(&mut simulator, opponent_id, Forsaken, pos);
+        let attacker_info = simulator.squads[&attacker].card_info.clone();
+        let target_info = simulator.squads[&target].card_info.clone();
+
+        let base = attacker_info.damage / CARD_DAMAGE_SECONDS / TICKS_PER_SECOND;
+        let expected = base * matchup::get_damage_multiplier(&attacker_info, &target_info);
+        for _ in 0..20 {
+            let dmg = simulator.get_damage_per_tick(&attacker_info, &target);
+            assert!(dmg >= expected * (1. - DAMAGE_VARIANCE));
+            assert!(dmg <= expected * (1. + DAMAGE_VARIANCE));
+        }
+
+        let structure = owned_power_slot(&simulator, opponent_id).entity.id;
+        let expected = base * matchup::get_structure_damage_multiplier(&attacker_info);
+        let dmg = simulator.get_damage_per_tick(&attacker_info, &structure);
+        assert!(dmg >= expected * (1. - DAMAGE_VARIANCE));
+        assert!(dmg <= expected * (1. + DAMAGE_VARIANCE));
+    }
+
+    #[test]
+    fn built_slot_is_lost_when_destroyed() {
+        let (mut simulator, bot_id, _) = simulator();
+        let own_pos = owned_power_slot(&simulator, bot_id).entity.position.to_2d();
+        let free_slot = simulator
+            .power_slots
+            .iter()
+            .filter(|s| s.entity.player_entity_id.is_none())
+            .min_by(|a, b| {
+                let dist_a = utils::dist(&own_pos, &a.entity.position.to_2d());
+                let dist_b = utils::dist(&own_pos, &b.entity.position.to_2d());
+                dist_a.total_cmp(&dist_b)
+            })
+            .unwrap()
+            .clone();
+        // a squad next to the slot gives ground presence
+        add_squad(
+            &mut simulator,
+            bot_id,
+            Dreadcharger,
+            free_slot.entity.position.to_2d(),
+        );
+        let free_slot = free_slot.entity.id;
+        let player = simulator.players.get_mut(&bot_id).unwrap();
+        player.power = POWER_SLOT_COST;
+        player.void_power = 0.;
+        let num_structures = simulator.num_structures(bot_id);
+
+        simulator.build_slot(bot_id, &free_slot, None).unwrap();
+        // power spent on slots does not go into the void
+        assert_eq!(simulator.players[&bot_id].power, 0.);
+        assert_eq!(simulator.players[&bot_id].void_power, 0.);
+        assert!(simulator.build_slot(bot_id, &free_slot, None).is_err());
+
+        for _ in 0..=POWER_SLOT_BUILD_TICKS {
+            simulator.step();
+        }
+        assert_eq!(simulator.get_structure_owner(&free_slot), Some(bot_id));
+        assert_eq!(simulator.num_structures(bot_id), num_structures + 1);
+
+        simulator.structure_health.get_mut(&free_slot).unwrap().0 = 0.;
+        simulator.step();
+        let slot = simulator
+            .power_slots
+            .iter()
+            .find(|s| s.entity.id == free_slot)
+            .unwrap();
+        assert_eq!(slot.entity.player_entity_id, None);
+        assert_ne!(slot.state, BuildState::Build);
+        assert_eq!(simulator.num_structures(bot_id), num_structures);
+
+        // the slot can be built again
+        simulator.players.get_mut(&bot_id).unwrap().power = POWER_SLOT_COST;
+        assert!(simulator.build_slot(bot_id, &free_slot, None).is_ok());
+    }
+
+    #[test]
+    fn winner_is_the_last_player_with_structures() {
+        let (mut simulator, bot_id, opponent_id) = simulator();
+        assert_eq!(simulator.winner(), None);
+
+        for id in simulator
+            .structure_health
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            if simulator.get_structure_owner(&id) == Some(opponent_id) {
+                simulator.structure_health.get_mut(&id).unwrap().0 = 0.;
+            }
+        }
+        simulator.step();
+        assert_eq!(simulator.num_structures(opponent_id), 0);
+        assert_eq!(simulator.winner(), Some(bot_id));
+        assert!(simulator.is_finished());
+    }
+}
@@ -0,0 +1,110 @@
+use api::*;
+use serde_json::json;
+
+// height used for all simulated entities, the bot only works with 2D positions
+const DEFAULT_HEIGHT: f32 = 55.;
+
+// Entities are created from the same json layout the game client sends (see scripts/requests),
+// which keeps the simulator independent of fields of the api types it does not care about.
+
+pub fn entity_json(
+    entity_id: EntityId,
+    owner: Option<EntityId>,
+    position: &Position2D,
+    current_hp: f32,
+    max_hp: f32,
+) -> serde_json::Value {
+    json!({
+        "id": entity_id.0.get(),
+        "effects": [],
+        "aspects": [
+            {
+                "Health": {
+                    "current_hp": current_hp,
+                    "cap_current_max": max_hp,
+                }
+            },
+            { "Attackable": {} },
+        ],
+        "job": { "Idle": {} },
+        "position": {
+            "x": position.x,
+            "y": DEFAULT_HEIGHT,
+            "z": position.y,
+        },
+        "player_entity_id": owner.map(|id| id.0.get()),
+    })
+}
+
+pub fn new_squad(
+    entity_id: EntityId,
+    owner: EntityId,
+    card_id: CardId,
+    position: &Position2D,
+    bound_power: f32,
+    figure_ids: &Vec<EntityId>,
+    current_hp: f32,
+    max_hp: f32,
+) -> Squad {
+    let squad_json = json!({
+        "entity": entity_json(entity_id, Some(owner), position, current_hp, max_hp),
+        "card_id": card_id.0,
+        "res_id": 0,
+        "bound_power": bound_power,
+        "squad_size": figure_ids.len(),
+        "figures": figure_ids.iter().map(|id| id.0.get()).collect::<Vec<u32>>(),
+    });
+    serde_json::from_value(squad_json).expect("Unable to create squad from json")
+}
+
+pub fn new_figure(
+    entity_id: EntityId,
+    owner: EntityId,
+    squad_id: EntityId,
+    position: &Position2D,
+    current_hp: f32,
+    max_hp: f32,
+) -> Figure {
+    let figure_json = json!({
+        "entity": entity_json(entity_id, Some(owner), position, current_hp, max_hp),
+        "squad_id": squad_id.0.get(),
+        "current_speed": 0.,
+        "rotation": 0.,
+    });
+    serde_json::from_value(figure_json).expect("Unable to create figure from json")
+}
+
+pub fn set_entity_health(entity: &mut Entity, hp: f32, max_hp: f32) {
+    for aspect in entity.aspects.iter_mut() {
+        match aspect {
+            Aspect::Health {
+                current_hp,
+                cap_current_max,
+            } => {
+                *current_hp = hp;
+                *cap_current_max = max_hp;
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    // free slots do not have a health aspect
+    entity.aspects.push(Aspect::Health {
+        current_hp: hp,
+        cap_current_max: max_hp,
+    });
+}
+
+pub fn get_entity_max_health(entity: &Entity) -> Option<f32> {
+    for aspect in entity.aspects.iter() {
+        match aspect {
+            Aspect::Health {
+                current_hp: _,
+                cap_current_max,
+            } => return Some(*cap_current_max),
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// Strategic parameters of the bot. Every value can be overwritten through a json file passed
+// with --strategy, missing values fall back to the defaults below.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrategyConfig {
+    // deck file to play, data/decks/shadow_frost.json is played when none is given
+    pub deck: Option<String>,
+    pub macro_controller: MacroConfig,
+    pub location_priorities: LocationPriorityConfig,
+    pub spawn_controller: SpawnConfig,
+    pub command_scheduler: CommandSchedulerConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacroConfig {
+    // distance to a location from which on the squads fight for it instead of moving there
+    pub control_area_aggro_radius: f32,
+    // required power before a well is built
+    pub min_power_build_well: f32,
+    // minimum difference in tempo to consider it an advantage
+    pub min_tempo_diff_advantage: f32,
+    // difference in number of squads to focus a well or orb instead of enemy squads
+    pub num_squads_critical_mass: i32,
+    // seconds after which the bot goes to T2 and T3 on its own
+    pub advance_t2_seconds: u32,
+    pub advance_t3_seconds: u32,
+    // power required to advance a tier while defending
+    pub defend_min_power_advance_t2: f32,
+    pub defend_min_power_advance_t3: f32,
+    // unspent power while defending to build a well or attack instead
+    pub defend_max_unspent_power: f32,
+    // seconds after which the enemy start location can be attacked
+    pub attack_start_location_seconds: u32,
+    // ratio of own to enemy influence below which a fight is lost and the squads retreat
+    pub min_strength_ratio_retreat: f32,
+}
+
+impl Default for MacroConfig {
+    fn default() -> Self {
+        MacroConfig {
+            control_area_aggro_radius: 60.,
+            min_power_build_well: 200.,
+            min_tempo_diff_advantage: 0.,
+            num_squads_critical_mass: 6,
+            advance_t2_seconds: 180,
+            advance_t3_seconds: 420,
+            defend_min_power_advance_t2: 200.,
+            defend_min_power_advance_t3: 300.,
+            defend_max_unspent_power: 300.,
+            attack_start_location_seconds: 300,
+            min_strength_ratio_retreat: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocationPriorityConfig {
+    // weight of being closer to the own than to the opponent's start location
+    pub start_proximity_weight_ahead: f32,
+    pub start_proximity_weight_behind: f32,
+    // weight of each power slot and of a token slot at a location
+    pub power_slot_weight: f32,
+    pub token_slot_weight: f32,
+    // weight of locations owned by the bot or the opponent
+    pub own_location_weight: f32,
+    pub opponent_location_weight_ahead: f32,
+    pub opponent_location_weight_behind: f32,
+    // weight of 100 bound power of enemy squads around a location
+    pub enemy_strength_weight: f32,
+    // radius around a location in which enemy squads are counted
+    pub enemy_strength_radius: f32,
+}
+
+impl Default for LocationPriorityConfig {
+    fn default() -> Self {
+        LocationPriorityConfig {
+            start_proximity_weight_ahead: 1.,
+            start_proximity_weight_behind: 4.,
+            power_slot_weight: 0.5,
+            token_slot_weight: 1.,
+            own_location_weight: 0.5,
+            opponent_location_weight_ahead: 0.5,
+            opponent_location_weight_behind: -1.,
+            enemy_strength_weight: -0.5,
+            enemy_strength_radius: 60.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawnConfig {
+    // minimum difference in bound power to spawn a new squad when matching opponent's army size
+    pub min_power_diff_spawn: f32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        SpawnConfig {
+            min_power_diff_spawn: 30.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandSchedulerConfig {
+    // ticks to wait between playing two cards
+    pub card_play_tick_timeout: u32,
+}
+
+impl Default for CommandSchedulerConfig {
+    fn default() -> Self {
+        CommandSchedulerConfig {
+            card_play_tick_timeout: 10,
+        }
+    }
+}
+
+impl StrategyConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("Unable to read {path}: {err}"))?;
+        serde_json::from_str(&content).map_err(|err| format!("Unable to parse {path}: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_values_use_defaults() {
+        let config: StrategyConfig = serde_json::from_str(
+            r#"{"macro_controller": {"min_power_build_well": 150.0}, "spawn_controller": {}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.macro_controller.min_power_build_well, 150.);
+        assert_eq!(config.macro_controller.advance_t2_seconds, 180);
+        assert_eq!(config.spawn_controller, SpawnConfig::default());
+    }
+
+    #[test]
+    fn unknown_values_are_rejected() {
+        let result = serde_json::from_str::<StrategyConfig>(
+            r#"{"macro_controller": {"min_power_build_wel": 150.0}}"#,
+        );
+        assert!(result.is_err());
+    }
+}
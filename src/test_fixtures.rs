@@ -0,0 +1,563 @@
+use api::sr_libs::utils::card_templates::CardTemplate;
+use api::Upgrade::U3;
+use api::*;
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+use crate::card_data::CardData;
+use crate::game_info::GameInfo;
+use crate::location::{self, Location, LocationPosition};
+use crate::simulator::entities;
+
+// offset between squads which are placed at the same location
+const SQUAD_SPACING: f32 = 2.;
+const POWER_SLOT_HEALTH: f32 = 2000.;
+const TOKEN_SLOT_HEALTH: f32 = 3000.;
+
+// Builds consistent GameStartState, GameState and GameInfo values for tests.
+// The map is Elyon and the start state is the recorded Elyon start (bot starts South, opponent
+// North), all modifications only change the GameState, e.g.
+//
+// let game_info = ScenarioBuilder::new()
+//     .bot_wells("South", 2)
+//     .opponent_squads(Dreadcharger, 3, "Center", 0.5)
+//     .game_info();
+pub struct ScenarioBuilder {
+    map_info: MapInfo,
+    start_state: GameStartState,
+    locations: BTreeMap<Location, LocationPosition>,
+    card_data: CardData,
+    current_tick: u32,
+    next_entity_id: u32,
+    power: BTreeMap<EntityId, f32>,
+    power_slots: Vec<PowerSlot>,
+    token_slots: Vec<TokenSlot>,
+    squads: Vec<Squad>,
+    figures: Vec<Figure>,
+}
+
+impl ScenarioBuilder {
+    pub fn new() -> Self {
+        let start_state: GameStartState =
+            serde_json::from_str(include_str!("../scripts/requests/start.json"))
+                .expect("Unable to parse start state");
+
+        let map_info = MapInfo {
+            map: Maps::ElyonSpectator,
+            community_map_details: None,
+        };
+
+        // use the location assignment of the bot itself
+        let mut game_info = GameInfo::new();
+        game_info.set_map(&map_info);
+        game_info.init(start_state.clone());
+
+        let mut card_data = CardData::new();
+        card_data.load();
+
+        let max_entity_id = start_state
+            .entities
+            .power_slots
+            .iter()
+            .map(|s| s.entity.id.0.get())
+            .chain(
+                start_state
+                    .entities
+                    .token_slots
+                    .iter()
+                    .map(|s| s.entity.id.0.get()),
+            )
+            .chain(start_state.players.iter().map(|p| p.entity.id.0.get()))
+            .max()
+            .unwrap();
+
+        let power = start_state
+            .players
+            .iter()
+            .map(|p| (p.entity.id, p.entity.power))
+            .collect();
+
+        ScenarioBuilder {
+            map_info,
+            power_slots: start_state.entities.power_slots.clone(),
+            token_slots: start_state.entities.token_slots.clone(),
+            start_state,
+            locations: game_info.locations,
+            card_data,
+            current_tick: 1,
+            next_entity_id: max_entity_id + 1,
+            power,
+            squads: vec![],
+            figures: vec![],
+        }
+    }
+
+    pub fn bot_id(&self) -> EntityId {
+        self.start_state.your_player_id
+    }
+
+    pub fn opponent_id(&self) -> EntityId {
+        self.start_state
+            .players
+            .iter()
+            .map(|p| p.entity.id)
+            .find(|&id| id != self.bot_id())
+            .unwrap()
+    }
+
+    pub fn add_player(&mut self, ally: bool) -> EntityId {
+        // add a player without structures to the bot's or the opponent's team, e.g. for 2v2
+        let team = if ally {
+            self.get_team(self.bot_id())
+        } else {
+            self.get_team(self.opponent_id())
+        };
+        let player_id = self.new_entity_id();
+        let mut player = self.start_state.players[0].clone();
+        player.name = format!("Player {}", player_id.0);
+        player.entity.id = player_id;
+        player.entity.team = team;
+        self.power.insert(player_id, player.entity.power);
+        self.start_state.players.push(player);
+        player_id
+    }
+
+    pub fn tick(&mut self, tick: u32) -> &mut Self {
+        self.current_tick = tick;
+        self
+    }
+
+    pub fn seconds(&mut self, seconds: u32) -> &mut Self {
+        // 1 Tick = 100 ms
+        self.tick(seconds * 10)
+    }
+
+    pub fn bot_power(&mut self, power: f32) -> &mut Self {
+        self.power.insert(self.bot_id(), power);
+        self
+    }
+
+    pub fn opponent_power(&mut self, power: f32) -> &mut Self {
+        self.power.insert(self.opponent_id(), power);
+        self
+    }
+
+    pub fn spend_bot_power(&mut self, amount: f32) -> &mut Self {
+        let power = self.power[&self.bot_id()];
+        self.bot_power(power - amount)
+    }
+
+    pub fn bot_wells(&mut self, location: &str, num_wells: usize) -> &mut Self {
+        self.set_wells(self.bot_id(), location, num_wells)
+    }
+
+    pub fn opponent_wells(&mut self, location: &str, num_wells: usize) -> &mut Self {
+        self.set_wells(self.opponent_id(), location, num_wells)
+    }
+
+    pub fn player_wells(
+        &mut self,
+        player_id: EntityId,
+        location: &str,
+        num_wells: usize,
+    ) -> &mut Self {
+        self.set_wells(player_id, location, num_wells)
+    }
+
+    pub fn bot_orb(&mut self, location: &str, color: OrbColor) -> &mut Self {
+        self.set_orb(self.bot_id(), location, color)
+    }
+
+    pub fn opponent_orb(&mut self, location: &str, color: OrbColor) -> &mut Self {
+        self.set_orb(self.opponent_id(), location, color)
+    }
+
+    pub fn free_location(&mut self, location: &str) -> &mut Self {
+        // remove all structures at a location, e.g. after they were destroyed
+        for slot_id in self.power_slot_ids(location) {
+            self.set_power_slot_owner(slot_id, None);
+        }
+        if let Some(slot_id) = self.token_slot_id(location) {
+            self.set_token_slot_owner(slot_id, None, OrbColor::White);
+        }
+        self
+    }
+
+    pub fn bot_squads(
+        &mut self,
+        card: CardTemplate,
+        num_squads: usize,
+        location: &str,
+        health_percent: f32,
+    ) -> &mut Self {
+        let pos = self.get_location(location).position();
+        self.add_squads(self.bot_id(), card, num_squads, pos, health_percent)
+    }
+
+    pub fn opponent_squads(
+        &mut self,
+        card: CardTemplate,
+        num_squads: usize,
+        location: &str,
+        health_percent: f32,
+    ) -> &mut Self {
+        let pos = self.get_location(location).position();
+        self.add_squads(self.opponent_id(), card, num_squads, pos, health_percent)
+    }
+
+    pub fn player_squads(
+        &mut self,
+        player_id: EntityId,
+        card: CardTemplate,
+        num_squads: usize,
+        location: &str,
+        health_percent: f32,
+    ) -> &mut Self {
+        let pos = self.get_location(location).position();
+        self.add_squads(player_id, card, num_squads, pos, health_percent)
+    }
+
+    pub fn bot_squads_at(
+        &mut self,
+        card: CardTemplate,
+        num_squads: usize,
+        pos: Position2D,
+        health_percent: f32,
+    ) -> &mut Self {
+        self.add_squads(self.bot_id(), card, num_squads, pos, health_percent)
+    }
+
+    pub fn move_squads(&mut self, squad_ids: &Vec<EntityId>, pos: Position2D) -> &mut Self {
+        // squads arrive instantly
+        for squad in self
+            .squads
+            .iter_mut()
+            .filter(|s| squad_ids.contains(&s.entity.id))
+        {
+            squad.entity.position.x = pos.x;
+            squad.entity.position.z = pos.y;
+        }
+        for figure in self
+            .figures
+            .iter_mut()
+            .filter(|f| squad_ids.contains(&f.squad_id))
+        {
+            figure.entity.position.x = pos.x;
+            figure.entity.position.z = pos.y;
+        }
+        self
+    }
+
+    pub fn kill_bot_squads(&mut self) -> &mut Self {
+        self.remove_squads(self.bot_id())
+    }
+
+    pub fn kill_opponent_squads(&mut self) -> &mut Self {
+        self.remove_squads(self.opponent_id())
+    }
+
+    pub fn map_info(&self) -> MapInfo {
+        self.map_info.clone()
+    }
+
+    pub fn start_state(&self) -> GameStartState {
+        self.start_state.clone()
+    }
+
+    pub fn game_state(&self) -> GameState {
+        let players = self
+            .start_state
+            .players
+            .iter()
+            .map(|p| {
+                let mut player_entity = p.entity.clone();
+                player_entity.power = self.power[&p.entity.id];
+                player_entity
+            })
+            .collect();
+
+        let mut state_entities = self.start_state.entities.clone();
+        state_entities.power_slots = self.power_slots.clone();
+        state_entities.token_slots = self.token_slots.clone();
+        state_entities.squads = self.squads.clone();
+        state_entities.figures = self.figures.clone();
+
+        GameState {
+            current_tick: Tick(NonZeroU32::new(self.current_tick).unwrap()),
+            rejected_commands: vec![],
+            players,
+            entities: state_entities,
+        }
+    }
+
+    pub fn game_info(&self) -> GameInfo {
+        let mut game_info = GameInfo::new();
+        game_info.set_map(&self.map_info);
+        game_info.init(self.start_state());
+        game_info.parse_state(self.game_state());
+        game_info
+    }
+
+    pub fn squad_ids(&self, player_id: EntityId) -> Vec<EntityId> {
+        self.squads
+            .iter()
+            .filter(|s| s.entity.player_entity_id == Some(player_id))
+            .map(|s| s.entity.id)
+            .collect()
+    }
+
+    pub fn power_slot_ids(&self, location: &str) -> Vec<EntityId> {
+        self.get_location(location)
+            .powers
+            .iter()
+            .map(|p| p.entity_id.unwrap())
+            .collect()
+    }
+
+    pub fn token_slot_id(&self, location: &str) -> Option<EntityId> {
+        self.get_location(location)
+            .token
+            .map(|t| t.entity_id.unwrap())
+    }
+
+    fn set_wells(&mut self, owner: EntityId, location: &str, num_wells: usize) -> &mut Self {
+        let slot_ids = self.power_slot_ids(location);
+        assert!(
+            num_wells <= slot_ids.len(),
+            "{location} only has {} power slots",
+            slot_ids.len()
+        );
+        for (i, slot_id) in slot_ids.into_iter().enumerate() {
+            self.set_power_slot_owner(slot_id, (i < num_wells).then_some(owner));
+        }
+        self
+    }
+
+    fn set_orb(&mut self, owner: EntityId, location: &str, color: OrbColor) -> &mut Self {
+        let slot_id = self
+            .token_slot_id(location)
+            .unwrap_or_else(|| panic!("{location} has no token slot"));
+        self.set_token_slot_owner(slot_id, Some(owner), color)
+    }
+
+    pub fn set_power_slot_owner(
+        &mut self,
+        slot_id: EntityId,
+        owner: Option<EntityId>,
+    ) -> &mut Self {
+        let team = owner.map(|id| self.get_team(id)).unwrap_or(0);
+        let power_slot = self
+            .power_slots
+            .iter_mut()
+            .find(|s| s.entity.id == slot_id)
+            .unwrap();
+        power_slot.entity.player_entity_id = owner;
+        power_slot.team = team;
+        if owner.is_some() {
+            power_slot.state = BuildState::Build;
+            entities::set_entity_health(
+                &mut power_slot.entity,
+                POWER_SLOT_HEALTH,
+                POWER_SLOT_HEALTH,
+            );
+        }
+        self
+    }
+
+    pub fn set_token_slot_owner(
+        &mut self,
+        slot_id: EntityId,
+        owner: Option<EntityId>,
+        color: OrbColor,
+    ) -> &mut Self {
+        let token_slot = self
+            .token_slots
+            .iter_mut()
+            .find(|s| s.entity.id == slot_id)
+            .unwrap();
+        token_slot.entity.player_entity_id = owner;
+        token_slot.color = color;
+        if owner.is_some() {
+            token_slot.state = BuildState::Build;
+            entities::set_entity_health(
+                &mut token_slot.entity,
+                TOKEN_SLOT_HEALTH,
+                TOKEN_SLOT_HEALTH,
+            );
+        }
+        self
+    }
+
+    fn add_squads(
+        &mut self,
+        owner: EntityId,
+        card: CardTemplate,
+        num_squads: usize,
+        pos: Position2D,
+        health_percent: f32,
+    ) -> &mut Self {
+        let card_id = CardId::new(card, U3);
+        let card_info = self.card_data.get_card_info_from_id(card_id.0).unwrap();
+        let max_hp = card_info.health;
+        let hp = max_hp * health_percent;
+        let num_figures = card_info.squad_size.max(1) as usize;
+
+        for i in 0..num_squads {
+            let squad_pos = Position2D {
+                x: pos.x + i as f32 * SQUAD_SPACING,
+                y: pos.y,
+            };
+            let squad_id = self.new_entity_id();
+            let figure_ids: Vec<EntityId> =
+                (0..num_figures).map(|_| self.new_entity_id()).collect();
+
+            self.squads.push(entities::new_squad(
+                squad_id,
+                owner,
+                card_id,
+                &squad_pos,
+                card_info.power_cost,
+                &figure_ids,
+                hp,
+                max_hp,
+            ));
+            // health is split evenly between all figures of a squad
+            for figure_id in figure_ids.iter() {
+                self.figures.push(entities::new_figure(
+                    *figure_id,
+                    owner,
+                    squad_id,
+                    &squad_pos,
+                    hp / num_figures as f32,
+                    max_hp / num_figures as f32,
+                ));
+            }
+        }
+        self
+    }
+
+    fn remove_squads(&mut self, owner: EntityId) -> &mut Self {
+        self.squads
+            .retain(|s| s.entity.player_entity_id != Some(owner));
+        self.figures
+            .retain(|f| f.entity.player_entity_id != Some(owner));
+        self
+    }
+
+    fn get_location(&self, name: &str) -> &LocationPosition {
+        self.locations
+            .values()
+            .find(|location| location.name == name)
+            .unwrap_or_else(|| panic!("Unknown location {name}"))
+    }
+
+    fn get_team(&self, player_id: EntityId) -> u8 {
+        self.start_state
+            .players
+            .iter()
+            .find(|p| p.entity.id == player_id)
+            .unwrap()
+            .entity
+            .team
+    }
+
+    fn new_entity_id(&mut self) -> EntityId {
+        let entity_id = EntityId(NonZeroU32::new(self.next_entity_id).unwrap());
+        self.next_entity_id += 1;
+        entity_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    #[test]
+    fn default_scenario_matches_start_state() {
+        let game_info = ScenarioBuilder::new().game_info();
+        assert_eq!(
+            location::get_location_name(&game_info.bot.start_location, &game_info),
+            "South"
+        );
+        assert_eq!(
+            location::get_location_name(&game_info.opponent.start_location, &game_info),
+            "North"
+        );
+        assert_eq!(game_info.bot.token_slots.len(), 1);
+        assert_eq!(game_info.opponent.token_slots.len(), 1);
+        assert!(game_info.bot.squads.is_empty());
+    }
+
+    #[test]
+    fn squads_have_consistent_health() {
+        let mut builder = ScenarioBuilder::new();
+        builder.opponent_squads(Dreadcharger, 3, "Center", 0.5);
+        let game_info = builder.game_info();
+
+        assert_eq!(game_info.opponent.squads.len(), 3);
+        for squad_id in builder.squad_ids(builder.opponent_id()) {
+            let (cur_hp, max_hp) = game_info.get_squad_health(&squad_id);
+            assert!(max_hp > 0.);
+            assert_eq!(cur_hp, max_hp * 0.5);
+        }
+    }
+
+    #[test]
+    fn wells_are_assigned_to_players() {
+        let mut builder = ScenarioBuilder::new();
+        builder.bot_wells("South", 2).opponent_wells("North", 1);
+        let game_info = builder.game_info();
+
+        assert_eq!(game_info.bot.power_slots.len(), 2);
+        assert_eq!(game_info.opponent.power_slots.len(), 1);
+        assert_eq!(game_info.power_slot_diff(), 1);
+    }
+
+    #[test]
+    fn freed_location_is_reported_as_destroyed() {
+        let mut builder = ScenarioBuilder::new();
+        builder.bot_wells("South", 2);
+        let mut game_info = builder.game_info();
+
+        builder.free_location("South").tick(2);
+        game_info.parse_state(builder.game_state());
+
+        assert_eq!(game_info.bot.destroyed_power_slot_ids.len(), 2);
+        assert_eq!(game_info.bot.destroyed_token_slot_ids.len(), 1);
+    }
+
+    #[test]
+    fn killed_squads_are_reported_as_dead() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .bot_squads(Forsaken, 1, "South", 1.)
+            .opponent_squads(Dreadcharger, 2, "Center", 1.);
+        let mut game_info = builder.game_info();
+
+        builder.kill_opponent_squads().tick(2);
+        game_info.parse_state(builder.game_state());
+        assert_eq!(game_info.opponent.dead_squad_ids.len(), 2);
+        assert!(game_info.opponent.squads.is_empty());
+
+        builder.kill_bot_squads().tick(3);
+        game_info.parse_state(builder.game_state());
+        assert_eq!(game_info.bot.dead_squad_ids.len(), 1);
+    }
+
+    #[test]
+    fn orbs_power_and_time() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .bot_orb("East", OrbColor::Frost)
+            .bot_power(300.)
+            .opponent_power(50.)
+            .seconds(200);
+        let game_info = builder.game_info();
+
+        assert_eq!(game_info.token_slot_diff(), 1);
+        assert_eq!(game_info.bot.power, 300.);
+        assert_eq!(game_info.opponent.power, 50.);
+        assert!(game_info.seconds_have_passed(180));
+    }
+}
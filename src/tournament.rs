@@ -0,0 +1,280 @@
+use api::warp_wrapper::BotImpl;
+use api::*;
+use log::*;
+use std::collections::BTreeMap;
+
+use crate::bot::SkylordsRebot;
+use crate::game_info::GameInfo;
+use crate::location;
+use crate::rush_bot::RushBot;
+use crate::simulator::{self, Simulator};
+use crate::BotImplementations;
+
+const INITIAL_ELO: f32 = 1500.;
+const ELO_K_FACTOR: f32 = 32.;
+// 1 Tick = 100 ms
+const TICKS_PER_SECOND: f32 = 10.;
+
+// Object safe part of BotImpl, so bots of different types can play in the same match.
+pub trait MatchBot {
+    fn start(&mut self, state: GameStartState);
+    fn play(&mut self, state: GameState) -> Vec<Command>;
+}
+
+impl<T: BotImpl> MatchBot for T {
+    fn start(&mut self, state: GameStartState) {
+        self.match_start(state)
+    }
+
+    fn play(&mut self, state: GameState) -> Vec<Command> {
+        self.tick(state)
+    }
+}
+
+// opponent which never sends any commands
+pub struct IdleBot;
+
+impl MatchBot for IdleBot {
+    fn start(&mut self, _state: GameStartState) {}
+
+    fn play(&mut self, _state: GameState) -> Vec<Command> {
+        vec![]
+    }
+}
+
+pub struct MatchPlayer {
+    pub bot: Box<dyn MatchBot>,
+    pub deck: &'static Deck,
+}
+
+fn create_bot<T: BotImpl + 'static>(map_info: &MapInfo) -> MatchPlayer {
+    let deck = &T::decks_for_map(map_info)[0];
+    MatchPlayer {
+        bot: Box::new(T::prepare_for_battle(map_info, deck)),
+        deck,
+    }
+}
+
+pub fn create_implementation(
+    implementation: BotImplementations,
+    map_info: &MapInfo,
+) -> MatchPlayer {
+    match implementation {
+        BotImplementations::SkylordsRebot => create_bot::<SkylordsRebot>(map_info),
+        BotImplementations::RushBot => create_bot::<RushBot>(map_info),
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchResult {
+    // None for a draw
+    pub winner: Option<BotImplementations>,
+    pub ticks: u32,
+}
+
+#[derive(Debug)]
+pub struct MatchOutcome {
+    // index of the winning player in the start state, None for a draw
+    pub winner: Option<usize>,
+    pub ticks: u32,
+    // number of structures of each player at the end of the match
+    pub structures: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct TournamentStats {
+    matches: u32,
+    wins: u32,
+    draws: u32,
+    ticks: u32,
+    elo: f32,
+}
+
+pub fn get_start_location_names(start_state: &GameStartState, map_info: &MapInfo) -> Vec<String> {
+    // start locations are derived the same way the bot does it
+    start_state
+        .players
+        .iter()
+        .map(|player| {
+            let mut player_start_state = start_state.clone();
+            player_start_state.your_player_id = player.entity.id;
+            let mut game_info = GameInfo::new();
+            game_info.set_map(map_info);
+            game_info.init(player_start_state);
+            location::get_location_name(&game_info.bot.start_location, &game_info).to_string()
+        })
+        .collect()
+}
+
+// players are in the same order as in the start state
+pub fn play_match(
+    start_state: &GameStartState,
+    players: Vec<MatchPlayer>,
+    seed: u64,
+    max_ticks: u32,
+) -> MatchOutcome {
+    assert_eq!(
+        players.len(),
+        start_state.players.len(),
+        "Every player of the start state needs a bot"
+    );
+
+    let mut simulator = Simulator::new(start_state.clone(), seed);
+    let mut bots = vec![];
+
+    for (mut player, match_player) in players.into_iter().zip(start_state.players.iter()) {
+        let player_id = match_player.entity.id;
+        simulator.set_deck(player_id, player.deck);
+
+        let mut player_start_state = start_state.clone();
+        player_start_state.your_player_id = player_id;
+        player.bot.start(player_start_state);
+
+        bots.push((player_id, player.bot));
+    }
+
+    while simulator.current_tick() <= max_ticks && !simulator.is_finished() {
+        let state = simulator.game_state();
+        for (player_id, bot) in bots.iter_mut() {
+            let commands = bot.play(state.clone());
+            simulator.apply_commands(*player_id, commands);
+        }
+        simulator.step();
+    }
+
+    let winner_id = simulator.winner();
+    MatchOutcome {
+        winner: bots
+            .iter()
+            .position(|(player_id, _)| Some(*player_id) == winner_id),
+        ticks: simulator.current_tick(),
+        structures: bots
+            .iter()
+            .map(|(player_id, _)| simulator.num_structures(*player_id))
+            .collect(),
+    }
+}
+
+pub fn run_match(
+    start_state: &GameStartState,
+    map_info: &MapInfo,
+    implementations: &[BotImplementations],
+    seed: u64,
+    max_ticks: u32,
+) -> MatchResult {
+    let players = implementations
+        .iter()
+        .map(|&implementation| create_implementation(implementation, map_info))
+        .collect();
+    let outcome = play_match(start_state, players, seed, max_ticks);
+
+    MatchResult {
+        winner: outcome.winner.map(|index| implementations[index]),
+        ticks: outcome.ticks,
+    }
+}
+
+pub fn run_tournament(
+    implementations: Vec<BotImplementations>,
+    num_matches: u32,
+    start_state_path: &str,
+    prepare_path: &str,
+    max_ticks: u32,
+) {
+    let start_state = simulator::load_start_state(start_state_path);
+    let map_info = simulator::load_map_info(prepare_path);
+    let start_locations = get_start_location_names(&start_state, &map_info);
+
+    let mut stats: BTreeMap<BotImplementations, TournamentStats> = implementations
+        .iter()
+        .map(|&implementation| {
+            (
+                implementation,
+                TournamentStats {
+                    matches: 0,
+                    wins: 0,
+                    draws: 0,
+                    ticks: 0,
+                    elo: INITIAL_ELO,
+                },
+            )
+        })
+        .collect();
+
+    for (i, &first) in implementations.iter().enumerate() {
+        for &second in implementations[(i + 1)..].iter() {
+            for seed in 0..num_matches as u64 {
+                // every pairing is played from both start locations
+                for lineup in [[first, second], [second, first]] {
+                    let result = run_match(&start_state, &map_info, &lineup, seed, max_ticks);
+                    let description = format!(
+                        "{:?} ({}) vs {:?} ({}), seed {seed}",
+                        lineup[0], start_locations[0], lineup[1], start_locations[1]
+                    );
+                    info!("{description}: {result:?}");
+                    println!(
+                        "{description}: winner {:?} after {:.0}s",
+                        result.winner,
+                        result.ticks as f32 / TICKS_PER_SECOND
+                    );
+                    update_stats(&mut stats, lineup[0], lineup[1], &result);
+                }
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "{:<16} {:>8} {:>6} {:>6} {:>6} {:>9} {:>11} {:>6}",
+        "Bot", "Matches", "Wins", "Draws", "Losses", "Win rate", "Avg length", "Elo"
+    );
+    for (implementation, s) in stats.iter() {
+        let win_rate = s.wins as f32 / s.matches.max(1) as f32;
+        let avg_length = s.ticks as f32 / s.matches.max(1) as f32 / TICKS_PER_SECOND;
+        println!(
+            "{:<16} {:>8} {:>6} {:>6} {:>6} {:>8.1}% {:>10.0}s {:>6.0}",
+            format!("{implementation:?}"),
+            s.matches,
+            s.wins,
+            s.draws,
+            s.matches - s.wins - s.draws,
+            win_rate * 100.,
+            avg_length,
+            s.elo
+        );
+    }
+}
+
+fn update_stats(
+    stats: &mut BTreeMap<BotImplementations, TournamentStats>,
+    first: BotImplementations,
+    second: BotImplementations,
+    result: &MatchResult,
+) {
+    // 1 for a win, 0.5 for a draw and 0 for a loss
+    let first_score = match result.winner {
+        Some(winner) if winner == first => 1.,
+        Some(_) => 0.,
+        None => 0.5,
+    };
+
+    let first_elo = stats[&first].elo;
+    let second_elo = stats[&second].elo;
+    let first_expected = 1. / (1. + 10_f32.powf((second_elo - first_elo) / 400.));
+    let elo_change = ELO_K_FACTOR * (first_score - first_expected);
+
+    for (implementation, score, change) in [
+        (first, first_score, elo_change),
+        (second, 1. - first_score, -elo_change),
+    ] {
+        let s = stats.get_mut(&implementation).unwrap();
+        s.matches += 1;
+        s.ticks += result.ticks;
+        s.elo += change;
+        if score == 1. {
+            s.wins += 1;
+        } else if score == 0.5 {
+            s.draws += 1;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use api::*;
+use log::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs;
+
+use crate::bot::{self, SkylordsRebot};
+use crate::deck;
+use crate::simulator;
+use crate::strategy_config::StrategyConfig;
+use crate::tournament::{self, IdleBot, MatchOutcome, MatchPlayer};
+use crate::BotImplementations;
+
+// share of the parameter range a value is moved at most when creating a candidate
+const MAX_STEP_RANGE_SHARE: f32 = 0.2;
+// probability of each parameter to be changed when creating a candidate
+const MUTATION_PROBABILITY: f64 = 0.5;
+// max structure difference that is taken into account when scoring a draw
+const MAX_DRAW_STRUCTURE_DIFF: f32 = 5.;
+
+struct TunableParameter {
+    name: &'static str,
+    min: f32,
+    max: f32,
+    integer: bool,
+    get: fn(&StrategyConfig) -> f32,
+    set: fn(&mut StrategyConfig, f32),
+}
+
+const PARAMETERS: [TunableParameter; 16] = [
+    TunableParameter {
+        name: "macro_controller.control_area_aggro_radius",
+        min: 20.,
+        max: 120.,
+        integer: false,
+        get: |c| c.macro_controller.control_area_aggro_radius,
+        set: |c, v| c.macro_controller.control_area_aggro_radius = v,
+    },
+    TunableParameter {
+        name: "macro_controller.min_power_build_well",
+        min: 100.,
+        max: 400.,
+        integer: false,
+        get: |c| c.macro_controller.min_power_build_well,
+        set: |c, v| c.macro_controller.min_power_build_well = v,
+    },
+    TunableParameter {
+        name: "macro_controller.min_tempo_diff_advantage",
+        min: -100.,
+        max: 100.,
+        integer: false,
+        get: |c| c.macro_controller.min_tempo_diff_advantage,
+        set: |c, v| c.macro_controller.min_tempo_diff_advantage = v,
+    },
+    TunableParameter {
+        name: "macro_controller.num_squads_critical_mass",
+        min: 2.,
+        max: 12.,
+        integer: true,
+        get: |c| c.macro_controller.num_squads_critical_mass as f32,
+        set: |c, v| c.macro_controller.num_squads_critical_mass = v as i32,
+    },
+    TunableParameter {
+        name: "macro_controller.advance_t2_seconds",
+        min: 60.,
+        max: 360.,
+        integer: true,
+        get: |c| c.macro_controller.advance_t2_seconds as f32,
+        set: |c, v| c.macro_controller.advance_t2_seconds = v as u32,
+    },
+    TunableParameter {
+        name: "macro_controller.advance_t3_seconds",
+        min: 240.,
+        max: 720.,
+        integer: true,
+        get: |c| c.macro_controller.advance_t3_seconds as f32,
+        set: |c, v| c.macro_controller.advance_t3_seconds = v as u32,
+    },
+    TunableParameter {
+        name: "macro_controller.defend_min_power_advance_t2",
+        min: 150.,
+        max: 400.,
+        integer: false,
+        get: |c| c.macro_controller.defend_min_power_advance_t2,
+        set: |c, v| c.macro_controller.defend_min_power_advance_t2 = v,
+    },
+    TunableParameter {
+        name: "macro_controller.defend_min_power_advance_t3",
+        min: 250.,
+        max: 500.,
+        integer: false,
+        get: |c| c.macro_controller.defend_min_power_advance_t3,
+        set: |c, v| c.macro_controller.defend_min_power_advance_t3 = v,
+    },
+    TunableParameter {
+        name: "macro_controller.defend_max_unspent_power",
+        min: 100.,
+        max: 600.,
+        integer: false,
+        get: |c| c.macro_controller.defend_max_unspent_power,
+        set: |c, v| c.macro_controller.defend_max_unspent_power = v,
+    },
+    TunableParameter {
+        name: "macro_controller.attack_start_location_seconds",
+        min: 120.,
+        max: 900.,
+        integer: true,
+        get: |c| c.macro_controller.attack_start_location_seconds as f32,
+        set: |c, v| c.macro_controller.attack_start_location_seconds = v as u32,
+    },
+    TunableParameter {
+        name: "macro_controller.min_strength_ratio_retreat",
+        min: 0.2,
+        max: 1.,
+        integer: false,
+        get: |c| c.macro_controller.min_strength_ratio_retreat,
+        set: |c, v| c.macro_controller.min_strength_ratio_retreat = v,
+    },
+    TunableParameter {
+        name: "location_priorities.start_proximity_weight_ahead",
+        min: 0.,
+        max: 5.,
+        integer: false,
+        get: |c| c.location_priorities.start_proximity_weight_ahead,
+        set: |c, v| c.location_priorities.start_proximity_weight_ahead = v,
+    },
+    TunableParameter {
+        name: "location_priorities.start_proximity_weight_behind",
+        min: 0.,
+        max: 10.,
+        integer: false,
+        get: |c| c.location_priorities.start_proximity_weight_behind,
+        set: |c, v| c.location_priorities.start_proximity_weight_behind = v,
+    },
+    TunableParameter {
+        name: "location_priorities.power_slot_weight",
+        min: 0.,
+        max: 2.,
+        integer: false,
+        get: |c| c.location_priorities.power_slot_weight,
+        set: |c, v| c.location_priorities.power_slot_weight = v,
+    },
+    TunableParameter {
+        name: "location_priorities.enemy_strength_weight",
+        min: -3.,
+        max: 0.,
+        integer: false,
+        get: |c| c.location_priorities.enemy_strength_weight,
+        set: |c, v| c.location_priorities.enemy_strength_weight = v,
+    },
+    TunableParameter {
+        name: "spawn_controller.min_power_diff_spawn",
+        min: 0.,
+        max: 150.,
+        integer: false,
+        get: |c| c.spawn_controller.min_power_diff_spawn,
+        set: |c, v| c.spawn_controller.min_power_diff_spawn = v,
+    },
+];
+
+// fixed pool of scripted opponents every candidate plays against
+#[derive(Debug, Clone, Copy)]
+enum Opponent {
+    Idle,
+    Scripted(BotImplementations),
+}
+
+const OPPONENTS: [Opponent; 2] = [
+    Opponent::Idle,
+    Opponent::Scripted(BotImplementations::RushBot),
+];
+
+fn create_opponent(opponent: Opponent, map_info: &MapInfo) -> MatchPlayer {
+    match opponent {
+        Opponent::Idle => MatchPlayer {
+            bot: Box::new(IdleBot),
+            deck: &deck::bot_deck().deck,
+        },
+        Opponent::Scripted(implementation) => {
+            tournament::create_implementation(implementation, map_info)
+        }
+    }
+}
+
+fn create_candidate(base: &StrategyConfig, rng: &mut StdRng) -> StrategyConfig {
+    let mut candidate = base.clone();
+    for parameter in PARAMETERS.iter() {
+        if !rng.gen_bool(MUTATION_PROBABILITY) {
+            continue;
+        }
+        let max_step = (parameter.max - parameter.min) * MAX_STEP_RANGE_SHARE;
+        let mut value = (parameter.get)(base) + rng.gen_range(-max_step..=max_step);
+        value = value.clamp(parameter.min, parameter.max);
+        if parameter.integer {
+            value = value.round();
+        }
+        (parameter.set)(&mut candidate, value);
+    }
+    candidate
+}
+
+fn score_outcome(outcome: &MatchOutcome, bot_index: usize) -> f32 {
+    // 1 for a win, 0 for a loss and between 0.25 and 0.75 for a draw depending on the
+    // difference in structures at the end of the match
+    match outcome.winner {
+        Some(winner) if winner == bot_index => 1.,
+        Some(_) => 0.,
+        None => {
+            // compared against the strongest other player
+            let own = outcome.structures[bot_index];
+            let other = outcome
+                .structures
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != bot_index)
+                .map(|(_, &num)| num)
+                .max()
+                .unwrap_or(0);
+            let structure_diff = (own as f32 - other as f32)
+                .clamp(-MAX_DRAW_STRUCTURE_DIFF, MAX_DRAW_STRUCTURE_DIFF);
+            0.5 + 0.25 * structure_diff / MAX_DRAW_STRUCTURE_DIFF
+        }
+    }
+}
+
+fn evaluate(
+    config: &StrategyConfig,
+    start_state: &GameStartState,
+    map_info: &MapInfo,
+    num_matches: u32,
+    max_ticks: u32,
+) -> f32 {
+    let mut total_score = 0.;
+    let mut num_played = 0;
+
+    for opponent in OPPONENTS {
+        for seed in 0..num_matches as u64 {
+            // the bot plays from every start location once
+            for bot_index in 0..start_state.players.len() {
+                let players = (0..start_state.players.len())
+                    .map(|index| {
+                        if index == bot_index {
+                            MatchPlayer {
+                                bot: Box::new(SkylordsRebot::with_strategy(
+                                    map_info,
+                                    &deck::bot_deck().deck,
+                                    config,
+                                )),
+                                deck: &deck::bot_deck().deck,
+                            }
+                        } else {
+                            create_opponent(opponent, map_info)
+                        }
+                    })
+                    .collect();
+
+                let outcome = tournament::play_match(start_state, players, seed, max_ticks);
+                let score = score_outcome(&outcome, bot_index);
+                debug!("{opponent:?}, player {bot_index}, seed {seed}: {outcome:?}, score {score}");
+                total_score += score;
+                num_played += 1;
+            }
+        }
+    }
+
+    total_score / num_played as f32
+}
+
+fn write_config(config: &StrategyConfig, output_path: &str) {
+    let content = serde_json::to_string_pretty(config).unwrap();
+    if let Err(err) = fs::write(output_path, content) {
+        error!("Unable to write {output_path}: {err}");
+    }
+}
+
+pub fn run_random_search(
+    num_iterations: u32,
+    num_matches: u32,
+    seed: u64,
+    start_state_path: &str,
+    prepare_path: &str,
+    max_ticks: u32,
+    output_path: &str,
+) {
+    let start_state = simulator::load_start_state(start_state_path);
+    let map_info = simulator::load_map_info(prepare_path);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // start from the currently used strategy
+    let mut best_config = bot::strategy_config();
+    let mut best_score = evaluate(
+        &best_config,
+        &start_state,
+        &map_info,
+        num_matches,
+        max_ticks,
+    );
+    println!("Initial score: {best_score:.3}");
+    write_config(&best_config, output_path);
+
+    for iteration in 1..=num_iterations {
+        let candidate = create_candidate(&best_config, &mut rng);
+        let score = evaluate(&candidate, &start_state, &map_info, num_matches, max_ticks);
+        info!("Iteration {iteration}: score {score:.3}, {candidate:?}");
+
+        // ties are accepted to keep moving on plateaus
+        if score >= best_score {
+            if score > best_score {
+                println!("Iteration {iteration}: new best score {score:.3}");
+            }
+            best_score = score;
+            best_config = candidate;
+            write_config(&best_config, output_path);
+        } else {
+            println!("Iteration {iteration}: score {score:.3} (best {best_score:.3})");
+        }
+    }
+
+    println!();
+    println!("Best score: {best_score:.3}, written to {output_path:?}");
+    let default_config = StrategyConfig::default();
+    for parameter in PARAMETERS.iter() {
+        println!(
+            "{:<50} {:>8.1} (default {:.1})",
+            parameter.name,
+            (parameter.get)(&best_config),
+            (parameter.get)(&default_config)
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use api::*;
+use log::*;
 use ordered_float::OrderedFloat;
-use std::collections::BTreeMap;
 
+use crate::card_data::{matchup, CardInfo};
 use crate::game_info::GameInfo;
 
 pub fn dist(pos1: &Position2D, pos2: &Position2D) -> f32 {
@@ -11,8 +12,8 @@ pub fn dist(pos1: &Position2D, pos2: &Position2D) -> f32 {
 pub fn threat_scores_defending(
     own_pos: &Position2D,
     target: &Squad,
-    game_info: &mut GameInfo,
-) -> (i32, OrderedFloat<f32>, OrderedFloat<f32>) {
+    game_info: &GameInfo,
+) -> (i32, OrderedFloat<f32>, OrderedFloat<f32>, OrderedFloat<f32>) {
     /*
     Return a Vector of scores which are used to sort enemy squads based on their
     threat level when defending.
@@ -21,12 +22,25 @@ pub fn threat_scores_defending(
     The lowest score means the highest threat.
 
     1. 0: siege, 1: melee, 2: ranged
-    2. 0-1: current percent health (finish low-health squads first)
-    3. 0-?: distance to own position (focus closer squads)
+    2. -5-5: negated matchup score against the own squads (focus squads which counter them)
+    3. 0-1: current percent health (finish low-health squads first)
+    4. 0-?: distance to own position (focus closer squads)
     */
-    let card_info = game_info.card_data.get_card_info_from_id(target.card_id.0);
-
-    let attack_type_score: i32 = match (card_info.siege, card_info.melee) {
+    let (siege, melee, matchup_score) =
+        match game_info.card_data.get_card_info_from_id(target.card_id.0) {
+            Ok(card_info) => (
+                card_info.siege,
+                card_info.melee,
+                get_matchup_against_own_squads(&card_info, game_info),
+            ),
+            Err(err) => {
+                // unknown squads are handled last
+                error!("{err}");
+                (false, false, 0.)
+            }
+        };
+
+    let attack_type_score: i32 = match (siege, melee) {
         (true, _) => 0,
         (false, true) => 1,
         (false, false) => 2,
@@ -39,6 +53,7 @@ pub fn threat_scores_defending(
 
     (
         attack_type_score,
+        OrderedFloat(-matchup_score),
         OrderedFloat(health_score),
         OrderedFloat(dist_score),
     )
@@ -47,8 +62,8 @@ pub fn threat_scores_defending(
 pub fn threat_scores_attacking(
     own_pos: &Position2D,
     target: &Squad,
-    game_info: &mut GameInfo,
-) -> (i32, OrderedFloat<f32>, OrderedFloat<f32>) {
+    game_info: &GameInfo,
+) -> (i32, OrderedFloat<f32>, OrderedFloat<f32>, OrderedFloat<f32>) {
     /*
     Return a Vector of scores which are used to sort enemy squads based on their
     threat level when attacking.
@@ -57,12 +72,23 @@ pub fn threat_scores_attacking(
     The lowest score means the highest threat.
 
     1. 0: melee, 2: ranged
-    2. 0-1: current percent health (finish low-health squads first)
-    3. 0-?: distance to own position (focus closer squads)
+    2. -5-5: negated matchup score against the own squads (focus squads which counter them)
+    3. 0-1: current percent health (finish low-health squads first)
+    4. 0-?: distance to own position (focus closer squads)
     */
-    let card_info = game_info.card_data.get_card_info_from_id(target.card_id.0);
+    let (melee, matchup_score) = match game_info.card_data.get_card_info_from_id(target.card_id.0) {
+        Ok(card_info) => (
+            card_info.melee,
+            get_matchup_against_own_squads(&card_info, game_info),
+        ),
+        Err(err) => {
+            // unknown squads are handled last
+            error!("{err}");
+            (false, 0.)
+        }
+    };
 
-    let melee_score: i32 = match card_info.melee {
+    let melee_score: i32 = match melee {
         true => 0,
         false => 1,
     };
@@ -74,11 +100,28 @@ pub fn threat_scores_attacking(
 
     (
         melee_score,
+        OrderedFloat(-matchup_score),
         OrderedFloat(health_score),
         OrderedFloat(dist_score),
     )
 }
 
+fn get_matchup_against_own_squads(card_info: &CardInfo, game_info: &GameInfo) -> f32 {
+    // how well the enemy squad fares against the squads of the bot
+    let own_cards: Vec<CardInfo> = game_info
+        .bot
+        .squads
+        .values()
+        .filter_map(|squad| {
+            game_info
+                .card_data
+                .get_card_info_from_id(squad.card_id.0)
+                .ok()
+        })
+        .collect();
+    matchup::get_army_matchup_score(card_info, &own_cards)
+}
+
 pub fn average_pos(positions: Vec<Position2D>) -> Position2D {
     let mut sum_x: f32 = 0.;
     let mut sum_y: f32 = 0.;
@@ -94,14 +137,54 @@ pub fn average_pos(positions: Vec<Position2D>) -> Position2D {
     }
 }
 
-pub fn most_frequent_element<T>(v: Vec<T>) -> Option<T>
-where
-    T: Ord,
-{
-    let mut counts: BTreeMap<T, usize> = BTreeMap::new();
-    for el in v {
-        counts.entry(el).and_modify(|curr| *curr += 1).or_insert(1);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+    use api::sr_libs::utils::card_templates::CardTemplate::*;
+
+    fn sorted_threats(builder: &ScenarioBuilder, own_pos: &Position2D) -> Vec<EntityId> {
+        let game_info = builder.game_info();
+        let squads: Vec<Squad> = game_info.opponent.squads.values().cloned().collect();
+        let mut threats: Vec<_> = squads
+            .iter()
+            .map(|squad| {
+                (
+                    threat_scores_defending(own_pos, squad, &game_info),
+                    squad.entity.id,
+                )
+            })
+            .collect();
+        threats.sort();
+        threats.into_iter().map(|(_, id)| id).collect()
     }
 
-    counts.into_iter().max_by_key(|(_, v)| *v).map(|(k, _)| k)
+    #[test]
+    fn defending_prefers_siege_over_melee_over_ranged() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .opponent_squads(Forsaken, 1, "South", 1.)
+            .opponent_squads(Dreadcharger, 1, "South", 1.)
+            .opponent_squads(Firedancer, 1, "South", 1.);
+        let ids = builder.squad_ids(builder.opponent_id());
+        let (forsaken, dreadcharger, firedancer) = (ids[0], ids[1], ids[2]);
+
+        let own_pos = Position2D { x: 0., y: 0. };
+        assert_eq!(
+            sorted_threats(&builder, &own_pos),
+            vec![firedancer, dreadcharger, forsaken]
+        );
+    }
+
+    #[test]
+    fn defending_prefers_damaged_squads() {
+        let mut builder = ScenarioBuilder::new();
+        builder
+            .opponent_squads(Dreadcharger, 1, "Center", 1.)
+            .opponent_squads(Dreadcharger, 1, "Center", 0.3);
+        let ids = builder.squad_ids(builder.opponent_id());
+
+        let own_pos = Position2D { x: 0., y: 0. };
+        assert_eq!(sorted_threats(&builder, &own_pos), vec![ids[1], ids[0]]);
+    }
 }
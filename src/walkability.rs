@@ -0,0 +1,452 @@
+use api::*;
+use log::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::utils;
+
+// Coarse grid of the map which knows where squads can walk and where structures stand. It is
+// built from the barriers and slots of the start state and barriers are updated every tick as
+// they are built, destroyed or removed together with their barrier set.
+
+// edge length of a grid cell
+pub const CELL_SIZE: f32 = 2.;
+// margin around the outermost known entities which is still part of the grid
+const MAP_MARGIN: f32 = 30.;
+// barrier modules are placed up to 7.5 apart in a set, so there are no gaps between them
+const BARRIER_MODULE_RADIUS: f32 = 3.75;
+// collision footprints of the slots
+const POWER_SLOT_RADIUS: f32 = 3.;
+const TOKEN_SLOT_RADIUS: f32 = 5.;
+
+#[derive(Debug)]
+pub struct WalkabilityGrid {
+    // position of the lower left corner of the first cell
+    origin: Position2D,
+    width: usize,
+    height: usize,
+    // number of non walkable barrier modules covering each cell
+    blocked: Vec<u8>,
+    // number of slot footprints covering each cell
+    occupied: Vec<u8>,
+    // last known position and walkable flag of each barrier module
+    barrier_modules: BTreeMap<EntityId, (Position2D, bool)>,
+}
+
+impl WalkabilityGrid {
+    pub fn new(
+        power_slots: &[PowerSlot],
+        token_slots: &[TokenSlot],
+        barrier_sets: &[BarrierSet],
+        barrier_modules: &[BarrierModule],
+    ) -> Self {
+        let positions: Vec<Position2D> = power_slots
+            .iter()
+            .map(|s| s.entity.position.to_2d())
+            .chain(token_slots.iter().map(|s| s.entity.position.to_2d()))
+            .chain(barrier_modules.iter().map(|m| m.entity.position.to_2d()))
+            .collect();
+
+        let mut grid = WalkabilityGrid {
+            origin: Position2D { x: 0., y: 0. },
+            width: 0,
+            height: 0,
+            blocked: vec![],
+            occupied: vec![],
+            barrier_modules: BTreeMap::new(),
+        };
+        if positions.is_empty() {
+            return grid;
+        }
+
+        let min_x = positions.iter().map(|p| p.x).fold(f32::MAX, f32::min) - MAP_MARGIN;
+        let min_y = positions.iter().map(|p| p.y).fold(f32::MAX, f32::min) - MAP_MARGIN;
+        let max_x = positions.iter().map(|p| p.x).fold(f32::MIN, f32::max) + MAP_MARGIN;
+        let max_y = positions.iter().map(|p| p.y).fold(f32::MIN, f32::max) + MAP_MARGIN;
+        grid.origin = Position2D { x: min_x, y: min_y };
+        grid.width = ((max_x - min_x) / CELL_SIZE).ceil() as usize;
+        grid.height = ((max_y - min_y) / CELL_SIZE).ceil() as usize;
+        grid.blocked = vec![0; grid.width * grid.height];
+        grid.occupied = vec![0; grid.width * grid.height];
+
+        for power_slot in power_slots.iter() {
+            let pos = power_slot.entity.position.to_2d();
+            grid.add_footprint(&pos, POWER_SLOT_RADIUS, false);
+        }
+        for token_slot in token_slots.iter() {
+            let pos = token_slot.entity.position.to_2d();
+            grid.add_footprint(&pos, TOKEN_SLOT_RADIUS, false);
+        }
+        grid.update_barriers(barrier_sets, barrier_modules);
+
+        debug!(
+            "Created walkability grid with {}x{} cells at {:?}",
+            grid.width, grid.height, grid.origin
+        );
+        grid
+    }
+
+    pub fn update_barriers(
+        &mut self,
+        barrier_sets: &[BarrierSet],
+        barrier_modules: &[BarrierModule],
+    ) {
+        // modules only exist as part of their set
+        let set_ids: BTreeSet<EntityId> = barrier_sets.iter().map(|set| set.entity.id).collect();
+        let modules: Vec<&BarrierModule> = barrier_modules
+            .iter()
+            .filter(|module| set_ids.contains(&module.set))
+            .collect();
+        if modules.len() < barrier_modules.len() {
+            debug!(
+                "Ignoring {} barrier modules without a barrier set",
+                barrier_modules.len() - modules.len()
+            );
+        }
+
+        // modules which are gone since the last update no longer block their cells
+        let module_ids: BTreeSet<EntityId> =
+            modules.iter().map(|module| module.entity.id).collect();
+        let removed_ids: Vec<EntityId> = self
+            .barrier_modules
+            .keys()
+            .filter(|id| !module_ids.contains(id))
+            .copied()
+            .collect();
+        for id in removed_ids {
+            if let Some((pos, false)) = self.barrier_modules.remove(&id) {
+                self.remove_footprint(&pos, BARRIER_MODULE_RADIUS, true);
+            }
+        }
+
+        for module in modules {
+            let pos = module.entity.position.to_2d();
+            let previous = self
+                .barrier_modules
+                .insert(module.entity.id, (pos, module.walkable));
+
+            match previous {
+                Some((_, walkable)) if walkable == module.walkable => continue,
+                Some((previous_pos, false)) => {
+                    // module was torn down
+                    self.remove_footprint(&previous_pos, BARRIER_MODULE_RADIUS, true);
+                }
+                _ => {}
+            }
+            if !module.walkable {
+                self.add_footprint(&pos, BARRIER_MODULE_RADIUS, true);
+            }
+        }
+    }
+
+    pub fn get_cell(&self, pos: &Position2D) -> Option<(usize, usize)> {
+        let x = ((pos.x - self.origin.x) / CELL_SIZE).floor();
+        let y = ((pos.y - self.origin.y) / CELL_SIZE).floor();
+        if x < 0. || y < 0. || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    pub fn get_cell_center(&self, cell: (usize, usize)) -> Position2D {
+        Position2D {
+            x: self.origin.x + (cell.0 as f32 + 0.5) * CELL_SIZE,
+            y: self.origin.y + (cell.1 as f32 + 0.5) * CELL_SIZE,
+        }
+    }
+
+    pub fn is_cell_blocked(&self, cell: (usize, usize)) -> bool {
+        self.blocked[self.index(cell)] > 0
+    }
+
+    pub fn is_cell_occupied(&self, cell: (usize, usize)) -> bool {
+        self.occupied[self.index(cell)] > 0
+    }
+
+    pub fn is_cell_walkable(&self, cell: (usize, usize)) -> bool {
+        !self.is_cell_blocked(cell) && !self.is_cell_occupied(cell)
+    }
+
+    pub fn is_blocked(&self, pos: &Position2D) -> bool {
+        // outside of the grid counts as blocked
+        self.get_cell(pos)
+            .map_or(true, |cell| self.is_cell_blocked(cell))
+    }
+
+    pub fn is_occupied(&self, pos: &Position2D) -> bool {
+        self.get_cell(pos)
+            .map_or(false, |cell| self.is_cell_occupied(cell))
+    }
+
+    pub fn is_walkable(&self, pos: &Position2D) -> bool {
+        self.get_cell(pos)
+            .map_or(false, |cell| self.is_cell_walkable(cell))
+    }
+
+    pub fn is_line_walkable(&self, from: &Position2D, to: &Position2D) -> bool {
+        // sample the line with half the cell size
+        let dist = ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt();
+        let num_steps = (dist / (CELL_SIZE / 2.)).ceil().max(1.) as usize;
+        (0..=num_steps).all(|step| {
+            let t = step as f32 / num_steps as f32;
+            let pos = Position2D {
+                x: from.x + (to.x - from.x) * t,
+                y: from.y + (to.y - from.y) * t,
+            };
+            self.is_walkable(&pos)
+        })
+    }
+
+    pub fn get_walkable_neighbors(&self, cell: (usize, usize)) -> Vec<(usize, usize)> {
+        // diagonal steps are only allowed if they don't cut a corner
+        let mut neighbors = vec![];
+        for dx in -1i32..=1 {
+            for dy in -1i32..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let Some(neighbor) = self.offset_cell(cell, dx, dy) else {
+                    continue;
+                };
+                if !self.is_cell_walkable(neighbor) {
+                    continue;
+                }
+                if dx != 0 && dy != 0 {
+                    let side_x = self.offset_cell(cell, dx, 0).unwrap();
+                    let side_y = self.offset_cell(cell, 0, dy).unwrap();
+                    if !self.is_cell_walkable(side_x) || !self.is_cell_walkable(side_y) {
+                        continue;
+                    }
+                }
+                neighbors.push(neighbor);
+            }
+        }
+        neighbors
+    }
+
+    pub fn get_closest_walkable_cell(
+        &self,
+        pos: &Position2D,
+        max_dist: f32,
+    ) -> Option<(usize, usize)> {
+        let cell = self.get_cell(pos)?;
+        if self.is_cell_walkable(cell) {
+            return Some(cell);
+        }
+
+        // search in growing rings around the cell
+        let max_ring = (max_dist / CELL_SIZE).ceil() as i32;
+        for ring in 1..=max_ring {
+            let closest = (-ring..=ring)
+                .flat_map(|dx| (-ring..=ring).map(move |dy| (dx, dy)))
+                .filter(|(dx, dy)| dx.abs() == ring || dy.abs() == ring)
+                .filter_map(|(dx, dy)| self.offset_cell(cell, dx, dy))
+                .filter(|c| self.is_cell_walkable(*c))
+                .min_by(|a, b| {
+                    let dist_a = utils::dist(pos, &self.get_cell_center(*a));
+                    let dist_b = utils::dist(pos, &self.get_cell_center(*b));
+                    dist_a.total_cmp(&dist_b)
+                });
+            if closest.is_some() {
+                return closest;
+            }
+        }
+        None
+    }
+
+    fn offset_cell(&self, cell: (usize, usize), dx: i32, dy: i32) -> Option<(usize, usize)> {
+        let x = cell.0 as i32 + dx;
+        let y = cell.1 as i32 + dy;
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    fn index(&self, cell: (usize, usize)) -> usize {
+        cell.1 * self.width + cell.0
+    }
+
+    fn get_cells_in_radius(&self, pos: &Position2D, radius: f32) -> Vec<(usize, usize)> {
+        // footprints at the edge only cover the cells inside of the grid
+        let mut cells = vec![];
+        let cell_range = |value: f32, origin: f32, size: usize| {
+            let min = ((value - radius - origin) / CELL_SIZE).floor().max(0.);
+            let max = ((value + radius - origin) / CELL_SIZE)
+                .floor()
+                .min(size as f32 - 1.);
+            (min <= max).then_some((min as usize, max as usize))
+        };
+        let x_range = cell_range(pos.x, self.origin.x, self.width);
+        let y_range = cell_range(pos.y, self.origin.y, self.height);
+        let (Some((min_x, max_x)), Some((min_y, max_y))) = (x_range, y_range) else {
+            warn!("Footprint at {pos:?} is outside of the walkability grid");
+            return cells;
+        };
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                let center = self.get_cell_center((x, y));
+                if (center.x - pos.x).powi(2) + (center.y - pos.y).powi(2) <= radius.powi(2) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    fn add_footprint(&mut self, pos: &Position2D, radius: f32, barrier: bool) {
+        for cell in self.get_cells_in_radius(pos, radius) {
+            let index = self.index(cell);
+            let counts = if barrier {
+                &mut self.blocked
+            } else {
+                &mut self.occupied
+            };
+            counts[index] = counts[index].saturating_add(1);
+        }
+    }
+
+    fn remove_footprint(&mut self, pos: &Position2D, radius: f32, barrier: bool) {
+        for cell in self.get_cells_in_radius(pos, radius) {
+            let index = self.index(cell);
+            let counts = if barrier {
+                &mut self.blocked
+            } else {
+                &mut self.occupied
+            };
+            counts[index] = counts[index].saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::ScenarioBuilder;
+
+    #[test]
+    fn barriers_and_slots_are_not_walkable() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let entities = &start_state.entities;
+        let grid = WalkabilityGrid::new(
+            &entities.power_slots,
+            &entities.token_slots,
+            &entities.barrier_sets,
+            &entities.barrier_modules,
+        );
+
+        let module = start_state
+            .entities
+            .barrier_modules
+            .iter()
+            .find(|m| !m.walkable)
+            .unwrap();
+        assert!(grid.is_blocked(&module.entity.position.to_2d()));
+        assert!(!grid.is_walkable(&module.entity.position.to_2d()));
+
+        let power_slot = &start_state.entities.power_slots[0];
+        let slot_pos = power_slot.entity.position.to_2d();
+        assert!(grid.is_occupied(&slot_pos));
+        assert!(!grid.is_blocked(&slot_pos));
+
+        // next to a slot is free
+        let free_pos = Position2D {
+            x: slot_pos.x,
+            y: slot_pos.y - 10.,
+        };
+        assert!(grid.is_walkable(&free_pos));
+    }
+
+    #[test]
+    fn barriers_are_updated() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let entities = &start_state.entities;
+        let mut grid = WalkabilityGrid::new(
+            &entities.power_slots,
+            &entities.token_slots,
+            &entities.barrier_sets,
+            &entities.barrier_modules,
+        );
+
+        let mut modules = start_state.entities.barrier_modules.clone();
+        let module = modules.iter_mut().find(|m| !m.walkable).unwrap();
+        let module_pos = module.entity.position.to_2d();
+        assert!(grid.is_blocked(&module_pos));
+
+        // module is destroyed
+        module.walkable = true;
+        grid.update_barriers(&entities.barrier_sets, &modules);
+        assert!(!grid.is_blocked(&module_pos));
+
+        modules.iter_mut().for_each(|m| m.walkable = false);
+        grid.update_barriers(&entities.barrier_sets, &modules);
+        assert!(grid.is_blocked(&module_pos));
+    }
+
+    #[test]
+    fn removed_barriers_are_walkable() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let entities = &start_state.entities;
+        let mut grid = WalkabilityGrid::new(
+            &entities.power_slots,
+            &entities.token_slots,
+            &entities.barrier_sets,
+            &entities.barrier_modules,
+        );
+        let module = entities
+            .barrier_modules
+            .iter()
+            .find(|m| !m.walkable)
+            .unwrap();
+        let module_pos = module.entity.position.to_2d();
+        assert!(grid.is_blocked(&module_pos));
+
+        // every module close enough to cover the position is dropped from the list
+        let mut modules: Vec<BarrierModule> = entities
+            .barrier_modules
+            .iter()
+            .filter(|m| {
+                utils::dist(&m.entity.position.to_2d(), &module_pos) > 2. * BARRIER_MODULE_RADIUS
+            })
+            .cloned()
+            .collect();
+        grid.update_barriers(&entities.barrier_sets, &modules);
+        assert!(!grid.is_blocked(&module_pos));
+
+        // the module is back
+        modules.push(module.clone());
+        grid.update_barriers(&entities.barrier_sets, &modules);
+        assert!(grid.is_blocked(&module_pos));
+
+        // the whole set is removed
+        let sets: Vec<BarrierSet> = entities
+            .barrier_sets
+            .iter()
+            .filter(|set| set.entity.id != module.set)
+            .cloned()
+            .collect();
+        grid.update_barriers(&sets, &entities.barrier_modules);
+        assert!(!grid.is_blocked(&module_pos));
+    }
+
+    #[test]
+    fn footprints_at_the_edge_are_clamped() {
+        let start_state = ScenarioBuilder::new().start_state();
+        let entities = &start_state.entities;
+        let mut grid = WalkabilityGrid::new(
+            &entities.power_slots,
+            &entities.token_slots,
+            &entities.barrier_sets,
+            &[],
+        );
+
+        // module in the lower left corner, its footprint reaches over the edge of the grid
+        let mut module = entities.barrier_modules[0].clone();
+        module.walkable = false;
+        module.entity.position.x = grid.origin.x + 1.;
+        module.entity.position.z = grid.origin.y + 1.;
+        grid.update_barriers(&entities.barrier_sets, &[module]);
+        assert!(grid.is_cell_blocked((0, 0)));
+        assert!(!grid.is_cell_walkable((0, 0)));
+    }
+}